@@ -1,26 +1,40 @@
 use hidapi::HidApi;
 use std::{
+    path::PathBuf,
     sync::{self, Arc, Mutex, mpsc},
     thread::{self, sleep},
     time::{Duration, Instant},
 };
 
 use crate::{
+    audio_reactive::{AudioReactive, ReactiveConfig},
+    audio_visualizer::VisualizerConfig,
     backend::{ControllerBackend, DirectBackend, IpcBackend, TRIGGER_OFF},
     common::*,
     daemon::DaemonManager,
-    dualsense::{self, BatteryInfo, DualSense},
+    dualsense::{self, BatteryInfo, DualSense, HAPTICS_SAMPLE_RATE},
     firmware_controller::FirmwareController,
+    haptic_clip::{CLIP_EXTENSIONS, ClipPlayer, HapticClip},
+    haptic_synth::{CustomPattern, PatternLibrary, RumbleHaptics},
+    haptics_stream::HapticSource,
     input_poller::InputPoller,
-    ipc::{IpcClient, socket_path},
+    ipc::{
+        ControllerSummary, DEFAULT_INPUT_RATE_HZ, DaemonEvent, EventMessage, EventTopic, IpcClient,
+        socket_path,
+    },
+    lightbar_anim::{AnimationLibrary, LightbarAnimation},
+    player_leds::PlayerLedConfig,
     profiles::{Profile, ProfileManager, TriggerConfig},
     settings::{Settings, SettingsManager},
     state::*,
     theme::{Theme, ThemeManager},
     transform::{GyroProcessor, InputTransform},
+    trigger_presets::{PresetEffect, TriggerPreset, TriggerPresetLibrary},
     util::mlock,
 };
 
+const TRIGGER_PREVIEW: Duration = Duration::from_secs(5);
+
 pub(crate) struct DS4UApp {
     pub(crate) settings: Settings,
     pub(crate) settings_manager: SettingsManager,
@@ -40,6 +54,9 @@ pub(crate) struct DS4UApp {
     pub(crate) controller_product_id: Option<u16>,
 
     pub(crate) profile_manager: ProfileManager,
+    pub(crate) pattern_library: PatternLibrary,
+    pub(crate) animation_library: AnimationLibrary,
+    pub(crate) trigger_presets: TriggerPresetLibrary,
     pub(crate) current_profile: Option<Profile>,
     pub(crate) profile_edit_name: String,
 
@@ -49,7 +66,7 @@ pub(crate) struct DS4UApp {
     pub(crate) last_battery_update: Instant,
 
     pub(crate) lightbar: LightbarState,
-    pub(crate) player_leds: u8,
+    pub(crate) player_led: PlayerLedConfig,
     pub(crate) microphone: MicrophoneState,
     pub(crate) triggers: TriggersState,
     pub(crate) sticks: StickSettings,
@@ -65,6 +82,7 @@ pub(crate) struct DS4UApp {
     pub(crate) egui_ctx: Option<egui::Context>,
 
     pub(crate) controller_serial: Option<String>,
+    pub(crate) controllers: Vec<ControllerSummary>,
 
     pub(crate) status_message: String,
     pub(crate) error_message: String,
@@ -108,6 +126,9 @@ impl DS4UApp {
             controller_product_id: None,
 
             profile_manager: ProfileManager::new(),
+            pattern_library: PatternLibrary::new(),
+            animation_library: AnimationLibrary::new(),
+            trigger_presets: TriggerPresetLibrary::new(),
             current_profile: None,
             profile_edit_name: String::new(),
 
@@ -122,18 +143,34 @@ impl DS4UApp {
                 b: 1.0,
                 brightness: 255.0,
                 enabled: true,
+                animation: None,
+                animations: Vec::new(),
+                animation_key: 0,
+                animation_started: Instant::now(),
+                bindings: Vec::new(),
+                metric: None,
+                visualizer: VisualizerConfig::default(),
+                capture_devices: Vec::new(),
             },
 
-            player_leds: 1,
+            player_led: PlayerLedConfig::default(),
 
             microphone: MicrophoneState {
                 enabled: false,
                 led_state: MicLedState::Off,
+                mixers: Vec::new(),
+                mute_elements: Vec::new(),
             },
 
             triggers: TriggersState {
                 left: TriggerConfig::default(),
                 right: TriggerConfig::default(),
+                left_program: None,
+                right_program: None,
+                presets: Vec::new(),
+                selected_preset: None,
+                preset_name: String::new(),
+                preview_until: None,
             },
 
             sticks: StickSettings {
@@ -151,13 +188,16 @@ impl DS4UApp {
             },
 
             audio: AudioSettings {
-                volume: 0,
+                levels: AudioLevels::default(),
+                levels_polled: Instant::now(),
                 speaker_mode: SpeakerMode::Internal,
+                routing: None,
             },
 
             vibration: VibrationSettings {
-                rumble: 0,
                 trigger: 0,
+                rumble_scale: 1.0,
+                rumble_haptics: RumbleHaptics::default(),
             },
 
             gyro: GyroState {
@@ -176,6 +216,17 @@ impl DS4UApp {
                 pattern: HapticPattern::None,
                 strength: 0,
                 speed: 1.0,
+                balance: 0.0,
+                custom: None,
+                custom_library: Vec::new(),
+                edit_channel: 0,
+                clip_path: None,
+                clip: None,
+                clip_gain: 1.0,
+                clip_balance: 0.0,
+                clip_loop: false,
+                reactive: ReactiveConfig::default(),
+                capture_devices: Vec::new(),
             },
 
             firmware: FirmwareController::new(),
@@ -184,6 +235,7 @@ impl DS4UApp {
             egui_ctx: None,
 
             controller_serial: None,
+            controllers: Vec::new(),
 
             status_message: String::new(),
             error_message: String::new(),
@@ -202,6 +254,8 @@ impl DS4UApp {
             daemon_raw_active: false,
         };
 
+        app.refresh_trigger_presets();
+        app.refresh_lightbar_animations();
         app.check_for_controller();
 
         {
@@ -252,16 +306,35 @@ impl DS4UApp {
 
         let handle = if self.ipc.is_some() {
             let path = socket_path();
+            let topics = [
+                EventTopic::Input,
+                EventTopic::Battery,
+                EventTopic::Connection,
+                EventTopic::Profile,
+            ];
+            let target = self.controller_serial.clone();
+            let events = match IpcClient::connect(&path).and_then(|mut c| {
+                c.set_target(target);
+                c.subscribe(&topics, DEFAULT_INPUT_RATE_HZ)
+            }) {
+                Ok(e) => e,
+                Err(_) => return,
+            };
+            let (event_tx, event_rx) = mpsc::channel();
+            self.input.event_rx = Some(event_rx);
+            self.input.closer = events.closer().ok();
             thread::spawn(move || {
-                let mut client = match IpcClient::connect(&path) {
-                    Ok(c) => c,
-                    Err(_) => return,
-                };
                 let mut last_b = u32::MAX;
                 let mut last_d = 0xFFu8;
-                while !stop_clone.load(sync::atomic::Ordering::Relaxed) {
-                    match client.get_input_state() {
-                        Ok(state) => {
+                for event in events {
+                    if stop_clone.load(sync::atomic::Ordering::Relaxed) {
+                        return;
+                    }
+                    match event {
+                        Ok(EventMessage {
+                            event: DaemonEvent::Input(state),
+                            ..
+                        }) => {
                             let (b, d, ry) = (state.buttons, state.dpad, state.right_y);
                             let _ = tx.send(state);
                             if let Some(ctx) = &waker {
@@ -273,9 +346,22 @@ impl DS4UApp {
                             last_b = b;
                             last_d = d;
                         }
-                        Err(_) => {
-                            sleep(Duration::from_millis(8));
+                        Ok(event) => {
+                            let _ = event_tx.send(event);
+                            if let Some(ctx) = &waker {
+                                ctx.request_repaint();
+                            }
                         }
+                        Err(_) => break,
+                    }
+                }
+                if !stop_clone.load(sync::atomic::Ordering::Relaxed) {
+                    let _ = event_tx.send(EventMessage {
+                        serial: None,
+                        event: DaemonEvent::Disconnected,
+                    });
+                    if let Some(ctx) = &waker {
+                        ctx.request_repaint();
                     }
                 }
             })
@@ -323,6 +409,55 @@ impl DS4UApp {
         self.input.stop();
     }
 
+    pub(crate) fn subscribed(&self) -> bool {
+        self.input.event_rx.is_some()
+    }
+
+    pub(crate) fn handle_daemon_events(&mut self) {
+        let events: Vec<EventMessage> = self
+            .input
+            .event_rx
+            .as_ref()
+            .map(|rx| rx.try_iter().collect())
+            .unwrap_or_default();
+
+        for EventMessage { serial, event } in events {
+            if matches!(
+                event,
+                DaemonEvent::Connected { .. } | DaemonEvent::Disconnected
+            ) {
+                self.refresh_controller_list();
+            }
+
+            let ours = serial.is_none() || serial == self.controller_serial;
+            if !ours {
+                continue;
+            }
+
+            match event {
+                DaemonEvent::Battery(info) => {
+                    self.battery_info = Some(info);
+                    self.last_battery_update = Instant::now();
+                }
+                DaemonEvent::Disconnected => {
+                    if !self.firmware.updating {
+                        self.stop_input_polling();
+                        self.disconnect_controller();
+                    }
+                    return;
+                }
+                DaemonEvent::ProfileSwitched { name } => {
+                    let current = self.current_profile.as_ref().map(|p| p.name.as_str());
+                    if current != Some(name.as_str()) {
+                        self.adopt_bound_profile(&name);
+                        self.status_message = format!("Daemon switched to '{}'", name);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
     fn connect_controller(&mut self) {
         match DualSense::new(&self.api, None) {
             Ok(ds) => {
@@ -336,6 +471,9 @@ impl DS4UApp {
                     self.firmware.build_time = None;
                 }
 
+                let bound = self.settings.profile_for(ds.serial()).to_string();
+                self.adopt_bound_profile(&bound);
+
                 self.controller_serial = Some(ds.serial().to_string());
                 self.controller_is_bt = Some(ds.is_bluetooth());
                 self.controller_product_id = Some(ds.product_id());
@@ -348,8 +486,11 @@ impl DS4UApp {
                 self.apply_lightbar();
                 self.apply_player_leds();
                 self.apply_microphone();
+                self.apply_audio_levels();
+                self.apply_speaker();
                 self.apply_input_transform();
                 self.apply_triggers();
+                self.apply_audio_reactive();
             }
             Err(_) => {
                 self.controller = None;
@@ -361,10 +502,12 @@ impl DS4UApp {
         let mut c = mlock(&client);
 
         if let Ok(Some((serial, pid, is_bt))) = c.get_controller_info() {
+            c.set_target(Some(serial.clone()));
             self.controller_serial = Some(serial);
             self.controller_is_bt = Some(is_bt);
             self.controller_product_id = Some(pid);
         }
+        let active_profile = c.get_active_profile().unwrap_or_default();
 
         if let Ok((ver, date, time)) = c.get_firmware_info() {
             self.firmware.current_version = Some(ver);
@@ -372,8 +515,11 @@ impl DS4UApp {
             self.firmware.build_time = Some(time);
         }
 
+        self.controllers = c.list_controllers().unwrap_or_default();
         drop(c);
 
+        self.adopt_bound_profile(&active_profile);
+
         self.firmware.latest_version = None;
         self.ipc = Some(client);
         self.status_message = "Controller connected (via daemon)".to_string();
@@ -383,14 +529,57 @@ impl DS4UApp {
         self.apply_lightbar();
         self.apply_player_leds();
         self.apply_microphone();
+        self.apply_audio_levels();
+        self.apply_speaker();
         self.apply_input_transform();
         self.apply_triggers();
         self.apply_gyro();
         self.apply_haptic_pattern();
+        self.apply_audio_reactive();
+        self.apply_rumble_scale();
+        self.apply_rumble_haptics();
+    }
+
+    pub(crate) fn refresh_controller_list(&mut self) {
+        self.controllers = match &self.ipc {
+            Some(ipc) => mlock(ipc).list_controllers().unwrap_or_default(),
+            None => Vec::new(),
+        };
+    }
+
+    pub(crate) fn assigned_player(&self) -> Option<u8> {
+        let serial = self.controller_serial.as_deref()?;
+        self.controllers
+            .iter()
+            .find(|c| c.serial == serial)
+            .map(|c| c.player)
+            .filter(|p| *p != 0)
+    }
+
+    pub(crate) fn reassign_players(&mut self) {
+        self.settings_manager.save(&self.settings);
+        if let Some(ipc) = self.ipc.clone() {
+            let _ = mlock(&ipc).reassign_players();
+        }
+        self.refresh_controller_list();
+    }
+
+    pub(crate) fn select_controller(&mut self, serial: &str) {
+        let Some(ipc) = self.ipc.clone() else {
+            return;
+        };
+        if self.controller_serial.as_deref() == Some(serial) {
+            return;
+        }
+
+        self.stop_input_polling();
+        mlock(&ipc).set_target(Some(serial.to_string()));
+        self.connect_via_daemon(ipc);
     }
 
     fn disconnect_controller(&mut self) {
         self.controller = None;
+        self.controllers.clear();
         self.battery_info = None;
         self.ipc = None;
         self.controller_is_bt = None;
@@ -402,7 +591,7 @@ impl DS4UApp {
     pub(crate) fn update_battery(&mut self) {
         self.last_battery_update = Instant::now();
 
-        if self.firmware.updating {
+        if self.firmware.updating || self.subscribed() {
             return;
         }
 
@@ -444,6 +633,9 @@ impl DS4UApp {
             match update {
                 ProgressUpdate::Progress(p) => {
                     self.firmware.progress = p;
+                    if self.firmware.used_daemon {
+                        self.daemon_manager.report_firmware_progress(p);
+                    }
                 }
                 ProgressUpdate::Status(s) => {
                     self.firmware.status = s;
@@ -493,16 +685,30 @@ impl DS4UApp {
             || self.daemon_raw_active
     }
 
+    fn sound_card(&self) -> Option<u32> {
+        let serial = self.controller_serial.as_deref()?;
+        let info = self
+            .api
+            .device_list()
+            .find(|info| info.vendor_id() == DS_VID && info.serial_number() == Some(serial))?;
+        crate::usb_haptics::find_card_for_hid(&info.path().to_string_lossy())
+    }
+
     pub(crate) fn start_raw_haptics(&mut self) {
         let pattern = self.haptic_state.pattern;
         let strength = self.haptic_state.strength;
         let speed = self.haptic_state.speed;
+        let balance = self.haptic_state.balance;
+        let custom = self.custom_pattern();
         self.error_message.clear();
         match self.controller_is_bt {
             Some(true) => {
                 if let Some(ctrl) = self.controller.clone() {
                     self.stop_input_polling();
-                    self.haptic_stream.start(ctrl, pattern, strength, speed);
+                    self.haptic_stream.start(
+                        ctrl,
+                        HapticSource::pattern(pattern, strength, speed, balance, custom),
+                    );
                     self.status_message = "Streaming raw haptics (Bluetooth)".to_string();
                 } else if let Some(ipc) = self.ipc.clone() {
                     match mlock(&ipc).set_raw_haptics(true) {
@@ -518,12 +724,15 @@ impl DS4UApp {
                 }
             }
             Some(false) => {
-                let Some(card) = crate::usb_haptics::find_dualsense_card() else {
+                let Some(card) = self.sound_card() else {
                     self.error_message =
                         "Couldn't find the controller's USB audio device".to_string();
                     return;
                 };
-                let _ = self.usb_haptic_stream.start(card, pattern, strength, speed);
+                self.usb_haptic_stream.start(
+                    card,
+                    HapticSource::pattern(pattern, strength, speed, balance, custom),
+                );
                 self.status_message = "Streaming raw haptics (USB)".to_string();
             }
             None => {
@@ -548,8 +757,217 @@ impl DS4UApp {
         let p = self.haptic_state.pattern;
         let s = self.haptic_state.strength;
         let sp = self.haptic_state.speed;
-        self.haptic_stream.set_params(p, s, sp);
-        self.usb_haptic_stream.set_params(p, s, sp);
+        let b = self.haptic_state.balance;
+        let custom = self.custom_pattern();
+        self.haptic_stream.set_params(p, s, sp, b, custom.clone());
+        self.usb_haptic_stream.set_params(p, s, sp, b, custom);
+    }
+
+    fn custom_pattern(&self) -> Option<Arc<CustomPattern>> {
+        self.haptic_state.custom.clone().map(Arc::new)
+    }
+
+    pub(crate) fn refresh_pattern_library(&mut self) {
+        self.haptic_state.custom_library = self.pattern_library.list();
+    }
+
+    pub(crate) fn new_custom_pattern(&mut self) {
+        let mut n = self.haptic_state.custom_library.len() + 1;
+        let name = loop {
+            let name = format!("Pattern{}", n);
+            if !self.haptic_state.custom_library.contains(&name) {
+                break name;
+            }
+            n += 1;
+        };
+        self.haptic_state.custom = Some(CustomPattern::new(&name));
+        self.haptic_state.edit_channel = 0;
+        self.save_custom_pattern();
+    }
+
+    pub(crate) fn select_custom_pattern(&mut self, name: &str) {
+        match self.pattern_library.load(name) {
+            Ok(p) => {
+                self.haptic_state.custom = Some(p);
+                self.apply_haptic_pattern();
+                self.update_raw_haptics();
+                self.sync_profile();
+            }
+            Err(e) => self.error_message = e.to_string(),
+        }
+    }
+
+    pub(crate) fn save_custom_pattern(&mut self) {
+        let Some(p) = self.haptic_state.custom.clone() else {
+            return;
+        };
+        match self.pattern_library.save(&p) {
+            Ok(()) => {
+                self.status_message = format!("Saved haptic pattern '{}'", p.name);
+                self.refresh_pattern_library();
+                self.sync_profile();
+            }
+            Err(e) => self.error_message = e.to_string(),
+        }
+    }
+
+    pub(crate) fn delete_custom_pattern(&mut self) {
+        let Some(p) = self.haptic_state.custom.take() else {
+            return;
+        };
+        if let Err(e) = self.pattern_library.delete(&p.name) {
+            self.error_message = e.to_string();
+        }
+        self.refresh_pattern_library();
+        self.apply_haptic_pattern();
+        self.update_raw_haptics();
+        self.sync_profile();
+    }
+
+    pub(crate) fn open_haptic_clip(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Select audio clip")
+            .add_filter("Audio", CLIP_EXTENSIONS)
+            .pick_file()
+        else {
+            return;
+        };
+
+        match HapticClip::load(&path) {
+            Ok(clip) => {
+                self.status_message = format!("Loaded {}", clip.name);
+                self.haptic_state.clip = Some(Arc::new(clip));
+                self.haptic_state.clip_path = Some(path);
+            }
+            Err(e) => self.error_message = format!("Failed to load clip: {}", e),
+        }
+    }
+
+    pub(crate) fn play_haptic_clip(&mut self) {
+        let (Some(path), Some(clip)) = (
+            self.haptic_state.clip_path.clone(),
+            self.haptic_state.clip.clone(),
+        ) else {
+            return;
+        };
+        let gain = self.haptic_state.clip_gain;
+        let balance = self.haptic_state.clip_balance;
+        let looping = self.haptic_state.clip_loop;
+        self.error_message.clear();
+
+        if let Some(ipc) = self.ipc.clone() {
+            match mlock(&ipc).play_haptic_clip(&path.to_string_lossy(), gain, balance, looping) {
+                Ok(()) => self.status_message = format!("Playing {}", clip.name),
+                Err(e) => self.error_message = format!("Clip playback failed: {e}"),
+            }
+            return;
+        }
+
+        match self.controller_is_bt {
+            Some(true) => {
+                let Some(ctrl) = self.controller.clone() else {
+                    self.error_message = "No controller connected".to_string();
+                    return;
+                };
+                self.stop_input_polling();
+                let player = ClipPlayer::new(&clip, HAPTICS_SAMPLE_RATE, gain, balance, looping);
+                self.haptic_stream.start(ctrl, HapticSource::Clip(player));
+            }
+            Some(false) => {
+                let Some(card) = self.sound_card() else {
+                    self.error_message =
+                        "Couldn't find the controller's USB audio device".to_string();
+                    return;
+                };
+                let player = ClipPlayer::new(
+                    &clip,
+                    crate::usb_haptics::USB_HAPTICS_RATE,
+                    gain,
+                    balance,
+                    looping,
+                );
+                self.usb_haptic_stream
+                    .start(card, HapticSource::Clip(player));
+            }
+            None => {
+                self.error_message = "No controller connected".to_string();
+                return;
+            }
+        }
+        self.status_message = format!("Playing {}", clip.name);
+    }
+
+    pub(crate) fn stop_haptic_clip(&mut self) {
+        if let Some(ipc) = self.ipc.clone() {
+            let _ = mlock(&ipc).stop_haptic_clip();
+        }
+        self.haptic_stream.stop();
+        self.usb_haptic_stream.stop();
+        self.status_message = "Clip stopped".to_string();
+    }
+
+    pub(crate) fn open_speaker_sound(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Select sound")
+            .add_filter("Audio", CLIP_EXTENSIONS)
+            .pick_file()
+        else {
+            return;
+        };
+        self.play_speaker_sound(Some(path));
+    }
+
+    pub(crate) fn play_speaker_sound(&mut self, path: Option<PathBuf>) {
+        self.error_message.clear();
+        let name = path
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .map_or("test tone".to_string(), |n| {
+                n.to_string_lossy().into_owned()
+            });
+
+        if let Some(ipc) = self.ipc.clone() {
+            let result = match &path {
+                Some(path) => mlock(&ipc).play_sound(&path.to_string_lossy(), 1.0, false),
+                None => mlock(&ipc).play_test_tone(),
+            };
+            match result {
+                Ok(()) => self.status_message = format!("Playing {}", name),
+                Err(e) => self.error_message = format!("Speaker playback failed: {e}"),
+            }
+            return;
+        }
+
+        if self.controller_is_bt != Some(false) {
+            self.error_message = "Speaker playback needs a USB connection".to_string();
+            return;
+        }
+        let Some(card) = self.sound_card() else {
+            self.error_message = "Couldn't find the controller's USB audio device".to_string();
+            return;
+        };
+        let rate = crate::usb_haptics::USB_HAPTICS_RATE;
+        let clip = match &path {
+            Some(path) => match HapticClip::load(path) {
+                Ok(clip) => clip,
+                Err(e) => {
+                    self.error_message = format!("Failed to load sound: {}", e);
+                    return;
+                }
+            },
+            None => HapticClip::test_tone(rate),
+        };
+        let player = ClipPlayer::new(&clip, rate, 1.0, 0.0, false);
+        self.usb_haptic_stream.play_sound(card, player);
+        self.status_message = format!("Playing {}", name);
+    }
+
+    pub(crate) fn stop_speaker_sound(&mut self) {
+        if let Some(ipc) = self.ipc.clone() {
+            let _ = mlock(&ipc).stop_sound();
+        }
+        self.usb_haptic_stream.stop_sound();
+        self.status_message = "Sound stopped".to_string();
     }
 
     fn acquire_direct_fw(&mut self) -> bool {
@@ -595,16 +1013,173 @@ impl DS4UApp {
         }
     }
 
+    pub(crate) fn apply_lightbar_bindings(&mut self) {
+        let bindings = self.lightbar.bindings.clone();
+        if let Some(be) = self.backend() {
+            be.set_lightbar_bindings(bindings);
+        }
+    }
+
+    pub(crate) fn apply_lightbar_metric(&mut self) {
+        let metric = self.lightbar.metric.clone();
+        let clearing = metric.is_none();
+        if let Some(be) = self.backend() {
+            be.set_lightbar_metric(metric);
+        }
+        if clearing {
+            self.apply_lightbar();
+        }
+    }
+
+    pub(crate) fn refresh_lightbar_animations(&mut self) {
+        self.lightbar.animations = self.animation_library.list();
+    }
+
+    pub(crate) fn preview_lightbar_animation(&mut self) {
+        let Some(animation) = self.lightbar.animation.clone() else {
+            return;
+        };
+        self.lightbar_effect = LightbarEffect::Custom { animation };
+        self.apply_lightbar_effect();
+    }
+
+    pub(crate) fn replay_lightbar_animation(&mut self) {
+        if let Some(be) = self.backend() {
+            be.set_lightbar_effect(LightbarEffect::None);
+        }
+        self.lightbar.animation_started = Instant::now();
+        self.preview_lightbar_animation();
+    }
+
+    pub(crate) fn new_lightbar_animation(&mut self) {
+        let mut n = self.lightbar.animations.len() + 1;
+        let name = loop {
+            let name = format!("Animation{}", n);
+            if !self.lightbar.animations.contains(&name) {
+                break name;
+            }
+            n += 1;
+        };
+        self.lightbar.animation = Some(LightbarAnimation::new(&name));
+        self.lightbar.animation_key = 0;
+        self.lightbar.animation_started = Instant::now();
+        self.preview_lightbar_animation();
+        self.save_lightbar_animation();
+    }
+
+    pub(crate) fn select_lightbar_animation(&mut self, name: &str) {
+        match self.animation_library.load(name) {
+            Ok(a) => {
+                self.lightbar.animation = Some(a);
+                self.lightbar.animation_key = 0;
+                self.lightbar.animation_started = Instant::now();
+                self.preview_lightbar_animation();
+                self.sync_profile();
+            }
+            Err(e) => self.error_message = e.to_string(),
+        }
+    }
+
+    pub(crate) fn save_lightbar_animation(&mut self) {
+        let Some(a) = self.lightbar.animation.clone() else {
+            return;
+        };
+        match self.animation_library.save(&a) {
+            Ok(()) => {
+                self.status_message = format!("Saved lightbar animation '{}'", a.name);
+                self.refresh_lightbar_animations();
+                self.sync_profile();
+            }
+            Err(e) => self.error_message = e.to_string(),
+        }
+    }
+
+    pub(crate) fn delete_lightbar_animation(&mut self) {
+        let Some(a) = self.lightbar.animation.take() else {
+            return;
+        };
+        if let Err(e) = self.animation_library.delete(&a.name) {
+            self.error_message = e.to_string();
+        }
+        self.refresh_lightbar_animations();
+        self.lightbar_effect = LightbarEffect::None;
+        self.apply_lightbar_effect();
+        self.apply_lightbar();
+        self.sync_profile();
+    }
+
     pub(crate) fn apply_haptic_pattern(&mut self) {
+        let custom = self.haptic_state.custom.clone();
         if let Some(be) = self.backend() {
+            be.set_custom_haptic(custom);
             be.set_haptic_pattern(
                 self.haptic_state.pattern,
                 self.haptic_state.strength,
                 self.haptic_state.speed,
+                self.haptic_state.balance,
             );
         }
     }
 
+    pub(crate) fn apply_audio_reactive(&mut self) {
+        let config = self.haptic_state.reactive.clone();
+
+        if let Some(ipc) = self.ipc.clone() {
+            if let Err(e) = mlock(&ipc).set_audio_reactive(config) {
+                self.error_message = format!("Audio reactive haptics failed: {e}");
+            }
+            return;
+        }
+
+        match self.controller_is_bt {
+            Some(true) => {
+                if !config.enabled {
+                    if self.haptic_stream.is_reactive() {
+                        self.haptic_stream.stop();
+                    }
+                } else if !self.haptic_stream.update_reactive(&config)
+                    && let Some(ctrl) = self.controller.clone()
+                {
+                    let capture = match AudioReactive::start(config, HAPTICS_SAMPLE_RATE) {
+                        Ok(c) => c,
+                        Err(e) => {
+                            self.error_message = format!("Audio reactive haptics failed: {e:#}");
+                            return;
+                        }
+                    };
+                    self.stop_input_polling();
+                    self.haptic_stream
+                        .start(ctrl, HapticSource::Reactive(capture));
+                }
+            }
+            Some(false) => {
+                if !config.enabled {
+                    if self.usb_haptic_stream.is_reactive() {
+                        self.usb_haptic_stream.stop();
+                    }
+                } else if !self.usb_haptic_stream.update_reactive(&config) {
+                    let Some(card) = self.sound_card() else {
+                        self.error_message =
+                            "Couldn't find the controller's USB audio device".to_string();
+                        return;
+                    };
+                    let capture =
+                        match AudioReactive::start(config, crate::usb_haptics::USB_HAPTICS_RATE) {
+                            Ok(c) => c,
+                            Err(e) => {
+                                self.error_message =
+                                    format!("Audio reactive haptics failed: {e:#}");
+                                return;
+                            }
+                        };
+                    self.usb_haptic_stream
+                        .start(card, HapticSource::Reactive(capture));
+                }
+            }
+            None => {}
+        }
+    }
+
     pub(crate) fn apply_gyro(&mut self) {
         let g = &self.gyro.processor;
         self.local_gyro.enabled = g.enabled;
@@ -618,7 +1193,7 @@ impl DS4UApp {
 
     pub(crate) fn apply_player_leds(&mut self) {
         if let Some(be) = self.backend() {
-            be.set_player_leds(self.player_leds);
+            be.set_player_leds(&self.player_led);
         }
     }
 
@@ -629,9 +1204,49 @@ impl DS4UApp {
         }
     }
 
+    pub(crate) fn apply_audio_levels(&mut self) {
+        if let Some(be) = self.backend() {
+            be.set_audio_levels(self.audio.levels);
+        }
+    }
+
+    pub(crate) fn apply_speaker(&mut self) {
+        if let Some(be) = self.backend() {
+            be.set_speaker(self.audio.speaker_mode);
+            be.set_audio_routing(self.audio.routing);
+        }
+    }
+
+    pub(crate) fn poll_audio_levels(&mut self) {
+        self.audio.levels_polled = Instant::now();
+        let Some(ipc) = self.ipc.clone() else {
+            return;
+        };
+        let Ok(levels) = mlock(&ipc).get_audio_levels() else {
+            return;
+        };
+        if levels != self.audio.levels {
+            self.audio.levels = levels;
+            self.sync_profile();
+        }
+    }
+
     pub(crate) fn apply_vibration(&mut self) {
         if let Some(be) = self.backend() {
-            be.set_vibration(self.vibration.rumble, self.vibration.trigger);
+            // Rumble strength is scaled per profile; only triggers still use attenuation.
+            be.set_vibration(0, self.vibration.trigger);
+        }
+    }
+
+    pub(crate) fn apply_rumble_scale(&mut self) {
+        if let Some(be) = self.backend() {
+            be.set_rumble_scale(self.vibration.rumble_scale);
+        }
+    }
+
+    pub(crate) fn apply_rumble_haptics(&mut self) {
+        if let Some(be) = self.backend() {
+            be.set_rumble_haptics(self.vibration.rumble_haptics);
         }
     }
 
@@ -673,16 +1288,208 @@ impl DS4UApp {
         let r = to_eff(&self.triggers.right).or(Some(TRIGGER_OFF));
         if let Some(be) = self.backend() {
             be.set_trigger_effects(l, r);
+            be.set_trigger_programs(
+                self.triggers.left_program.clone(),
+                self.triggers.right_program.clone(),
+            );
+        }
+    }
+
+    pub(crate) fn refresh_trigger_presets(&mut self) {
+        self.triggers.presets = self.trigger_presets.list();
+    }
+
+    fn selected_trigger_preset(&self) -> Option<TriggerPreset> {
+        let name = self.triggers.selected_preset.as_ref()?;
+        self.triggers
+            .presets
+            .iter()
+            .find(|p| &p.name == name)
+            .cloned()
+    }
+
+    pub(crate) fn apply_trigger_preset(&mut self, left: bool, right: bool) {
+        let Some(p) = self.selected_trigger_preset() else {
+            return;
+        };
+        self.triggers.preview_until = None;
+        if left {
+            self.triggers.left = p.to_config(&self.triggers.left);
+        }
+        if right {
+            self.triggers.right = p.to_config(&self.triggers.right);
+        }
+        self.apply_triggers();
+        self.sync_profile();
+        self.status_message = format!("Applied trigger preset '{}'", p.name);
+    }
+
+    pub(crate) fn preview_trigger_preset(&mut self, left: bool) {
+        let Some(p) = self.selected_trigger_preset() else {
+            return;
+        };
+        let effect = Some(p.to_config(&TriggerConfig::default()).to_effect());
+        if let Some(be) = self.backend() {
+            be.set_trigger_programs(None, None);
+            if left {
+                be.set_trigger_effects(effect, None);
+            } else {
+                be.set_trigger_effects(None, effect);
+            }
+        }
+        self.triggers.preview_until = Some(Instant::now() + TRIGGER_PREVIEW);
+    }
+
+    pub(crate) fn check_trigger_preview(&mut self) {
+        if self
+            .triggers
+            .preview_until
+            .is_some_and(|t| Instant::now() >= t)
+        {
+            self.triggers.preview_until = None;
+            self.apply_triggers();
+        }
+    }
+
+    pub(crate) fn save_trigger_preset(&mut self, left: bool) {
+        let cfg = if left {
+            &self.triggers.left
+        } else {
+            &self.triggers.right
+        };
+        let preset = TriggerPreset {
+            name: self.triggers.preset_name.trim().to_string(),
+            effect: PresetEffect::Config(cfg.clone()),
+        };
+        match self.trigger_presets.save(&preset) {
+            Ok(()) => {
+                self.status_message = format!("Saved trigger preset '{}'", preset.name);
+                self.refresh_trigger_presets();
+                self.triggers.selected_preset = Some(preset.name);
+            }
+            Err(e) => self.error_message = e.to_string(),
+        }
+    }
+
+    pub(crate) fn delete_trigger_preset(&mut self) {
+        let Some(name) = self.triggers.selected_preset.clone() else {
+            return;
+        };
+        match self.trigger_presets.delete(&name) {
+            Ok(()) => {
+                self.triggers.selected_preset = None;
+                self.refresh_trigger_presets();
+            }
+            Err(e) => self.error_message = e.to_string(),
+        }
+    }
+
+    pub(crate) fn import_trigger_preset(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Import trigger preset")
+            .add_filter("Trigger preset", &["json"])
+            .pick_file()
+        else {
+            return;
+        };
+        match self.trigger_presets.import(&path) {
+            Ok(p) => {
+                self.status_message = format!("Imported trigger preset '{}'", p.name);
+                self.refresh_trigger_presets();
+                self.triggers.selected_preset = Some(p.name);
+            }
+            Err(e) => self.error_message = format!("Failed to import preset: {}", e),
+        }
+    }
+
+    pub(crate) fn export_trigger_preset(&mut self) {
+        let Some(p) = self.selected_trigger_preset() else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Export trigger preset")
+            .add_filter("Trigger preset", &["json"])
+            .set_file_name(format!("{}.json", p.name))
+            .save_file()
+        else {
+            return;
+        };
+        match TriggerPresetLibrary::export(&p, &path) {
+            Ok(()) => self.status_message = format!("Exported to {}", path.display()),
+            Err(e) => self.error_message = format!("Failed to export preset: {}", e),
         }
     }
 
     pub(crate) fn load_profile(&mut self, profile: &Profile) {
+        self.set_profile_fields(profile);
+
+        match &self.controller_serial {
+            Some(serial) => self.settings.binding_mut(serial).profile = profile.name.clone(),
+            None => self.settings.profile = profile.name.clone(),
+        }
+        self.settings_manager.save(&self.settings);
+
+        self.apply_lightbar();
+        self.apply_lightbar_effect();
+        self.apply_lightbar_bindings();
+        self.apply_lightbar_metric();
+        self.apply_player_leds();
+        self.apply_microphone();
+        self.apply_audio_levels();
+        self.apply_speaker();
+        self.apply_input_transform();
+        self.apply_triggers();
+        self.apply_gyro();
+        self.apply_haptic_pattern();
+        self.apply_audio_reactive();
+        self.apply_rumble_scale();
+        self.apply_rumble_haptics();
+
+        if let Some(ref ipc) = self.ipc.clone() {
+            let _ = mlock(ipc).switch_profile(&profile.name);
+        }
+    }
+
+    fn set_profile_fields(&mut self, profile: &Profile) {
         self.lightbar.r = profile.lightbar_r;
         self.lightbar.g = profile.lightbar_g;
         self.lightbar.b = profile.lightbar_b;
         self.lightbar.brightness = profile.lightbar_brightness;
-        self.player_leds = profile.player_leds;
+        self.lightbar.bindings = profile.lightbar_bindings.clone();
+        self.lightbar.metric = profile.lightbar_metric.clone();
+        match profile.lightbar_animation.as_str() {
+            "" => {
+                if matches!(self.lightbar_effect, LightbarEffect::Custom { .. }) {
+                    self.lightbar_effect = LightbarEffect::None;
+                }
+            }
+            name => {
+                if let Ok(animation) = self.animation_library.load(name) {
+                    self.lightbar.animation = Some(animation.clone());
+                    self.lightbar.animation_key = 0;
+                    self.lightbar.animation_started = Instant::now();
+                    self.lightbar_effect = LightbarEffect::Custom { animation };
+                }
+            }
+        }
+        match &profile.lightbar_visualizer {
+            Some(config) => {
+                self.lightbar.visualizer = config.clone();
+                self.lightbar_effect = LightbarEffect::Visualizer {
+                    config: config.clone(),
+                };
+            }
+            None => {
+                if matches!(self.lightbar_effect, LightbarEffect::Visualizer { .. }) {
+                    self.lightbar_effect = LightbarEffect::None;
+                }
+            }
+        }
+        self.player_led = profile.to_player_led_config();
         self.microphone.enabled = profile.mic_enabled;
+        self.audio.levels = profile.audio_levels;
+        self.audio.speaker_mode = profile.speaker_mode;
+        self.audio.routing = profile.audio_routing;
 
         self.sticks.left_curve = profile.stick_left_curve.clone();
         self.sticks.right_curve = profile.stick_right_curve.clone();
@@ -698,6 +1505,8 @@ impl DS4UApp {
 
         self.triggers.left = profile.trigger_left_config.clone();
         self.triggers.right = profile.trigger_right_config.clone();
+        self.triggers.left_program = profile.trigger_left_program.clone();
+        self.triggers.right_program = profile.trigger_right_program.clone();
 
         self.gyro.processor = profile.to_gyro_processor();
         self.touchpad.mode = if !profile.touchpad_enabled {
@@ -713,22 +1522,25 @@ impl DS4UApp {
         self.haptic_state.pattern = profile.haptic_pattern;
         self.haptic_state.strength = profile.haptic_strength;
         self.haptic_state.speed = profile.haptic_speed;
+        self.haptic_state.balance = profile.haptic_balance;
+        self.vibration.rumble_scale = profile.rumble_scale;
+        self.vibration.rumble_haptics = profile.rumble_haptics;
+        self.haptic_state.custom = match profile.haptic_custom.as_str() {
+            "" => None,
+            name => self.pattern_library.load(name).ok(),
+        };
+        self.haptic_state.reactive = profile.audio_reactive.clone();
 
         self.current_profile = Some(profile.clone());
+    }
 
-        self.settings.profile = profile.name.clone();
-        self.settings_manager.save(&self.settings);
-
-        self.apply_lightbar();
-        self.apply_player_leds();
-        self.apply_microphone();
-        self.apply_input_transform();
-        self.apply_triggers();
-        self.apply_gyro();
-        self.apply_haptic_pattern();
-
-        if let Some(ref ipc) = self.ipc.clone() {
-            let _ = mlock(ipc).switch_profile(&profile.name);
+    fn adopt_bound_profile(&mut self, name: &str) {
+        let current = self.current_profile.as_ref().map(|p| p.name.as_str());
+        if name.is_empty() || current == Some(name) {
+            return;
+        }
+        if let Ok(p) = self.profile_manager.load_profile(name) {
+            self.set_profile_fields(&p);
         }
     }
 
@@ -741,8 +1553,24 @@ impl DS4UApp {
         profile.lightbar_g = self.lightbar.g;
         profile.lightbar_b = self.lightbar.b;
         profile.lightbar_brightness = self.lightbar.brightness;
-        profile.player_leds = self.player_leds;
+        profile.lightbar_bindings = self.lightbar.bindings.clone();
+        profile.lightbar_metric = self.lightbar.metric.clone();
+        profile.lightbar_animation = match &self.lightbar_effect {
+            LightbarEffect::Custom { animation } => animation.name.clone(),
+            _ => String::new(),
+        };
+        profile.lightbar_visualizer = match &self.lightbar_effect {
+            LightbarEffect::Visualizer { config } => Some(config.clone()),
+            _ => None,
+        };
+        if let Some(i) = self.player_led.preset_index() {
+            profile.player_leds = i;
+        }
+        profile.player_led = Some(self.player_led.clone());
         profile.mic_enabled = self.microphone.enabled;
+        profile.audio_levels = self.audio.levels;
+        profile.speaker_mode = self.audio.speaker_mode;
+        profile.audio_routing = self.audio.routing;
 
         profile.stick_left_curve = self.sticks.left_curve.clone();
         profile.stick_right_curve = self.sticks.right_curve.clone();
@@ -758,6 +1586,8 @@ impl DS4UApp {
 
         profile.trigger_left_config = self.triggers.left.clone();
         profile.trigger_right_config = self.triggers.right.clone();
+        profile.trigger_left_program = self.triggers.left_program.clone();
+        profile.trigger_right_program = self.triggers.right_program.clone();
 
         profile.gyro = self.gyro.processor.clone();
         profile.touchpad_enabled = !matches!(self.touchpad.mode, TouchpadMode::Disabled);
@@ -770,6 +1600,16 @@ impl DS4UApp {
         profile.haptic_pattern = self.haptic_state.pattern;
         profile.haptic_strength = self.haptic_state.strength;
         profile.haptic_speed = self.haptic_state.speed;
+        profile.haptic_balance = self.haptic_state.balance;
+        profile.rumble_scale = self.vibration.rumble_scale;
+        profile.rumble_haptics = self.vibration.rumble_haptics;
+        profile.haptic_custom = self
+            .haptic_state
+            .custom
+            .as_ref()
+            .map(|p| p.name.clone())
+            .unwrap_or_default();
+        profile.audio_reactive = self.haptic_state.reactive.clone();
 
         if let Some(ref ipc) = self.ipc.clone() {
             let _ = mlock(ipc).save_profile(profile.clone());
@@ -783,6 +1623,10 @@ impl DS4UApp {
             return;
         }
 
+        if self.subscribed() {
+            return;
+        }
+
         if let Some(ref ipc) = self.ipc.clone() {
             let still_present = matches!(mlock(ipc).get_controller_info(), Ok(Some(_)));
             if !still_present {
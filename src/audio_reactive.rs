@@ -0,0 +1,371 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use alsa::device_name::HintIter;
+use alsa::pcm::{Access, Format, HwParams, PCM};
+use alsa::{Direction, ValueOr};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::util::mlock;
+
+pub const CAPTURE_RATE: u32 = 48_000;
+pub const CAPTURE_CHANNELS: usize = 2;
+pub const CAPTURE_FRAMES: usize = 240;
+const MAX_BUFFERED_SECS: f32 = 0.1;
+const ATTACK_SECS: f32 = 0.005;
+const RELEASE_SECS: f32 = 0.15;
+// The envelope modulates this tone; the coils barely move for a slow envelope on its own.
+const CARRIER_HZ: f32 = 160.0;
+
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Debug, Default)]
+pub enum ChannelMapping {
+    #[default]
+    Stereo,
+    Swapped,
+    Mono,
+    LeftOnly,
+    RightOnly,
+}
+
+impl ChannelMapping {
+    pub const ALL: [ChannelMapping; 5] = [
+        ChannelMapping::Stereo,
+        ChannelMapping::Swapped,
+        ChannelMapping::Mono,
+        ChannelMapping::LeftOnly,
+        ChannelMapping::RightOnly,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ChannelMapping::Stereo => "Stereo",
+            ChannelMapping::Swapped => "Swapped",
+            ChannelMapping::Mono => "Mono",
+            ChannelMapping::LeftOnly => "Left",
+            ChannelMapping::RightOnly => "Right",
+        }
+    }
+
+    fn apply(self, [l, r]: [f32; 2]) -> [f32; 2] {
+        match self {
+            ChannelMapping::Stereo => [l, r],
+            ChannelMapping::Swapped => [r, l],
+            ChannelMapping::Mono => {
+                let m = (l + r) * 0.5;
+                [m, m]
+            }
+            ChannelMapping::LeftOnly => [l, l],
+            ChannelMapping::RightOnly => [r, r],
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+#[serde(default)]
+pub struct ReactiveConfig {
+    pub enabled: bool,
+    pub device: String,
+    pub crossover_hz: f32,
+    pub gain: f32,
+    pub gate: f32,
+    pub mapping: ChannelMapping,
+}
+
+impl Default for ReactiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            device: "default".to_string(),
+            crossover_hz: 120.0,
+            gain: 2.0,
+            gate: 0.02,
+            mapping: ChannelMapping::Stereo,
+        }
+    }
+}
+
+pub fn list_capture_devices() -> Vec<String> {
+    let Ok(hints) = HintIter::new_str(None, "pcm") else {
+        return vec!["default".to_string()];
+    };
+    let mut names: Vec<String> = hints
+        .filter(|h| h.direction != Some(Direction::Playback))
+        .filter_map(|h| h.name)
+        .filter(|n| n != "null")
+        .collect();
+    if !names.iter().any(|n| n == "default") {
+        names.insert(0, "default".to_string());
+    }
+    names
+}
+
+#[derive(Default, Clone, Copy)]
+struct Biquad {
+    b: [f32; 3],
+    a: [f32; 2],
+    s: [f32; 2],
+}
+
+impl Biquad {
+    fn lowpass(cutoff: f32, rate: u32) -> Self {
+        let w = std::f32::consts::TAU * cutoff.clamp(10.0, rate as f32 * 0.45) / rate as f32;
+        let alpha = w.sin() / std::f32::consts::SQRT_2;
+        let cos = w.cos();
+        let a0 = 1.0 + alpha;
+        let b1 = (1.0 - cos) / a0;
+        Self {
+            b: [b1 * 0.5, b1, b1 * 0.5],
+            a: [-2.0 * cos / a0, (1.0 - alpha) / a0],
+            s: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, x: f32) -> f32 {
+        let y = self.b[0] * x + self.s[0];
+        self.s[0] = self.b[1] * x - self.a[0] * y + self.s[1];
+        self.s[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+struct Processor {
+    config: ReactiveConfig,
+    filters: [Biquad; 2],
+    envelope: [f32; 2],
+    gate: f32,
+    attack: f32,
+    release: f32,
+    phase: f32,
+}
+
+impl Processor {
+    fn new(config: ReactiveConfig) -> Self {
+        let coef = |secs: f32| 1.0 - (-1.0 / (secs * CAPTURE_RATE as f32)).exp();
+        let lp = Biquad::lowpass(config.crossover_hz, CAPTURE_RATE);
+        Self {
+            config,
+            filters: [lp; 2],
+            envelope: [0.0; 2],
+            gate: 0.0,
+            attack: coef(ATTACK_SECS),
+            release: coef(RELEASE_SECS),
+            phase: 0.0,
+        }
+    }
+
+    fn reconfigure(&mut self, config: &ReactiveConfig) {
+        if config.crossover_hz != self.config.crossover_hz {
+            let lp = Biquad::lowpass(config.crossover_hz, CAPTURE_RATE);
+            for f in &mut self.filters {
+                f.b = lp.b;
+                f.a = lp.a;
+            }
+        }
+        self.config = config.clone();
+    }
+
+    fn process(&mut self, frame: [f32; 2]) -> [f32; 2] {
+        let [l, r] = self.config.mapping.apply(frame);
+        let filtered = [self.filters[0].process(l), self.filters[1].process(r)];
+
+        for (env, x) in self.envelope.iter_mut().zip(filtered) {
+            let level = x.abs();
+            let k = if level > *env {
+                self.attack
+            } else {
+                self.release
+            };
+            *env += (level - *env) * k;
+        }
+
+        let open = if self.envelope[0].max(self.envelope[1]) >= self.config.gate {
+            1.0
+        } else {
+            0.0
+        };
+        let k = if open > self.gate {
+            self.attack
+        } else {
+            self.release
+        };
+        self.gate += (open - self.gate) * k;
+
+        let carrier = (std::f32::consts::TAU * self.phase).sin();
+        self.phase = (self.phase + CARRIER_HZ / CAPTURE_RATE as f32).fract();
+
+        let g = self.config.gain * self.gate;
+        self.envelope.map(|env| (env * g).min(1.0) * carrier)
+    }
+}
+
+struct Shared {
+    config: Mutex<ReactiveConfig>,
+    frames: Mutex<VecDeque<[f32; 2]>>,
+}
+
+pub fn open_capture(device: &str) -> Result<PCM> {
+    let pcm = PCM::new(device, Direction::Capture, false)
+        .with_context(|| format!("opening ALSA capture device {device}"))?;
+    {
+        let hwp = HwParams::any(&pcm)?;
+        hwp.set_access(Access::RWInterleaved)?;
+        hwp.set_format(Format::S16LE)?;
+        hwp.set_channels(CAPTURE_CHANNELS as u32)?;
+        hwp.set_rate(CAPTURE_RATE, ValueOr::Nearest)?;
+        pcm.hw_params(&hwp)?;
+    }
+    Ok(pcm)
+}
+
+fn run_capture(pcm: &PCM, stop: &AtomicBool, shared: &Shared, out_rate: u32) -> Result<()> {
+    let io = pcm.io_i16()?;
+    pcm.prepare()?;
+    pcm.start()?;
+
+    let mut proc = Processor::new(mlock(&shared.config).clone());
+    let mut buf = vec![0i16; CAPTURE_FRAMES * CAPTURE_CHANNELS];
+    let mut out = Vec::with_capacity(CAPTURE_FRAMES);
+    let max_buffered = (out_rate as f32 * MAX_BUFFERED_SECS) as usize;
+    let mut acc = [0.0f32; 2];
+    let mut count = 0u32;
+    let mut phase = 0u32;
+
+    while !stop.load(Ordering::Relaxed) {
+        let n = match io.readi(&mut buf) {
+            Ok(n) => n,
+            Err(e) => {
+                pcm.try_recover(e, true)?;
+                continue;
+            }
+        };
+
+        proc.reconfigure(&mlock(&shared.config));
+
+        out.clear();
+        for f in buf[..n * CAPTURE_CHANNELS].chunks_exact(CAPTURE_CHANNELS) {
+            let y = proc.process([f[0] as f32 / 32768.0, f[1] as f32 / 32768.0]);
+            acc[0] += y[0];
+            acc[1] += y[1];
+            count += 1;
+            // Box-average down to the consumer's rate.
+            phase += out_rate;
+            if phase >= CAPTURE_RATE {
+                phase -= CAPTURE_RATE;
+                out.push([acc[0] / count as f32, acc[1] / count as f32]);
+                acc = [0.0; 2];
+                count = 0;
+            }
+        }
+
+        let mut frames = mlock(&shared.frames);
+        frames.extend(out.iter().copied());
+        while frames.len() > max_buffered {
+            frames.pop_front();
+        }
+    }
+    Ok(())
+}
+
+pub struct AudioReactive {
+    device: String,
+    shared: Arc<Shared>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AudioReactive {
+    // Opens the capture device up front so a bad device is reported to the caller.
+    pub fn start(config: ReactiveConfig, out_rate: u32) -> Result<Self> {
+        let device = config.device.clone();
+        let pcm = open_capture(&device)?;
+        let shared = Arc::new(Shared {
+            config: Mutex::new(config),
+            frames: Mutex::new(VecDeque::new()),
+        });
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let shared_c = Arc::clone(&shared);
+        let stop_c = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            if let Err(e) = run_capture(&pcm, &stop_c, &shared_c, out_rate) {
+                eprintln!("[audio-reactive] capture ended: {e}");
+            }
+        });
+
+        Ok(Self {
+            device,
+            shared,
+            stop,
+            thread: Some(handle),
+        })
+    }
+
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().is_some_and(|h| !h.is_finished())
+    }
+
+    pub fn set_config(&self, config: ReactiveConfig) {
+        *mlock(&self.shared.config) = config;
+    }
+
+    pub fn fill(&mut self, out: &mut [[f32; 2]]) -> bool {
+        let mut frames = mlock(&self.shared.frames);
+        for f in out.iter_mut() {
+            *f = frames.pop_front().unwrap_or([0.0; 2]);
+        }
+        self.is_running()
+    }
+}
+
+impl Drop for AudioReactive {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_follows_the_envelope() {
+        let mut proc = Processor::new(ReactiveConfig {
+            gain: 1.0,
+            ..ReactiveConfig::default()
+        });
+        let tone = |i: usize| (std::f32::consts::TAU * 50.0 * i as f32 / CAPTURE_RATE as f32).sin();
+
+        let mut peak = 0.0f32;
+        for i in 0..CAPTURE_RATE as usize / 2 {
+            let [l, _] = proc.process([tone(i) * 0.5, 0.0]);
+            assert!(l.abs() <= proc.envelope[0] + 1e-6);
+            peak = peak.max(l.abs());
+        }
+        assert!(peak > 0.3);
+
+        for _ in 0..CAPTURE_RATE as usize {
+            proc.process([0.0, 0.0]);
+        }
+        assert!(proc.process([0.0, 0.0])[0].abs() < 0.01);
+    }
+
+    #[test]
+    fn missing_capture_device_is_an_error() {
+        let config = ReactiveConfig {
+            enabled: true,
+            device: "ds4u-no-such-device".to_string(),
+            ..ReactiveConfig::default()
+        };
+        assert!(AudioReactive::start(config, CAPTURE_RATE).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+use std::f32::consts::TAU;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use alsa::pcm::PCM;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audio_reactive::{CAPTURE_CHANNELS, CAPTURE_FRAMES, CAPTURE_RATE, open_capture},
+    lightbar_anim::{hsv_to_rgb, mix},
+    util::mlock,
+};
+
+const FFT_SIZE: usize = 1024;
+const BANDS: [(f32, f32); 3] = [(20.0, 250.0), (250.0, 2000.0), (2000.0, 12000.0)];
+
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Debug, Default)]
+pub enum Palette {
+    #[default]
+    Bands,
+    Spectrum,
+    Fire,
+    Ice,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 4] = [
+        Palette::Bands,
+        Palette::Spectrum,
+        Palette::Fire,
+        Palette::Ice,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Palette::Bands => "Bands",
+            Palette::Spectrum => "Spectrum",
+            Palette::Fire => "Fire",
+            Palette::Ice => "Ice",
+        }
+    }
+
+    fn color(self, levels: &Levels) -> (u8, u8, u8) {
+        let [bass, mid, treble] = levels.bands;
+        let ramp = |stops: [(u8, u8, u8); 3], t: f32| {
+            let (r, g, b) = if t < 0.5 {
+                mix(stops[0], stops[1], t * 2.0)
+            } else {
+                mix(stops[1], stops[2], t * 2.0 - 1.0)
+            };
+            (
+                (r as f32 * t) as u8,
+                (g as f32 * t) as u8,
+                (b as f32 * t) as u8,
+            )
+        };
+        match self {
+            Palette::Bands => (
+                (bass * 255.0) as u8,
+                (mid * 255.0) as u8,
+                (treble * 255.0) as u8,
+            ),
+            Palette::Spectrum => {
+                let total = bass + mid + treble;
+                let centre = if total > f32::EPSILON {
+                    (mid * 0.5 + treble) / total
+                } else {
+                    0.0
+                };
+                hsv_to_rgb(centre * 240.0, 1.0, levels.loudness)
+            }
+            Palette::Fire => ramp(
+                [(160, 0, 0), (255, 110, 0), (255, 230, 120)],
+                levels.loudness,
+            ),
+            Palette::Ice => ramp(
+                [(0, 20, 160), (0, 180, 255), (220, 250, 255)],
+                levels.loudness,
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+#[serde(default)]
+pub struct VisualizerConfig {
+    pub device: String,
+    pub gain: f32,
+    pub smoothing: f32,
+    pub palette: Palette,
+}
+
+impl Default for VisualizerConfig {
+    fn default() -> Self {
+        Self {
+            device: "default".to_string(),
+            gain: 4.0,
+            smoothing: 0.6,
+            palette: Palette::Bands,
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+struct Levels {
+    loudness: f32,
+    bands: [f32; 3],
+}
+
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let step = -TAU / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (s, c) = (step * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let tr = re[b] * c - im[b] * s;
+                let ti = re[b] * s + im[b] * c;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+struct Analyzer {
+    window: Vec<f32>,
+    re: Vec<f32>,
+    im: Vec<f32>,
+    levels: Levels,
+}
+
+impl Analyzer {
+    fn new() -> Self {
+        Self {
+            window: (0..FFT_SIZE)
+                .map(|i| 0.5 - 0.5 * (TAU * i as f32 / FFT_SIZE as f32).cos())
+                .collect(),
+            re: vec![0.0; FFT_SIZE],
+            im: vec![0.0; FFT_SIZE],
+            levels: Levels::default(),
+        }
+    }
+
+    fn process(&mut self, samples: &[f32], config: &VisualizerConfig) -> Levels {
+        let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+        for (i, s) in samples.iter().enumerate() {
+            self.re[i] = s * self.window[i];
+            self.im[i] = 0.0;
+        }
+        fft(&mut self.re, &mut self.im);
+
+        let hz_per_bin = CAPTURE_RATE as f32 / FFT_SIZE as f32;
+        // A full-scale sine through a Hann window peaks at N/4.
+        let scale = 4.0 / FFT_SIZE as f32;
+        let bands = BANDS.map(|(lo, hi)| {
+            let from = ((lo / hz_per_bin) as usize).max(1);
+            let to = ((hi / hz_per_bin) as usize).min(FFT_SIZE / 2);
+            let power: f32 = (from..to)
+                .map(|k| self.re[k] * self.re[k] + self.im[k] * self.im[k])
+                .sum();
+            (power.sqrt() * scale * config.gain).clamp(0.0, 1.0)
+        });
+        let target = Levels {
+            loudness: (rms * config.gain * std::f32::consts::SQRT_2).clamp(0.0, 1.0),
+            bands,
+        };
+
+        let smoothing = config.smoothing.clamp(0.0, 0.95);
+        let follow = |current: &mut f32, target: f32| {
+            // Rise at twice the rate of the fall so beats still land.
+            let k = if target > *current {
+                1.0 - smoothing * 0.5
+            } else {
+                1.0 - smoothing
+            };
+            *current += (target - *current) * k;
+        };
+        follow(&mut self.levels.loudness, target.loudness);
+        for (level, target) in self.levels.bands.iter_mut().zip(target.bands) {
+            follow(level, target);
+        }
+        self.levels
+    }
+}
+
+struct Shared {
+    config: Mutex<VisualizerConfig>,
+    levels: Mutex<Levels>,
+}
+
+fn run_capture(pcm: &PCM, stop: &AtomicBool, shared: &Shared) -> Result<()> {
+    let io = pcm.io_i16()?;
+    pcm.prepare()?;
+    pcm.start()?;
+
+    let mut analyzer = Analyzer::new();
+    let mut buf = vec![0i16; CAPTURE_FRAMES * CAPTURE_CHANNELS];
+    let mut samples = Vec::with_capacity(FFT_SIZE * 2);
+
+    while !stop.load(Ordering::Relaxed) {
+        let n = match io.readi(&mut buf) {
+            Ok(n) => n,
+            Err(e) => {
+                pcm.try_recover(e, true)?;
+                continue;
+            }
+        };
+
+        samples.extend(
+            buf[..n * CAPTURE_CHANNELS]
+                .chunks_exact(CAPTURE_CHANNELS)
+                .map(|f| (f[0] as f32 + f[1] as f32) / 65536.0),
+        );
+        while samples.len() >= FFT_SIZE {
+            let config = mlock(&shared.config).clone();
+            let levels = analyzer.process(&samples[..FFT_SIZE], &config);
+            *mlock(&shared.levels) = levels;
+            samples.drain(..FFT_SIZE);
+        }
+    }
+    Ok(())
+}
+
+pub struct AudioVisualizer {
+    device: String,
+    shared: Arc<Shared>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AudioVisualizer {
+    pub fn start(config: VisualizerConfig) -> Self {
+        let device = config.device.clone();
+        let shared = Arc::new(Shared {
+            config: Mutex::new(config),
+            levels: Mutex::new(Levels::default()),
+        });
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let shared_c = Arc::clone(&shared);
+        let stop_c = Arc::clone(&stop);
+        let device_c = device.clone();
+        let handle = thread::spawn(move || {
+            let pcm = match open_capture(&device_c) {
+                Ok(pcm) => pcm,
+                Err(e) => {
+                    eprintln!("[visualizer] open failed: {e:#}");
+                    return;
+                }
+            };
+            if let Err(e) = run_capture(&pcm, &stop_c, &shared_c) {
+                eprintln!("[visualizer] capture ended: {e}");
+            }
+        });
+
+        Self {
+            device,
+            shared,
+            stop,
+            thread: Some(handle),
+        }
+    }
+
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().is_some_and(|h| !h.is_finished())
+    }
+
+    pub fn set_config(&self, config: VisualizerConfig) {
+        *mlock(&self.shared.config) = config;
+    }
+
+    pub fn color(&self) -> (u8, u8, u8) {
+        let palette = mlock(&self.shared.config).palette;
+        palette.color(&mlock(&self.shared.levels))
+    }
+}
+
+impl Drop for AudioVisualizer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
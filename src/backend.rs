@@ -1,9 +1,14 @@
 use std::sync::{Arc, Mutex};
 
 use crate::{
-    common::{HapticPattern, LightbarEffect, MicLedState},
+    common::{AudioLevels, AudioRouting, HapticPattern, LightbarEffect, MicLedState, SpeakerMode},
     dualsense::DualSense,
+    haptic_synth::{CustomPattern, RumbleHaptics},
     ipc::IpcClient,
+    lightbar_react::LightbarBinding,
+    player_leds::PlayerLedConfig,
+    profiles::TriggerProgram,
+    system_metrics::MetricBinding,
     transform::InputTransform,
     util::mlock,
 };
@@ -12,16 +17,32 @@ pub(crate) const TRIGGER_OFF: (u8, [u8; 10]) = (0x05, [0u8; 10]);
 
 pub(crate) trait ControllerBackend {
     fn set_lightbar(&self, r: u8, g: u8, b: u8, brightness: u8);
-    fn set_player_leds(&self, leds: u8);
+    fn set_player_leds(&self, config: &PlayerLedConfig);
     fn set_mic(&self, enabled: bool);
     fn set_mic_led(&self, state: MicLedState);
+    fn set_audio_levels(&self, levels: AudioLevels);
+    fn set_speaker(&self, mode: SpeakerMode);
     fn set_vibration(&self, rumble: u8, trigger: u8);
     fn set_trigger_effects(&self, left: Option<(u8, [u8; 10])>, right: Option<(u8, [u8; 10])>);
 
     fn set_lightbar_effect(&self, _effect: LightbarEffect) {}
-    fn set_haptic_pattern(&self, _pattern: HapticPattern, _strength: u8, _speed: f32) {}
+    fn set_haptic_pattern(
+        &self,
+        _pattern: HapticPattern,
+        _strength: u8,
+        _speed: f32,
+        _balance: f32,
+    ) {
+    }
+    fn set_custom_haptic(&self, _pattern: Option<CustomPattern>) {}
+    fn set_rumble_scale(&self, _scale: f32) {}
+    fn set_rumble_haptics(&self, _config: RumbleHaptics) {}
+    fn set_lightbar_bindings(&self, _bindings: Vec<LightbarBinding>) {}
+    fn set_lightbar_metric(&self, _metric: Option<MetricBinding>) {}
+    fn set_trigger_programs(&self, _left: Option<TriggerProgram>, _right: Option<TriggerProgram>) {}
     fn set_gyro(&self, _enabled: bool, _smoothing: f32, _sensitivity: f32) {}
     fn set_input_transform(&self, _transform: InputTransform) {}
+    fn set_audio_routing(&self, _routing: Option<AudioRouting>) {}
 }
 
 pub(crate) struct DirectBackend(pub Arc<Mutex<DualSense>>);
@@ -33,8 +54,10 @@ impl ControllerBackend for DirectBackend {
         let _ = mlock(&self.0).set_lightbar(r, g, b, brightness);
     }
 
-    fn set_player_leds(&self, leds: u8) {
-        let _ = mlock(&self.0).set_player_leds(leds);
+    fn set_player_leds(&self, config: &PlayerLedConfig) {
+        let mut ds = mlock(&self.0);
+        let _ = ds.set_player_led_brightness(config.brightness);
+        let _ = ds.set_player_led_mask(config.pattern);
     }
 
     fn set_mic(&self, enabled: bool) {
@@ -45,6 +68,14 @@ impl ControllerBackend for DirectBackend {
         let _ = mlock(&self.0).set_mic_led(state);
     }
 
+    fn set_audio_levels(&self, levels: AudioLevels) {
+        let _ = mlock(&self.0).set_audio_levels(levels);
+    }
+
+    fn set_speaker(&self, mode: SpeakerMode) {
+        let _ = mlock(&self.0).set_speaker(mode);
+    }
+
     fn set_vibration(&self, rumble: u8, trigger: u8) {
         let _ = mlock(&self.0).set_vibration(rumble, trigger);
     }
@@ -59,8 +90,8 @@ impl ControllerBackend for IpcBackend {
         let _ = mlock(&self.0).set_lightbar(r, g, b, brightness);
     }
 
-    fn set_player_leds(&self, leds: u8) {
-        let _ = mlock(&self.0).set_player_leds(leds);
+    fn set_player_leds(&self, config: &PlayerLedConfig) {
+        let _ = mlock(&self.0).set_player_led_config(config.clone());
     }
 
     fn set_mic(&self, enabled: bool) {
@@ -71,6 +102,14 @@ impl ControllerBackend for IpcBackend {
         let _ = mlock(&self.0).set_mic_led(state);
     }
 
+    fn set_audio_levels(&self, levels: AudioLevels) {
+        let _ = mlock(&self.0).set_audio_levels(levels);
+    }
+
+    fn set_speaker(&self, mode: SpeakerMode) {
+        let _ = mlock(&self.0).set_speaker(mode);
+    }
+
     fn set_vibration(&self, rumble: u8, trigger: u8) {
         let _ = mlock(&self.0).set_vibration(rumble, trigger);
     }
@@ -83,8 +122,32 @@ impl ControllerBackend for IpcBackend {
         let _ = mlock(&self.0).set_lightbar_effect(effect);
     }
 
-    fn set_haptic_pattern(&self, pattern: HapticPattern, strength: u8, speed: f32) {
-        let _ = mlock(&self.0).set_haptic_pattern(pattern, strength, speed);
+    fn set_haptic_pattern(&self, pattern: HapticPattern, strength: u8, speed: f32, balance: f32) {
+        let _ = mlock(&self.0).set_haptic_pattern(pattern, strength, speed, balance);
+    }
+
+    fn set_custom_haptic(&self, pattern: Option<CustomPattern>) {
+        let _ = mlock(&self.0).set_custom_haptic(pattern);
+    }
+
+    fn set_rumble_scale(&self, scale: f32) {
+        let _ = mlock(&self.0).set_rumble_scale(scale);
+    }
+
+    fn set_rumble_haptics(&self, config: RumbleHaptics) {
+        let _ = mlock(&self.0).set_rumble_haptics(config);
+    }
+
+    fn set_lightbar_bindings(&self, bindings: Vec<LightbarBinding>) {
+        let _ = mlock(&self.0).set_lightbar_bindings(bindings);
+    }
+
+    fn set_lightbar_metric(&self, metric: Option<MetricBinding>) {
+        let _ = mlock(&self.0).set_lightbar_metric(metric);
+    }
+
+    fn set_trigger_programs(&self, left: Option<TriggerProgram>, right: Option<TriggerProgram>) {
+        let _ = mlock(&self.0).set_trigger_programs(left, right);
     }
 
     fn set_gyro(&self, enabled: bool, smoothing: f32, sensitivity: f32) {
@@ -94,4 +157,8 @@ impl ControllerBackend for IpcBackend {
     fn set_input_transform(&self, transform: InputTransform) {
         let _ = mlock(&self.0).set_input_transform(transform);
     }
+
+    fn set_audio_routing(&self, routing: Option<AudioRouting>) {
+        let _ = mlock(&self.0).set_audio_routing(routing);
+    }
 }
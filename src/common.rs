@@ -1,5 +1,9 @@
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    audio_visualizer::VisualizerConfig, inputs::JackState, lightbar_anim::LightbarAnimation,
+};
+
 pub const DS_VID: u16 = 0x054c;
 pub const DS_PID: u16 = 0x0ce6;
 pub const DSE_PID: u16 = 0x0df2;
@@ -38,6 +42,7 @@ pub enum TriggerMode {
     Galloping,
     Vibration,
     Machine,
+    Raw,
 }
 
 #[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
@@ -50,13 +55,73 @@ pub enum SensitivityCurve {
     Dynamic,
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct AudioLevels {
+    pub headphone: u8,
+    pub speaker: u8,
+    pub mic: u8,
+}
+
+impl Default for AudioLevels {
+    fn default() -> Self {
+        Self {
+            headphone: 200,
+            speaker: 200,
+            mic: 200,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug, Default)]
 pub enum SpeakerMode {
+    #[default]
     Internal,
     Headphone,
     Both,
 }
 
+impl SpeakerMode {
+    pub const ALL: [SpeakerMode; 3] = [
+        SpeakerMode::Internal,
+        SpeakerMode::Headphone,
+        SpeakerMode::Both,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SpeakerMode::Internal => "Internal",
+            SpeakerMode::Headphone => "Headphone",
+            SpeakerMode::Both => "Both",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct AudioRouting {
+    pub unplugged: SpeakerMode,
+    pub plugged: SpeakerMode,
+}
+
+impl Default for AudioRouting {
+    fn default() -> Self {
+        Self {
+            unplugged: SpeakerMode::Internal,
+            plugged: SpeakerMode::Headphone,
+        }
+    }
+}
+
+impl AudioRouting {
+    pub fn mode_for(&self, jack: JackState) -> SpeakerMode {
+        if jack.headphones {
+            self.plugged
+        } else {
+            self.unplugged
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Serialize, Deserialize, Default)]
 pub enum LightbarEffect {
     #[default]
@@ -70,6 +135,16 @@ pub enum LightbarEffect {
     Strobe {
         speed: f32,
     },
+    Battery {
+        low: u8,
+        player_leds: bool,
+    },
+    Custom {
+        animation: LightbarAnimation,
+    },
+    Visualizer {
+        config: VisualizerConfig,
+    },
 }
 
 #[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Debug, Default)]
@@ -80,4 +155,5 @@ pub enum HapticPattern {
     Pulse,
     Ramp,
     Wave,
+    Custom,
 }
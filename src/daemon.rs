@@ -1,30 +1,71 @@
 use std::{
-    io::{BufRead, BufReader, Write},
+    collections::{HashMap, HashSet, hash_map::Entry},
+    io::{BufRead, BufReader, Read, Write},
+    net::UdpSocket,
     sync::{
-        Arc, Condvar, Mutex, RwLock,
-        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex, OnceLock, RwLock,
+        atomic::{AtomicBool, AtomicU8, AtomicU32, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError},
     },
-    thread::{self, sleep},
+    thread::{self, JoinHandle, sleep},
     time::{Duration, Instant},
 };
 
+use anyhow::{Result, bail};
 use hidapi::HidApi;
 
 use crate::{
-    common::{HapticPattern, LightbarEffect},
-    dualsense::{DualSense, HAPTICS_PACKET_FRAMES, HAPTICS_SAMPLE_RATE},
-    haptics_stream::generate_packet,
+    audio_reactive::{AudioReactive, ReactiveConfig},
+    audio_visualizer::AudioVisualizer,
+    common::{
+        AudioLevels, AudioRouting, DS_TRIGGER_EFFECT_OFF, HapticPattern, LightbarEffect,
+        MicLedState, SpeakerMode,
+    },
+    dsx::{self, DsxAction, DsxTarget},
+    dualsense::{
+        BatteryInfo, DualSense, HAPTICS_PACKET_FRAMES, HAPTICS_SAMPLE_RATE, InputReader,
+        PLAYER_LED_PATTERNS, list_serials,
+    },
+    haptic_clip::{ClipPlayer, HapticClip},
+    haptic_synth::{
+        CustomPattern, HapticVoice, PatternLibrary, RumbleHaptics, RumbleVoice, levels,
+    },
+    haptics_stream::{HapticSource, to_packet},
+    inputs::{BTN_MUTE, ControllerState},
     ipc::{
-        DaemonCommand, DaemonResponse, DaemonStream, IpcClient, addr_display, bind_daemon,
-        cleanup_endpoint, daemon_endpoint,
+        ControllerSummary, DaemonCommand, DaemonEvent, DaemonRequest, DaemonResponse, DaemonStream,
+        EventMessage, EventTopic, IpcClient, addr_display, bind_daemon, cleanup_endpoint,
+        daemon_endpoint,
+    },
+    lightbar_anim::{AnimationLibrary, hsv_to_rgb},
+    lightbar_react::{LightbarBinding, ReactiveLightbar},
+    mixer::{CaptureSwitch, VolumeControl, controller_mixer},
+    notify::ActiveNotification,
+    player_leds::PlayerLedConfig,
+    profiles::{ProfileManager, TriggerProgram},
+    settings::{MicMuteSettings, MuteMode, PlayerSlotMode, Settings, SettingsManager},
+    system_metrics::{
+        MetricBinding, MetricSource, SystemSampler, cpu_temp, memory_used, run_check,
     },
-    profiles::ProfileManager,
-    settings::SettingsManager,
     transform::{GyroProcessor, InputTransform},
+    usb_haptics::{USB_HAPTICS_RATE, UsbHapticStream, find_card_for_hid, mix_overlay},
     util::{mlock, rlock, wait_cv, wlock},
+    virtual_pad::VirtualPad,
 };
 
 const TAG: &str = "[ds4u daemon]";
+const MAX_PLAYER_SLOT: u8 = 7;
+const EVENT_QUEUE: usize = 256;
+const LIGHTBAR_REACT_INTERVAL: Duration = Duration::from_millis(16);
+const METRIC_INTERVAL: Duration = Duration::from_secs(1);
+const DSX_SETTINGS_POLL: Duration = Duration::from_secs(2);
+const DSX_MIN_INTERVAL: Duration = Duration::from_millis(16);
+const DSX_REPEAT_INTERVAL: Duration = Duration::from_secs(1);
+const MUTE_SETTINGS_POLL: Duration = Duration::from_secs(2);
+const MUTE_POLL: Duration = Duration::from_millis(100);
+const AUDIO_SYNC_INTERVAL: Duration = Duration::from_millis(500);
+const VIRTUAL_PAD_POLL: Duration = Duration::from_secs(1);
+const FF_POLL: Duration = Duration::from_millis(5);
 
 pub struct DaemonManager {
     client: Option<Arc<Mutex<IpcClient>>>,
@@ -54,53 +95,317 @@ impl DaemonManager {
             let _ = mlock(arc).set_update_mode(active);
         }
     }
+
+    pub fn report_firmware_progress(&self, progress: u32) {
+        if let Some(ref arc) = self.client {
+            let _ = mlock(arc).report_firmware_progress(progress);
+        }
+    }
 }
 
+type TriggerEffects = (Option<(u8, [u8; 10])>, Option<(u8, [u8; 10])>);
+
 struct DaemonInner {
     active_transform: InputTransform,
     active_effect: LightbarEffect,
+    effect_started: Instant,
     lightbar_color: (u8, u8, u8, u8),
-    player_leds: u8,
+    lightbar_bindings: Vec<LightbarBinding>,
+    reactive_lightbar: ReactiveLightbar,
+    effect_color: Option<(u8, u8, u8)>,
+    lightbar_metric: Option<MetricBinding>,
+    metric_color: Option<(u8, u8, u8)>,
+    player_led: PlayerLedConfig,
     mic_enabled: bool,
+    audio_levels: AudioLevels,
+    speaker_mode: SpeakerMode,
+    audio_routing: Option<AudioRouting>,
     active_profile_name: String,
     trigger_left: Option<(u8, [u8; 10])>,
     trigger_right: Option<(u8, [u8; 10])>,
-    haptic: (HapticPattern, u8, f32),
+    trigger_programs: [Option<TriggerProgram>; 2],
+    trigger_output: Option<TriggerEffects>,
+    haptic: (HapticPattern, u8, f32, f32),
+    custom_haptic: Option<Arc<CustomPattern>>,
     raw_haptics: bool,
+    rumble_scale: f32,
+    rumble_haptics: RumbleHaptics,
+    ff_rumble: (f32, f32),
+    clip: Option<ClipPlayer>,
+    buzz: Option<ClipPlayer>,
+    reactive: ReactiveConfig,
+    reactive_capture: Option<AudioReactive>,
+    visualizer: Option<AudioVisualizer>,
+    notification: Option<ActiveNotification>,
     gyro: GyroProcessor,
 }
 
+impl Default for DaemonInner {
+    fn default() -> Self {
+        Self {
+            active_transform: InputTransform::default(),
+            active_effect: LightbarEffect::None,
+            effect_started: Instant::now(),
+            lightbar_color: (0, 128, 255, 255),
+            lightbar_bindings: Vec::new(),
+            reactive_lightbar: ReactiveLightbar::default(),
+            effect_color: None,
+            lightbar_metric: None,
+            metric_color: None,
+            player_led: PlayerLedConfig::default(),
+            mic_enabled: false,
+            audio_levels: AudioLevels::default(),
+            speaker_mode: SpeakerMode::default(),
+            audio_routing: None,
+            active_profile_name: String::new(),
+            trigger_left: None,
+            trigger_right: None,
+            trigger_programs: [None, None],
+            trigger_output: None,
+            haptic: (HapticPattern::None, 0, 1.0, 0.0),
+            custom_haptic: None,
+            raw_haptics: false,
+            rumble_scale: 1.0,
+            rumble_haptics: RumbleHaptics::default(),
+            ff_rumble: (0.0, 0.0),
+            clip: None,
+            buzz: None,
+            reactive: ReactiveConfig::default(),
+            reactive_capture: None,
+            visualizer: None,
+            notification: None,
+            gyro: GyroProcessor::default(),
+        }
+    }
+}
+
+impl DaemonInner {
+    // A metric binding tints the lightbar without touching the profile colour.
+    fn base_color(&self) -> (u8, u8, u8, u8) {
+        let (r, g, b, br) = self.lightbar_color;
+        let (r, g, b) = self.metric_color.unwrap_or((r, g, b));
+        (r, g, b, br)
+    }
+
+    fn shows_base_color(&self) -> bool {
+        matches!(self.active_effect, LightbarEffect::None)
+            && self.lightbar_bindings.is_empty()
+            && self
+                .notification
+                .as_ref()
+                .is_none_or(|n| n.flash().is_none())
+    }
+}
+
+#[derive(Default)]
+struct InputSnapshot {
+    state: Option<ControllerState>,
+    battery: Option<BatteryInfo>,
+}
+
+struct Subscriber {
+    topics: Vec<EventTopic>,
+    tx: SyncSender<EventMessage>,
+}
+
+#[derive(Default)]
+struct EventBus {
+    subscribers: Mutex<Vec<Subscriber>>,
+}
+
+impl EventBus {
+    fn subscribe(&self, topics: &[EventTopic]) -> Receiver<EventMessage> {
+        let (tx, rx) = mpsc::sync_channel(EVENT_QUEUE);
+        mlock(&self.subscribers).push(Subscriber {
+            topics: topics.to_vec(),
+            tx,
+        });
+        rx
+    }
+
+    fn publish(&self, serial: Option<&str>, event: DaemonEvent) {
+        let topic = event.topic();
+        let msg = EventMessage {
+            serial: serial.map(str::to_string),
+            event,
+        };
+        // A subscriber that falls behind loses events rather than growing its queue.
+        mlock(&self.subscribers).retain(|s| {
+            !s.topics.contains(&topic)
+                || !matches!(
+                    s.tx.try_send(msg.clone()),
+                    Err(TrySendError::Disconnected(_))
+                )
+        });
+    }
+}
+
+struct Pad {
+    serial: String,
+    product_id: u16,
+    is_bt: bool,
+    connected: AtomicBool,
+    player: AtomicU8,
+    device: Mutex<DualSense>,
+    inner: RwLock<DaemonInner>,
+    latest: RwLock<InputSnapshot>,
+    usb_haptics: Mutex<UsbHapticStream>,
+    virtual_pad: RwLock<Option<Arc<VirtualPad>>>,
+    hid_path: String,
+    card: OnceLock<u32>,
+}
+
+impl Pad {
+    fn connected_event(&self) -> DaemonEvent {
+        DaemonEvent::Connected {
+            serial: self.serial.clone(),
+            product_id: self.product_id,
+            is_bt: self.is_bt,
+        }
+    }
+
+    fn summary(&self, settings: &Settings) -> ControllerSummary {
+        ControllerSummary {
+            serial: self.serial.clone(),
+            nickname: settings.nickname_for(&self.serial).to_string(),
+            product_id: self.product_id,
+            is_bt: self.is_bt,
+            profile: rlock(&self.inner).active_profile_name.clone(),
+            battery: rlock(&self.latest).battery.clone(),
+            player: self.player.load(Ordering::Relaxed),
+        }
+    }
+
+    fn sound_card(&self) -> Option<u32> {
+        if self.is_bt {
+            return None;
+        }
+        if let Some(card) = self.card.get() {
+            return Some(*card);
+        }
+        // The sound card can enumerate after hidraw, so keep looking until it shows up.
+        let card = find_card_for_hid(&self.hid_path)?;
+        Some(*self.card.get_or_init(|| card))
+    }
+
+    fn player_led_mask(&self) -> u8 {
+        match self.player.load(Ordering::Relaxed) {
+            0 => rlock(&self.inner).player_led.pattern,
+            slot => PLAYER_LED_PATTERNS[slot as usize],
+        }
+    }
+
+    fn speaker_mode(&self) -> SpeakerMode {
+        let jack = rlock(&self.latest).state.as_ref().map(|s| s.jack);
+        let inner = rlock(&self.inner);
+        match (inner.audio_routing, jack) {
+            (Some(routing), Some(jack)) => routing.mode_for(jack),
+            _ => inner.speaker_mode,
+        }
+    }
+
+    fn player_leds_overridden(&self, effect: &LightbarEffect) -> bool {
+        self.player.load(Ordering::Relaxed) != 0
+            || matches!(
+                effect,
+                LightbarEffect::Battery {
+                    player_leds: true,
+                    ..
+                }
+            )
+    }
+}
+
 struct DaemonState {
-    device: Mutex<Option<DualSense>>,
+    pads: RwLock<Vec<Arc<Pad>>>,
     update_in_progress: AtomicBool,
-    inner: RwLock<DaemonInner>,
+    mic_synced: AtomicBool,
+    firmware_progress: AtomicU32,
+    events: EventBus,
     hotplug: (Mutex<bool>, Condvar),
 }
 
 impl DaemonState {
     fn new() -> Arc<Self> {
         Arc::new(Self {
-            device: Mutex::new(None),
+            pads: RwLock::new(Vec::new()),
             update_in_progress: AtomicBool::new(false),
-            inner: RwLock::new(DaemonInner {
-                active_transform: InputTransform::default(),
-                active_effect: LightbarEffect::None,
-                lightbar_color: (0, 128, 255, 255),
-                player_leds: 1,
-                mic_enabled: false,
-                active_profile_name: String::new(),
-                trigger_left: None,
-                trigger_right: None,
-                haptic: (HapticPattern::None, 0, 1.0),
-                raw_haptics: false,
-                gyro: GyroProcessor::default(),
-            }),
+            mic_synced: AtomicBool::new(false),
+            firmware_progress: AtomicU32::new(0),
+            events: EventBus::default(),
             hotplug: (Mutex::new(false), Condvar::new()),
         })
     }
+
+    fn pads(&self) -> Vec<Arc<Pad>> {
+        rlock(&self.pads).clone()
+    }
+
+    fn pad(&self, serial: Option<&str>) -> Option<Arc<Pad>> {
+        let pads = rlock(&self.pads);
+        match serial {
+            Some(s) => pads.iter().find(|p| p.serial == s).cloned(),
+            None => pads.first().cloned(),
+        }
+    }
+
+    fn targets(&self, serial: Option<&str>) -> Vec<Arc<Pad>> {
+        match serial {
+            Some(s) => self.pad(Some(s)).into_iter().collect(),
+            None => self.pads(),
+        }
+    }
+
+    fn signal_hotplug(&self) {
+        let (l, c) = &self.hotplug;
+        *mlock(l) = true;
+        c.notify_all();
+    }
+
+    fn drop_pad(&self, serial: &str) {
+        let removed = {
+            let mut pads = wlock(&self.pads);
+            pads.iter()
+                .position(|p| p.serial == serial)
+                .map(|i| pads.remove(i))
+        };
+        if let Some(pad) = removed {
+            pad.connected.store(false, Ordering::SeqCst);
+            mlock(&pad.usb_haptics).shutdown();
+            wlock(&pad.virtual_pad).take();
+            let (capture, visualizer) = {
+                let mut inner = wlock(&pad.inner);
+                (inner.reactive_capture.take(), inner.visualizer.take())
+            };
+            drop((capture, visualizer));
+            println!("{} controller disconnected: {}", TAG, serial);
+            self.events.publish(Some(serial), DaemonEvent::Disconnected);
+            assign_player_slots(self);
+        }
+        self.signal_hotplug();
+    }
+
+    fn drop_all(&self) {
+        for pad in self.pads() {
+            self.drop_pad(&pad.serial);
+        }
+    }
 }
 
-fn apply_profile_to_state(state: &Arc<DaemonState>, name: &str) -> String {
+fn default_profile_name(serial: Option<&str>) -> String {
+    let settings = SettingsManager::new().load();
+    let name = match serial {
+        Some(s) => settings.profile_for(s),
+        None => &settings.profile,
+    };
+    if name.is_empty() {
+        "Default".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+fn apply_profile_to_pad(pad: &Pad, name: &str) -> String {
     let pm = ProfileManager::new();
     let profile = if pm.profile_exists(name) {
         pm.load_profile(name).ok()
@@ -114,15 +419,51 @@ fn apply_profile_to_state(state: &Arc<DaemonState>, name: &str) -> String {
         return String::new();
     };
 
-    let mut inner = wlock(&state.inner);
+    let mut inner = wlock(&pad.inner);
     inner.active_transform = p.to_input_transform();
     let r = (p.lightbar_r * 255.0) as u8;
     let g = (p.lightbar_g * 255.0) as u8;
     let b = (p.lightbar_b * 255.0) as u8;
     let br = p.lightbar_brightness as u8;
     inner.lightbar_color = (r, g, b, br);
-    inner.player_leds = p.player_leds;
+    inner.lightbar_bindings = p.lightbar_bindings.clone();
+    inner.effect_color = None;
+    inner.lightbar_metric = p.lightbar_metric.clone();
+    inner.metric_color = None;
+    inner.player_led = p.to_player_led_config();
     inner.mic_enabled = p.mic_enabled;
+    inner.audio_levels = p.audio_levels;
+    inner.speaker_mode = p.speaker_mode;
+    inner.audio_routing = p.audio_routing;
+    match p.lightbar_animation.as_str() {
+        "" => {
+            if matches!(inner.active_effect, LightbarEffect::Custom { .. }) {
+                inner.active_effect = LightbarEffect::None;
+            }
+        }
+        name => {
+            let playing = matches!(
+                &inner.active_effect,
+                LightbarEffect::Custom { animation } if animation.name == name
+            );
+            if !playing && let Ok(animation) = AnimationLibrary::new().load(name) {
+                inner.active_effect = LightbarEffect::Custom { animation };
+                inner.effect_started = Instant::now();
+            }
+        }
+    }
+    match &p.lightbar_visualizer {
+        Some(config) => {
+            inner.active_effect = LightbarEffect::Visualizer {
+                config: config.clone(),
+            };
+        }
+        None => {
+            if matches!(inner.active_effect, LightbarEffect::Visualizer { .. }) {
+                inner.active_effect = LightbarEffect::None;
+            }
+        }
+    }
 
     use crate::common::TriggerMode;
     inner.trigger_left = match p.trigger_left_config.mode {
@@ -133,22 +474,198 @@ fn apply_profile_to_state(state: &Arc<DaemonState>, name: &str) -> String {
         TriggerMode::Off => None,
         _ => Some(p.trigger_right_config.to_effect()),
     };
-
-    inner.haptic = (p.haptic_pattern, p.haptic_strength, p.haptic_speed);
+    inner.trigger_programs = [
+        p.trigger_left_program.clone(),
+        p.trigger_right_program.clone(),
+    ];
+    inner.trigger_output = None;
+
+    inner.haptic = (
+        p.haptic_pattern,
+        p.haptic_strength,
+        p.haptic_speed,
+        p.haptic_balance,
+    );
+    inner.custom_haptic = match p.haptic_custom.as_str() {
+        "" => None,
+        name => PatternLibrary::new().load(name).ok().map(Arc::new),
+    };
+    inner.reactive = p.audio_reactive.clone();
+    inner.rumble_scale = p.rumble_scale.max(0.0);
+    inner.rumble_haptics = p.rumble_haptics;
     inner.gyro = p.to_gyro_processor();
+    inner.active_profile_name = p.name.clone();
+    drop(inner);
+
+    if let Err(e) = sync_audio_reactive(pad) {
+        eprintln!("{} audio reactive haptics on {}: {:#}", TAG, pad.serial, e);
+    }
+    sync_visualizer(pad);
+    p.name
+}
+
+fn sync_visualizer(pad: &Pad) {
+    // Like reactive captures, a stopped visualizer is dropped after the lock is released.
+    let old = {
+        let mut inner = wlock(&pad.inner);
+        match inner.active_effect.clone() {
+            LightbarEffect::Visualizer { config } => {
+                let reuse = inner
+                    .visualizer
+                    .as_ref()
+                    .is_some_and(|v| v.is_running() && v.device() == config.device);
+                if reuse {
+                    if let Some(v) = &inner.visualizer {
+                        v.set_config(config);
+                    }
+                    None
+                } else {
+                    println!(
+                        "{} lightbar visualizer from '{}' on {}",
+                        TAG, config.device, pad.serial
+                    );
+                    inner.visualizer.replace(AudioVisualizer::start(config))
+                }
+            }
+            _ => inner.visualizer.take(),
+        }
+    };
+    drop(old);
+}
+
+fn sync_audio_reactive(pad: &Pad) -> Result<()> {
+    let config = rlock(&pad.inner).reactive.clone();
+
+    if pad.is_bt {
+        // Dropping a capture joins a thread that may sit in readi; never do that under the lock.
+        let mut result = Ok(());
+        let old = {
+            let mut inner = wlock(&pad.inner);
+            let reuse = inner
+                .reactive_capture
+                .as_ref()
+                .is_some_and(|c| config.enabled && c.is_running() && c.device() == config.device);
+            if reuse {
+                if let Some(c) = &inner.reactive_capture {
+                    c.set_config(config);
+                }
+                None
+            } else if config.enabled {
+                println!(
+                    "{} audio reactive haptics from '{}' on {}",
+                    TAG, config.device, pad.serial
+                );
+                match AudioReactive::start(config, HAPTICS_SAMPLE_RATE) {
+                    Ok(capture) => inner.reactive_capture.replace(capture),
+                    Err(e) => {
+                        result = Err(e);
+                        inner.reactive_capture.take()
+                    }
+                }
+            } else {
+                inner.reactive_capture.take()
+            }
+        };
+        drop(old);
+        return result;
+    }
 
-    p.name.clone()
+    let mut usb = mlock(&pad.usb_haptics);
+    if !config.enabled {
+        if usb.is_reactive() {
+            usb.stop();
+        }
+        return Ok(());
+    }
+    if usb.update_reactive(&config) {
+        return Ok(());
+    }
+    let Some(card) = pad.sound_card() else {
+        bail!("No USB audio device for {}", pad.serial);
+    };
+    println!(
+        "{} audio reactive haptics from '{}' on {}",
+        TAG, config.device, pad.serial
+    );
+    let capture = AudioReactive::start(config, USB_HAPTICS_RATE)?;
+    usb.start(card, HapticSource::Reactive(capture));
+    Ok(())
 }
 
-fn push_triggers_to_device(state: &Arc<DaemonState>) {
+fn push_triggers_to_device(pad: &Pad) {
     let (left, right) = {
-        let inner = rlock(&state.inner);
+        let mut inner = wlock(&pad.inner);
+        inner.trigger_output = None;
         (inner.trigger_left, inner.trigger_right)
     };
-    if let Some(ds) = mlock(&state.device).as_mut() {
-        let l = left.or(Some((0x05, [0u8; 10])));
-        let r = right.or(Some((0x05, [0u8; 10])));
-        let _ = ds.set_trigger_effects(l, r);
+    let l = left.or(Some((0x05, [0u8; 10])));
+    let r = right.or(Some((0x05, [0u8; 10])));
+    let _ = mlock(&pad.device).set_trigger_effects(l, r);
+}
+
+fn push_outputs_to_device(pad: &Pad) {
+    let (color, mic, led_brightness, levels) = {
+        let i = rlock(&pad.inner);
+        (
+            i.base_color(),
+            i.mic_enabled,
+            i.player_led.brightness,
+            i.audio_levels,
+        )
+    };
+    let leds = pad.player_led_mask();
+    let speaker = pad.speaker_mode();
+    let (r, g, b, br) = color;
+    {
+        let mut ds = mlock(&pad.device);
+        let _ = ds.set_lightbar(r, g, b, br);
+        let _ = ds.set_player_led_brightness(led_brightness);
+        let _ = ds.set_player_led_mask(leds);
+        let _ = ds.set_mic(mic);
+        let _ = ds.set_audio_levels(levels);
+        let _ = ds.set_speaker(speaker);
+    }
+    push_triggers_to_device(pad);
+}
+
+fn assign_player_slots(state: &DaemonState) {
+    let settings = SettingsManager::new().load();
+    let pads = state.pads();
+
+    let mut wanted: Vec<u8> = pads
+        .iter()
+        .map(|pad| match settings.player_slots {
+            PlayerSlotMode::Profile => 0,
+            PlayerSlotMode::Order => pad.player.load(Ordering::Relaxed),
+            PlayerSlotMode::Sticky => settings
+                .controllers
+                .get(&pad.serial)
+                .map_or(0, |b| b.player.min(MAX_PLAYER_SLOT)),
+        })
+        .collect();
+
+    for i in 0..wanted.len() {
+        if wanted[i] != 0 && wanted[..i].contains(&wanted[i]) {
+            wanted[i] = 0;
+        }
+    }
+    if settings.player_slots != PlayerSlotMode::Profile {
+        for i in 0..wanted.len() {
+            if wanted[i] == 0 {
+                wanted[i] = (1..=MAX_PLAYER_SLOT)
+                    .find(|s| !wanted.contains(s))
+                    .unwrap_or(MAX_PLAYER_SLOT);
+            }
+        }
+    }
+
+    for (pad, slot) in pads.iter().zip(wanted) {
+        if pad.player.swap(slot, Ordering::Relaxed) != slot {
+            let _ = mlock(&pad.device).set_player_led_mask(pad.player_led_mask());
+            if slot != 0 {
+                println!("{} {} is player {}", TAG, pad.serial, slot);
+            }
+        }
     }
 }
 
@@ -164,19 +681,6 @@ pub fn run_daemon() {
 
     let state = DaemonState::new();
 
-    {
-        let sm = SettingsManager::new();
-        let settings = sm.load();
-        let name = if settings.profile.is_empty() {
-            "Default"
-        } else {
-            &settings.profile
-        };
-        let loaded = apply_profile_to_state(&state, name);
-        wlock(&state.inner).active_profile_name = loaded.clone();
-        println!("{} profile '{}' loaded", TAG, loaded);
-    }
-
     {
         let s = Arc::clone(&state);
         thread::spawn(move || device_connection_loop(s));
@@ -202,6 +706,31 @@ pub fn run_daemon() {
         thread::spawn(move || raw_haptic_loop(s));
     }
 
+    {
+        let s = Arc::clone(&state);
+        thread::spawn(move || metric_loop(s));
+    }
+
+    {
+        let s = Arc::clone(&state);
+        thread::spawn(move || dsx_loop(s));
+    }
+
+    {
+        let s = Arc::clone(&state);
+        thread::spawn(move || mute_loop(s));
+    }
+
+    {
+        let s = Arc::clone(&state);
+        thread::spawn(move || audio_sync_loop(s));
+    }
+
+    {
+        let s = Arc::clone(&state);
+        thread::spawn(move || virtual_pad_loop(s));
+    }
+
     for stream in listener.incoming() {
         match stream {
             Ok(s) => {
@@ -215,36 +744,17 @@ pub fn run_daemon() {
 
 fn device_connection_loop(state: Arc<DaemonState>) {
     loop {
-        if !state.update_in_progress.load(Ordering::Relaxed) {
-            let mut dev = mlock(&state.device);
-            if dev.is_none()
-                && let Ok(api) = HidApi::new()
-                && let Ok(mut ds) = DualSense::new(&api, None)
-            {
-                println!("{} controller connected: {}", TAG, ds.serial());
-
-                let snap = {
-                    let i = rlock(&state.inner);
-                    (
-                        i.lightbar_color,
-                        i.player_leds,
-                        i.mic_enabled,
-                        i.trigger_left,
-                        i.trigger_right,
-                    )
-                };
-                let ((r, g, b, br), leds, mic, tl, tr) = snap;
-                let _ = ds.set_lightbar(r, g, b, br);
-                let _ = ds.set_player_leds(leds);
-                let _ = ds.set_mic(mic);
-                let l = tl.or(Some((0x05, [0u8; 10])));
-                let r2 = tr.or(Some((0x05, [0u8; 10])));
-                let _ = ds.set_trigger_effects(l, r2);
-
-                *dev = Some(ds);
+        if !state.update_in_progress.load(Ordering::Relaxed)
+            && let Ok(api) = HidApi::new()
+        {
+            for serial in list_serials(&api) {
+                if state.pad(Some(&serial)).is_some() {
+                    continue;
+                }
+                if let Ok(ds) = DualSense::new(&api, Some(&serial)) {
+                    connect_pad(&state, &api, ds);
+                }
             }
-
-            drop(dev);
         }
 
         let (lock, cvar) = &state.hotplug;
@@ -257,6 +767,55 @@ fn device_connection_loop(state: Arc<DaemonState>) {
     }
 }
 
+fn connect_pad(state: &Arc<DaemonState>, api: &HidApi, ds: DualSense) {
+    let serial = ds.serial().to_string();
+    let reader = match ds.input_reader(api) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{} failed to open input handle for {}: {}", TAG, serial, e);
+            return;
+        }
+    };
+    println!("{} controller connected: {}", TAG, serial);
+
+    // Prime the battery so clients attaching right away don't see NoDevice.
+    let battery = match reader.read_input(100) {
+        Ok(Some((_, b))) => Some(b),
+        _ => None,
+    };
+
+    let pad = Arc::new(Pad {
+        serial,
+        product_id: ds.product_id(),
+        is_bt: ds.is_bluetooth(),
+        hid_path: ds.hid_path().to_string(),
+        card: OnceLock::new(),
+        connected: AtomicBool::new(true),
+        player: AtomicU8::new(0),
+        device: Mutex::new(ds),
+        inner: RwLock::new(DaemonInner::default()),
+        latest: RwLock::new(InputSnapshot {
+            state: None,
+            battery,
+        }),
+        usb_haptics: Mutex::new(UsbHapticStream::new()),
+        virtual_pad: RwLock::new(None),
+    });
+
+    let loaded = apply_profile_to_pad(&pad, &default_profile_name(Some(&pad.serial)));
+    println!("{} profile '{}' loaded for {}", TAG, loaded, pad.serial);
+
+    wlock(&state.pads).push(Arc::clone(&pad));
+    assign_player_slots(state);
+    push_outputs_to_device(&pad);
+    state
+        .events
+        .publish(Some(&pad.serial), pad.connected_event());
+
+    let s = Arc::clone(state);
+    thread::spawn(move || input_loop(s, pad, reader));
+}
+
 fn hotplug_thread(state: Arc<DaemonState>) {
     use std::os::fd::AsRawFd;
 
@@ -308,6 +867,161 @@ fn hotplug_thread(state: Arc<DaemonState>) {
     }
 }
 
+fn input_loop(state: Arc<DaemonState>, pad: Arc<Pad>, reader: InputReader) {
+    let mut last_buttons: Option<(u32, u8)> = None;
+    while pad.connected.load(Ordering::Relaxed) {
+        if state.update_in_progress.load(Ordering::Relaxed) {
+            sleep(Duration::from_millis(100));
+            continue;
+        }
+
+        let read = reader.read_input(8);
+
+        match read {
+            Ok(Some((mut input, battery))) => {
+                let (dynamic, lightbar) = {
+                    let mut inner = wlock(&pad.inner);
+                    inner.active_transform.apply(&mut input);
+                    inner.gyro.process(&mut input.gyro);
+                    (
+                        evaluate_trigger_programs(&mut inner, &input),
+                        evaluate_lightbar_bindings(&mut inner, &input),
+                    )
+                };
+                if let Some((left, right)) = dynamic {
+                    let off = Some((DS_TRIGGER_EFFECT_OFF, [0u8; 10]));
+                    let _ = mlock(&pad.device).set_trigger_effects(left.or(off), right.or(off));
+                }
+                if let Some((r, g, b, br)) = lightbar {
+                    let _ = mlock(&pad.device).set_lightbar(r, g, b, br);
+                }
+                let virtual_pad = rlock(&pad.virtual_pad).clone();
+                if let Some(virtual_pad) = virtual_pad {
+                    let _ = virtual_pad.emit(&input);
+                }
+
+                let (buttons, dpad) = (input.buttons, input.dpad);
+                let prev = last_buttons.replace((buttons, dpad));
+                if prev != Some((buttons, dpad)) {
+                    let prev_buttons = prev.map_or(0, |(b, _)| b);
+                    state.events.publish(
+                        Some(&pad.serial),
+                        DaemonEvent::Buttons {
+                            buttons,
+                            dpad,
+                            pressed: buttons & !prev_buttons,
+                            released: prev_buttons & !buttons,
+                        },
+                    );
+                }
+
+                let jack = input.jack;
+                let (battery_changed, jack_changed) = {
+                    let mut latest = wlock(&pad.latest);
+                    let jack_changed = latest.state.as_ref().map(|s| s.jack) != Some(jack);
+                    latest.state = Some(input);
+                    let changed = latest.battery.as_ref() != Some(&battery);
+                    latest.battery = Some(battery.clone());
+                    (changed, jack_changed)
+                };
+                if battery_changed {
+                    state
+                        .events
+                        .publish(Some(&pad.serial), DaemonEvent::Battery(battery));
+                }
+                if jack_changed {
+                    if rlock(&pad.inner).audio_routing.is_some() {
+                        let mode = pad.speaker_mode();
+                        println!(
+                            "{} {}: headphones {}, routing audio to {}",
+                            TAG,
+                            pad.serial,
+                            if jack.headphones {
+                                "plugged"
+                            } else {
+                                "unplugged"
+                            },
+                            mode.label()
+                        );
+                        let _ = mlock(&pad.device).set_speaker(mode);
+                    }
+                    state
+                        .events
+                        .publish(Some(&pad.serial), DaemonEvent::Jack(jack));
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                if state.update_in_progress.load(Ordering::Relaxed) {
+                    continue;
+                }
+                println!(
+                    "{} read error on {}: {} - dropping handle",
+                    TAG, pad.serial, e
+                );
+                state.drop_pad(&pad.serial);
+                return;
+            }
+        }
+    }
+}
+
+fn evaluate_trigger_programs(
+    inner: &mut DaemonInner,
+    input: &ControllerState,
+) -> Option<TriggerEffects> {
+    let eval = |program: &Option<TriggerProgram>, base, travel| match program {
+        Some(p) => p.evaluate(base, travel, input.buttons),
+        None => base,
+    };
+    let output = match &inner.trigger_programs {
+        [None, None] => None,
+        [left, right] => Some((
+            eval(left, inner.trigger_left, input.l2),
+            eval(right, inner.trigger_right, input.r2),
+        )),
+    };
+    if output == inner.trigger_output {
+        return None;
+    }
+    let previous = std::mem::replace(&mut inner.trigger_output, output);
+    match output {
+        Some(effects) => Some(effects),
+        None if previous.is_some() => Some((inner.trigger_left, inner.trigger_right)),
+        None => None,
+    }
+}
+
+fn evaluate_lightbar_bindings(
+    inner: &mut DaemonInner,
+    input: &ControllerState,
+) -> Option<(u8, u8, u8, u8)> {
+    let (r, g, b, br) = inner.base_color();
+    if inner
+        .notification
+        .as_ref()
+        .is_some_and(|n| n.flash().is_some())
+    {
+        inner.reactive_lightbar.interrupt();
+        return None;
+    }
+    if inner.lightbar_bindings.is_empty() {
+        if !inner.reactive_lightbar.active() {
+            return None;
+        }
+        inner.reactive_lightbar.reset();
+        return matches!(inner.active_effect, LightbarEffect::None).then_some((r, g, b, br));
+    }
+    let base = inner.effect_color.unwrap_or((r, g, b));
+    let (r, g, b) = inner
+        .reactive_lightbar
+        .evaluate(&inner.lightbar_bindings, input, base);
+    inner
+        .reactive_lightbar
+        .should_send((r, g, b), LIGHTBAR_REACT_INTERVAL)
+        .then_some((r, g, b, br))
+}
+
 fn handle_client(stream: DaemonStream, state: Arc<DaemonState>) {
     let write_half = match stream.try_clone() {
         Ok(s) => s,
@@ -332,8 +1046,8 @@ fn handle_client(stream: DaemonStream, state: Arc<DaemonState>) {
             Ok(_) => {}
         }
 
-        let cmd: DaemonCommand = match serde_json::from_str(line.trim()) {
-            Ok(c) => c,
+        let DaemonRequest { serial, cmd } = match serde_json::from_str(line.trim()) {
+            Ok(r) => r,
             Err(e) => {
                 send(
                     &mut writer,
@@ -344,10 +1058,12 @@ fn handle_client(stream: DaemonStream, state: Arc<DaemonState>) {
                 continue;
             }
         };
+        let target = serial.as_deref();
 
         match cmd {
             DaemonCommand::Ping => {
                 send(&mut writer, DaemonResponse::Pong);
+                continue;
             }
 
             DaemonCommand::Shutdown => {
@@ -356,144 +1072,116 @@ fn handle_client(stream: DaemonStream, state: Arc<DaemonState>) {
                 std::process::exit(0);
             }
 
+            DaemonCommand::ListControllers => {
+                let settings = SettingsManager::new().load();
+                let controllers = state.pads().iter().map(|p| p.summary(&settings)).collect();
+                send(&mut writer, DaemonResponse::ControllerList { controllers });
+                continue;
+            }
+
             DaemonCommand::SetUpdateMode { active } => {
                 if active {
                     state.update_in_progress.store(true, Ordering::SeqCst);
-                    *mlock(&state.device) = None;
-                    println!("{} device released for firmware update", TAG);
+                    state.firmware_progress.store(0, Ordering::Relaxed);
+                    state.drop_all();
+                    println!("{} devices released for firmware update", TAG);
                 } else {
                     state.update_in_progress.store(false, Ordering::SeqCst);
-                    println!("{} firmware update done, device will reconnect", TAG);
+                    state.signal_hotplug();
+                    println!("{} firmware update done, devices will reconnect", TAG);
                 }
+                state.events.publish(
+                    None,
+                    DaemonEvent::Firmware {
+                        active,
+                        progress: state.firmware_progress.load(Ordering::Relaxed),
+                    },
+                );
                 send(&mut writer, DaemonResponse::Ok);
+                continue;
             }
 
-            DaemonCommand::SetInputTransform { transform } => {
-                wlock(&state.inner).active_transform = transform;
+            DaemonCommand::ReportFirmwareProgress { progress } => {
+                let progress = progress.min(100);
+                state.firmware_progress.store(progress, Ordering::Relaxed);
+                state.events.publish(
+                    None,
+                    DaemonEvent::Firmware {
+                        active: state.update_in_progress.load(Ordering::Relaxed),
+                        progress,
+                    },
+                );
                 send(&mut writer, DaemonResponse::Ok);
+                continue;
             }
 
-            DaemonCommand::ClearInputTransform => {
-                wlock(&state.inner).active_transform = InputTransform::default();
+            DaemonCommand::Subscribe {
+                topics,
+                input_rate_hz,
+            } => {
                 send(&mut writer, DaemonResponse::Ok);
-            }
-
-            DaemonCommand::SetLightbarEffect { effect } => {
-                let restoring = matches!(effect, LightbarEffect::None);
-                wlock(&state.inner).active_effect = effect;
-
-                if restoring {
-                    let (r, g, b, br) = rlock(&state.inner).lightbar_color;
-                    if let Some(ds) = mlock(&state.device).as_mut() {
-                        let _ = ds.set_lightbar(r, g, b, br);
-                    }
+                let closed = Arc::new(AtomicBool::new(false));
+                if let Ok(mut peer) = reader.get_ref().try_clone() {
+                    let closed = Arc::clone(&closed);
+                    thread::spawn(move || {
+                        let mut buf = [0u8; 64];
+                        while matches!(peer.read(&mut buf), Ok(n) if n > 0) {}
+                        closed.store(true, Ordering::Relaxed);
+                    });
                 }
-                send(&mut writer, DaemonResponse::Ok);
+                stream_events(&mut writer, &state, target, &topics, input_rate_hz, &closed);
+                return;
             }
 
-            DaemonCommand::SetHapticPattern {
-                pattern,
-                strength,
-                speed,
-            } => {
-                let restoring = matches!(pattern, HapticPattern::None);
-                wlock(&state.inner).haptic = (pattern, strength.min(7), speed.max(0.05));
-                if restoring && let Some(ds) = mlock(&state.device).as_mut() {
-                    let _ = ds.set_vibration(0, 0);
+            DaemonCommand::SwitchProfile { name } => {
+                let mut pads = state.targets(target);
+                if target.is_some() && pads.is_empty() {
+                    send(&mut writer, DaemonResponse::NoDevice);
+                    continue;
+                }
+                if target.is_none() {
+                    // An untargeted switch changes the default profile; controllers bound
+                    // to their own profile keep it.
+                    let sm = SettingsManager::new();
+                    let mut settings = sm.load();
+                    settings.profile = name.clone();
+                    sm.save(&settings);
+                    pads.retain(|p| settings.follows_default_profile(&p.serial));
+                }
+                for pad in pads {
+                    let loaded = apply_profile_to_pad(&pad, &name);
+                    push_outputs_to_device(&pad);
+                    println!("{} switched {} to profile '{}'", TAG, pad.serial, loaded);
+                    state.events.publish(
+                        Some(&pad.serial),
+                        DaemonEvent::ProfileSwitched { name: loaded },
+                    );
                 }
                 send(&mut writer, DaemonResponse::Ok);
+                continue;
             }
 
-            DaemonCommand::SetRawHaptics { active } => {
-                wlock(&state.inner).raw_haptics = active;
+            DaemonCommand::ReloadProfile => {
+                for pad in state.targets(target) {
+                    let name = rlock(&pad.inner).active_profile_name.clone();
+                    let name = if name.is_empty() {
+                        "Default".to_string()
+                    } else {
+                        name
+                    };
+                    apply_profile_to_pad(&pad, &name);
+                    push_triggers_to_device(&pad);
+                    println!("{} reloaded profile '{}' for {}", TAG, name, pad.serial);
+                }
                 send(&mut writer, DaemonResponse::Ok);
+                continue;
             }
 
-            DaemonCommand::SetGyro {
-                enabled,
-                smoothing,
-                sensitivity,
-            } => {
-                let mut inner = wlock(&state.inner);
-                let g = &mut inner.gyro;
-                g.enabled = enabled;
-                g.smoothing = smoothing.clamp(0.0, 0.95);
-                g.sensitivity = sensitivity.max(0.0);
-                send(&mut writer, DaemonResponse::Ok);
-            }
-
-            DaemonCommand::SetTriggerEffects { left, right } => {
-                {
-                    let mut inner = wlock(&state.inner);
-                    if let Some(l) = left {
-                        inner.trigger_left = Some(l);
-                    }
-                    if let Some(r) = right {
-                        inner.trigger_right = Some(r);
-                    }
-                }
-                if state.update_in_progress.load(Ordering::Relaxed) {
-                    send(
-                        &mut writer,
-                        DaemonResponse::Error {
-                            message: "Firmware update in progress".to_string(),
-                        },
-                    );
-                    continue;
-                }
-                let (l, r) = {
-                    let i = rlock(&state.inner);
-                    (i.trigger_left, i.trigger_right)
-                };
-                if let Some(ds) = mlock(&state.device).as_mut() {
-                    let lf = l.or(Some((0x05, [0u8; 10])));
-                    let rt = r.or(Some((0x05, [0u8; 10])));
-                    let _ = ds.set_trigger_effects(lf, rt);
-                }
-                send(&mut writer, DaemonResponse::Ok);
-            }
-
-            DaemonCommand::SwitchProfile { name } => {
-                let loaded = apply_profile_to_state(&state, &name);
-                wlock(&state.inner).active_profile_name = loaded.clone();
-                let sm = SettingsManager::new();
-                let mut settings = sm.load();
-                settings.profile = loaded.clone();
-                sm.save(&settings);
-                {
-                    let (color, leds, mic) = {
-                        let i = rlock(&state.inner);
-                        (i.lightbar_color, i.player_leds, i.mic_enabled)
-                    };
-                    let (r, g, b, br) = color;
-                    if let Some(ds) = mlock(&state.device).as_mut() {
-                        let _ = ds.set_lightbar(r, g, b, br);
-                        let _ = ds.set_player_leds(leds);
-                        let _ = ds.set_mic(mic);
-                    }
-                }
-                push_triggers_to_device(&state);
-                println!("{} switched to profile '{}'", TAG, loaded);
-                send(&mut writer, DaemonResponse::Ok);
-            }
-
-            DaemonCommand::ReloadProfile => {
-                let name = rlock(&state.inner).active_profile_name.clone();
-                let name = if name.is_empty() {
-                    "Default".to_string()
-                } else {
-                    name
-                };
-                apply_profile_to_state(&state, &name);
-                push_triggers_to_device(&state);
-                println!("{} reloaded profile '{}'", TAG, name);
-                send(&mut writer, DaemonResponse::Ok);
-            }
-
-            DaemonCommand::ListProfiles => {
-                let pm = ProfileManager::new();
-                let profiles = pm.list_profiles().into_iter().map(|p| p.name).collect();
-                send(&mut writer, DaemonResponse::ProfileList { profiles });
+            DaemonCommand::ListProfiles => {
+                let pm = ProfileManager::new();
+                let profiles = pm.list_profiles().into_iter().map(|p| p.name).collect();
+                send(&mut writer, DaemonResponse::ProfileList { profiles });
+                continue;
             }
 
             DaemonCommand::SaveProfile { profile } => {
@@ -507,6 +1195,7 @@ fn handle_client(stream: DaemonStream, state: Arc<DaemonState>) {
                         },
                     ),
                 }
+                continue;
             }
 
             DaemonCommand::DeleteProfile { name } => {
@@ -520,268 +1209,1383 @@ fn handle_client(stream: DaemonStream, state: Arc<DaemonState>) {
                         },
                     ),
                 }
+                continue;
             }
 
-            DaemonCommand::GetActiveProfile => {
-                let name = rlock(&state.inner).active_profile_name.clone();
-                send(&mut writer, DaemonResponse::ActiveProfile { name });
+            DaemonCommand::ReassignPlayers => {
+                assign_player_slots(&state);
+                send(&mut writer, DaemonResponse::Ok);
+                continue;
             }
 
-            cmd @ (DaemonCommand::GetBattery
-            | DaemonCommand::GetInputState
-            | DaemonCommand::GetFirmwareInfo
-            | DaemonCommand::GetControllerInfo) => {
-                let transform = rlock(&state.inner).active_transform.clone();
-                let mut dev = mlock(&state.device);
-                match dev.as_mut() {
-                    None => send(&mut writer, DaemonResponse::NoDevice),
-                    Some(ds) => {
-                        let resp = dispatch(ds, cmd, &transform);
-                        send(&mut writer, resp);
-                    }
-                }
+            DaemonCommand::GetActiveProfile => {
+                let name = match state.pad(target) {
+                    Some(pad) => rlock(&pad.inner).active_profile_name.clone(),
+                    None => default_profile_name(None),
+                };
+                send(&mut writer, DaemonResponse::ActiveProfile { name });
+                continue;
             }
 
             cmd => {
-                if state.update_in_progress.load(Ordering::Relaxed) {
-                    send(
-                        &mut writer,
-                        DaemonResponse::Error {
-                            message: "Firmware update in progress".to_string(),
-                        },
-                    );
+                let Some(pad) = state.pad(target) else {
+                    state.signal_hotplug();
+                    send(&mut writer, DaemonResponse::NoDevice);
                     continue;
-                }
+                };
+                let resp = handle_pad_command(&state, &pad, cmd);
+                send(&mut writer, resp);
+            }
+        }
+    }
+}
 
-                if let DaemonCommand::SetLightbar {
-                    r,
-                    g,
-                    b,
-                    brightness,
-                } = &cmd
-                {
-                    let mut inner = wlock(&state.inner);
-                    inner.lightbar_color = (*r, *g, *b, *brightness);
-                    if !matches!(inner.active_effect, LightbarEffect::None) {
-                        send(&mut writer, DaemonResponse::Ok);
-                        continue;
-                    }
+fn handle_pad_command(state: &DaemonState, pad: &Pad, cmd: DaemonCommand) -> DaemonResponse {
+    match cmd {
+        DaemonCommand::GetInputState => {
+            return match rlock(&pad.latest).state.clone() {
+                Some(s) => DaemonResponse::InputState(s),
+                None => DaemonResponse::NoDevice,
+            };
+        }
+
+        DaemonCommand::GetBattery => {
+            return match rlock(&pad.latest).battery.clone() {
+                Some(b) => DaemonResponse::Battery(b),
+                None => DaemonResponse::NoDevice,
+            };
+        }
+
+        cmd @ (DaemonCommand::GetFirmwareInfo | DaemonCommand::GetControllerInfo) => {
+            return dispatch(&mut mlock(&pad.device), cmd);
+        }
+
+        DaemonCommand::SetInputTransform { transform } => {
+            wlock(&pad.inner).active_transform = transform;
+            return DaemonResponse::Ok;
+        }
+
+        DaemonCommand::ClearInputTransform => {
+            wlock(&pad.inner).active_transform = InputTransform::default();
+            return DaemonResponse::Ok;
+        }
+
+        DaemonCommand::SetLightbarEffect { effect } => {
+            let restoring = matches!(effect, LightbarEffect::None);
+            {
+                let mut inner = wlock(&pad.inner);
+                let continuing = match (&inner.active_effect, &effect) {
+                    (
+                        LightbarEffect::Custom { animation: a },
+                        LightbarEffect::Custom { animation: b },
+                    ) => a.name == b.name,
+                    _ => false,
+                };
+                inner.active_effect = effect;
+                inner.effect_color = None;
+                if !continuing {
+                    inner.effect_started = Instant::now();
                 }
+            }
+            sync_visualizer(pad);
 
-                if let DaemonCommand::SetTriggerEffect {
-                    left,
-                    right,
-                    effect_type,
-                    params,
-                } = &cmd
+            if restoring {
+                let (r, g, b, br) = rlock(&pad.inner).base_color();
+                let _ = mlock(&pad.device).set_lightbar(r, g, b, br);
+            }
+            return DaemonResponse::Ok;
+        }
+
+        DaemonCommand::SetHapticPattern {
+            pattern,
+            strength,
+            speed,
+            balance,
+        } => {
+            let restoring = matches!(pattern, HapticPattern::None);
+            wlock(&pad.inner).haptic = (
+                pattern,
+                strength.min(7),
+                speed.max(0.05),
+                balance.clamp(-1.0, 1.0),
+            );
+            if restoring {
+                let _ = mlock(&pad.device).set_vibration(0, 0);
+            }
+            return DaemonResponse::Ok;
+        }
+
+        DaemonCommand::SetCustomHaptic { pattern } => {
+            wlock(&pad.inner).custom_haptic = pattern.map(Arc::new);
+            return DaemonResponse::Ok;
+        }
+
+        DaemonCommand::SetRawHaptics { active } => {
+            wlock(&pad.inner).raw_haptics = active;
+            return DaemonResponse::Ok;
+        }
+
+        DaemonCommand::Notify { notification } => {
+            if let Err(e) = notification.validate() {
+                return DaemonResponse::Error {
+                    message: e.to_string(),
+                };
+            }
+            let buzz = notification.buzz;
+            let duration_ms = notification.duration_ms;
+            {
+                let mut inner = wlock(&pad.inner);
+                if let Some(active) = &inner.notification
+                    && !active.expired()
+                    && active.priority() > notification.priority
                 {
-                    let mut inner = wlock(&state.inner);
-                    let new_val = if *effect_type == 0x05 {
-                        None
-                    } else {
-                        Some((*effect_type, *params))
+                    return DaemonResponse::Error {
+                        message: "A higher-priority notification is already showing".to_string(),
                     };
-                    if *left {
-                        inner.trigger_left = new_val;
-                    }
-                    if *right {
-                        inner.trigger_right = new_val;
-                    }
-                } else if let DaemonCommand::SetTriggerOff = &cmd {
-                    let mut inner = wlock(&state.inner);
-                    inner.trigger_left = None;
-                    inner.trigger_right = None;
-                }
-
-                let transform = rlock(&state.inner).active_transform.clone();
-                let mut dev = mlock(&state.device);
-                match dev.as_mut() {
-                    None => {
-                        let (l, c) = &state.hotplug;
-                        *mlock(l) = true;
-                        c.notify_all();
-                        send(&mut writer, DaemonResponse::NoDevice);
-                    }
-                    Some(ds) => {
-                        let resp = dispatch(ds, cmd, &transform);
-                        let failed = matches!(&resp, DaemonResponse::Error { .. });
-                        send(&mut writer, resp);
-                        if failed {
-                            println!("{} device error - dropping handle", TAG);
-                            *dev = None;
-                            let (l, c) = &state.hotplug;
-                            *mlock(l) = true;
-                            c.notify_all();
-                        }
+                }
+                inner.notification = Some(ActiveNotification::new(notification));
+                if pad.is_bt {
+                    inner.buzz = buzz.map(|b| {
+                        ClipPlayer::new(&b.clip(duration_ms), HAPTICS_SAMPLE_RATE, 1.0, 0.0, false)
+                    });
+                }
+            }
+            if let Some(buzz) = buzz
+                && !pad.is_bt
+            {
+                match pad.sound_card() {
+                    Some(card) => {
+                        let player = ClipPlayer::new(
+                            &buzz.clip(duration_ms),
+                            USB_HAPTICS_RATE,
+                            1.0,
+                            0.0,
+                            false,
+                        );
+                        mlock(&pad.usb_haptics).play_overlay(card, player);
                     }
+                    None => println!("{} no USB audio device for {}", TAG, pad.serial),
+                }
+            }
+            return DaemonResponse::Ok;
+        }
+
+        DaemonCommand::SetTriggerPrograms { left, right } => {
+            wlock(&pad.inner).trigger_programs = [left, right];
+            push_triggers_to_device(pad);
+            return DaemonResponse::Ok;
+        }
+
+        DaemonCommand::SetLightbarBindings { bindings } => {
+            wlock(&pad.inner).lightbar_bindings = bindings;
+            return DaemonResponse::Ok;
+        }
+
+        DaemonCommand::SetLightbarMetric { metric } => {
+            let restore = {
+                let mut inner = wlock(&pad.inner);
+                inner.lightbar_metric = metric.clone();
+                let cleared = metric.is_none() && inner.metric_color.take().is_some();
+                (cleared && inner.shows_base_color()).then_some(inner.lightbar_color)
+            };
+            if let Some((r, g, b, br)) = restore {
+                let _ = mlock(&pad.device).set_lightbar(r, g, b, br);
+            }
+            return DaemonResponse::Ok;
+        }
+
+        DaemonCommand::SetRumbleScale { scale } => {
+            wlock(&pad.inner).rumble_scale = scale.max(0.0);
+            return DaemonResponse::Ok;
+        }
+
+        DaemonCommand::SetRumbleHaptics { config } => {
+            wlock(&pad.inner).rumble_haptics = config;
+            return DaemonResponse::Ok;
+        }
+
+        DaemonCommand::SetAudioReactive { config } => {
+            wlock(&pad.inner).reactive = config;
+            return match sync_audio_reactive(pad) {
+                Ok(()) => DaemonResponse::Ok,
+                Err(e) => DaemonResponse::Error {
+                    message: format!("{e:#}"),
+                },
+            };
+        }
+
+        DaemonCommand::SetGyro {
+            enabled,
+            smoothing,
+            sensitivity,
+        } => {
+            let mut inner = wlock(&pad.inner);
+            let g = &mut inner.gyro;
+            g.enabled = enabled;
+            g.smoothing = smoothing.clamp(0.0, 0.95);
+            g.sensitivity = sensitivity.max(0.0);
+            return DaemonResponse::Ok;
+        }
+
+        _ => {}
+    }
+
+    if state.update_in_progress.load(Ordering::Relaxed) {
+        return DaemonResponse::Error {
+            message: "Firmware update in progress".to_string(),
+        };
+    }
+
+    let cmd = match cmd {
+        DaemonCommand::SetRumble { left, right } => {
+            let scale = rlock(&pad.inner).rumble_scale;
+            let amp = |v: u8| (v as f32 * scale).round().min(255.0) as u8;
+            DaemonCommand::SetRumble {
+                left: amp(left),
+                right: amp(right),
+            }
+        }
+        cmd => cmd,
+    };
+
+    match &cmd {
+        DaemonCommand::PlayHapticClip {
+            path,
+            gain,
+            balance,
+            looping,
+        } => {
+            return match play_clip_on_pad(pad, path, *gain, *balance, *looping) {
+                Ok(()) => DaemonResponse::Ok,
+                Err(e) => DaemonResponse::Error {
+                    message: e.to_string(),
+                },
+            };
+        }
+
+        DaemonCommand::StopHapticClip => {
+            wlock(&pad.inner).clip = None;
+            mlock(&pad.usb_haptics).stop();
+            return DaemonResponse::Ok;
+        }
+
+        DaemonCommand::PlaySound {
+            path,
+            gain,
+            looping,
+        } => {
+            let clip = HapticClip::load(std::path::Path::new(path));
+            return match clip.and_then(|clip| play_sound_on_pad(pad, &clip, *gain, *looping)) {
+                Ok(()) => DaemonResponse::Ok,
+                Err(e) => DaemonResponse::Error {
+                    message: e.to_string(),
+                },
+            };
+        }
+
+        DaemonCommand::PlayTestTone => {
+            let clip = HapticClip::test_tone(USB_HAPTICS_RATE);
+            return match play_sound_on_pad(pad, &clip, 1.0, false) {
+                Ok(()) => DaemonResponse::Ok,
+                Err(e) => DaemonResponse::Error {
+                    message: e.to_string(),
+                },
+            };
+        }
+
+        DaemonCommand::StopSound => {
+            mlock(&pad.usb_haptics).stop_sound();
+            return DaemonResponse::Ok;
+        }
+
+        DaemonCommand::SetTriggerEffects { left, right } => {
+            {
+                let mut inner = wlock(&pad.inner);
+                if let Some(l) = left {
+                    inner.trigger_left = Some(*l);
+                }
+                if let Some(r) = right {
+                    inner.trigger_right = Some(*r);
                 }
             }
+            push_triggers_to_device(pad);
+            return DaemonResponse::Ok;
+        }
+
+        DaemonCommand::SetLightbar {
+            r,
+            g,
+            b,
+            brightness,
+        } => {
+            let mut inner = wlock(&pad.inner);
+            inner.lightbar_color = (*r, *g, *b, *brightness);
+            if !inner.shows_base_color() {
+                return DaemonResponse::Ok;
+            }
+            if let Some((r, g, b)) = inner.metric_color {
+                let _ = mlock(&pad.device).set_lightbar(r, g, b, *brightness);
+                return DaemonResponse::Ok;
+            }
+        }
+
+        DaemonCommand::SetTriggerEffect {
+            left,
+            right,
+            effect_type,
+            params,
+        } => {
+            let mut inner = wlock(&pad.inner);
+            let new_val = if *effect_type == 0x05 {
+                None
+            } else {
+                Some((*effect_type, *params))
+            };
+            if *left {
+                inner.trigger_left = new_val;
+            }
+            if *right {
+                inner.trigger_right = new_val;
+            }
+            inner.trigger_output = None;
+        }
+
+        DaemonCommand::SetTriggerOff => {
+            let mut inner = wlock(&pad.inner);
+            inner.trigger_left = None;
+            inner.trigger_right = None;
+            inner.trigger_output = None;
+        }
+
+        DaemonCommand::SetAudioLevels { levels } => {
+            wlock(&pad.inner).audio_levels = *levels;
+        }
+
+        DaemonCommand::SetSpeaker { mode } => {
+            wlock(&pad.inner).speaker_mode = *mode;
+        }
+
+        DaemonCommand::SetAudioRouting { routing } => {
+            wlock(&pad.inner).audio_routing = *routing;
+            let _ = mlock(&pad.device).set_speaker(pad.speaker_mode());
+            return DaemonResponse::Ok;
+        }
+
+        DaemonCommand::GetAudioLevels => {
+            return DaemonResponse::AudioLevels {
+                levels: rlock(&pad.inner).audio_levels,
+            };
+        }
+
+        DaemonCommand::SetMicLed { .. } if state.mic_synced.load(Ordering::Relaxed) => {
+            return DaemonResponse::Ok;
+        }
+
+        DaemonCommand::SetPlayerLeds { leds } => {
+            let mut inner = wlock(&pad.inner);
+            if let Some(&pattern) = PLAYER_LED_PATTERNS.get(*leds as usize) {
+                inner.player_led.pattern = pattern;
+                inner.player_led.animation = None;
+            }
+            if pad.player_leds_overridden(&inner.active_effect) {
+                return DaemonResponse::Ok;
+            }
         }
+
+        DaemonCommand::SetPlayerLedConfig { config } => {
+            let overridden = {
+                let mut inner = wlock(&pad.inner);
+                inner.player_led = config.clone();
+                pad.player_leds_overridden(&inner.active_effect)
+            };
+            let mut ds = mlock(&pad.device);
+            let _ = ds.set_player_led_brightness(config.brightness);
+            if !overridden {
+                let _ = ds.set_player_led_mask(config.pattern);
+            }
+            return DaemonResponse::Ok;
+        }
+
+        _ => {}
     }
+
+    let resp = dispatch(&mut mlock(&pad.device), cmd);
+    if matches!(&resp, DaemonResponse::Error { .. }) {
+        println!("{} device error on {} - dropping handle", TAG, pad.serial);
+        state.drop_pad(&pad.serial);
+    }
+    resp
 }
 
-fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (u8, u8, u8) {
-    let c = v * s;
-    let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
-    let m = v - c;
-    let (r, g, b) = if h < 60.0 {
-        (c, x, 0.0)
-    } else if h < 120.0 {
-        (x, c, 0.0)
-    } else if h < 180.0 {
-        (0.0, c, x)
-    } else if h < 240.0 {
-        (0.0, x, c)
-    } else if h < 300.0 {
-        (x, 0.0, c)
+fn play_clip_on_pad(pad: &Pad, path: &str, gain: f32, balance: f32, looping: bool) -> Result<()> {
+    let clip = HapticClip::load(std::path::Path::new(path))?;
+    if pad.is_bt {
+        let player = ClipPlayer::new(&clip, HAPTICS_SAMPLE_RATE, gain, balance, looping);
+        wlock(&pad.inner).clip = Some(player);
     } else {
-        (c, 0.0, x)
+        let Some(card) = pad.sound_card() else {
+            bail!("Couldn't find the controller's USB audio device");
+        };
+        let player = ClipPlayer::new(&clip, USB_HAPTICS_RATE, gain, balance, looping);
+        mlock(&pad.usb_haptics).start(card, HapticSource::Clip(player));
+    }
+    println!("{} playing {} on {}", TAG, clip.name, pad.serial);
+    Ok(())
+}
+
+fn play_sound_on_pad(pad: &Pad, clip: &HapticClip, gain: f32, looping: bool) -> Result<()> {
+    if pad.is_bt {
+        bail!("Speaker playback needs a USB connection");
+    }
+    let Some(card) = pad.sound_card() else {
+        bail!("Couldn't find the controller's USB audio device");
     };
-    (
-        ((r + m) * 255.0) as u8,
-        ((g + m) * 255.0) as u8,
-        ((b + m) * 255.0) as u8,
-    )
+    let player = ClipPlayer::new(clip, USB_HAPTICS_RATE, gain, 0.0, looping);
+    mlock(&pad.usb_haptics).play_sound(card, player);
+    println!(
+        "{} playing {} on the speaker of {}",
+        TAG, clip.name, pad.serial
+    );
+    Ok(())
+}
+
+fn write_event(w: &mut DaemonStream, msg: &EventMessage) -> bool {
+    let Ok(mut line) = serde_json::to_string(msg) else {
+        return true;
+    };
+    line.push('\n');
+    w.write_all(line.as_bytes()).is_ok()
+}
+
+fn initial_events(state: &DaemonState, topics: &[EventTopic]) -> Vec<EventMessage> {
+    let pads = state.pads();
+    let mut out = Vec::new();
+    let mut push = |pad: &Pad, event: DaemonEvent| {
+        out.push(EventMessage {
+            serial: Some(pad.serial.clone()),
+            event,
+        })
+    };
+
+    for pad in &pads {
+        if topics.contains(&EventTopic::Connection) {
+            push(pad, pad.connected_event());
+        }
+        if topics.contains(&EventTopic::Battery)
+            && let Some(b) = rlock(&pad.latest).battery.clone()
+        {
+            push(pad, DaemonEvent::Battery(b));
+        }
+        if topics.contains(&EventTopic::Audio)
+            && let Some(jack) = rlock(&pad.latest).state.as_ref().map(|s| s.jack)
+        {
+            push(pad, DaemonEvent::Jack(jack));
+        }
+        if topics.contains(&EventTopic::Profile) {
+            let name = rlock(&pad.inner).active_profile_name.clone();
+            push(pad, DaemonEvent::ProfileSwitched { name });
+        }
+    }
+
+    if pads.is_empty() && topics.contains(&EventTopic::Connection) {
+        out.push(EventMessage {
+            serial: None,
+            event: DaemonEvent::Disconnected,
+        });
+    }
+    out
+}
+
+fn stream_events(
+    writer: &mut DaemonStream,
+    state: &Arc<DaemonState>,
+    target: Option<&str>,
+    topics: &[EventTopic],
+    input_rate_hz: u32,
+    closed: &AtomicBool,
+) {
+    let rx = state.events.subscribe(topics);
+
+    for msg in initial_events(state, topics) {
+        if !write_event(writer, &msg) {
+            return;
+        }
+    }
+
+    let wants_input = topics.contains(&EventTopic::Input);
+    let period = Duration::from_secs_f32(1.0 / input_rate_hz.clamp(1, 1000) as f32);
+    let mut next_input = Instant::now();
+
+    while !closed.load(Ordering::Relaxed) {
+        let timeout = if wants_input {
+            next_input.saturating_duration_since(Instant::now())
+        } else {
+            Duration::from_secs(1)
+        };
+
+        match rx.recv_timeout(timeout) {
+            Ok(msg) => {
+                if !write_event(writer, &msg) {
+                    return;
+                }
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+
+        if !wants_input || Instant::now() < next_input {
+            continue;
+        }
+        next_input = (next_input + period).max(Instant::now());
+
+        let Some(pad) = state.pad(target) else {
+            continue;
+        };
+        let input = rlock(&pad.latest).state.clone();
+        if let Some(s) = input {
+            let msg = EventMessage {
+                serial: Some(pad.serial.clone()),
+                event: DaemonEvent::Input(s),
+            };
+            if !write_event(writer, &msg) {
+                return;
+            }
+        }
+    }
 }
 
 fn effect_loop(state: Arc<DaemonState>) {
     let start = Instant::now();
+    let mut flashing: HashSet<String> = HashSet::new();
+    let mut bars: HashMap<String, u8> = HashMap::new();
+    let mut led_frames: HashMap<String, u8> = HashMap::new();
     loop {
         sleep(Duration::from_millis(33));
 
-        let effect = rlock(&state.inner).active_effect.clone();
-        if matches!(effect, LightbarEffect::None) {
+        if state.update_in_progress.load(Ordering::Relaxed) {
             continue;
         }
 
+        let t = start.elapsed().as_secs_f32();
+        for pad in state.pads() {
+            let (effect, started, (base_r, base_g, base_b, base_br), flash, led, bound) = {
+                let mut i = wlock(&pad.inner);
+                if i.notification.as_ref().is_some_and(|n| n.expired()) {
+                    i.notification = None;
+                }
+                let flash = i.notification.as_ref().and_then(|n| n.flash());
+                (
+                    i.active_effect.clone(),
+                    i.effect_started,
+                    i.base_color(),
+                    flash,
+                    i.player_led.clone(),
+                    !i.lightbar_bindings.is_empty(),
+                )
+            };
+
+            if led.animation.is_some() && !pad.player_leds_overridden(&effect) {
+                let mask = led.frame(t);
+                if led_frames.get(&pad.serial) != Some(&mask) {
+                    led_frames.insert(pad.serial.clone(), mask);
+                    let _ = mlock(&pad.device).set_player_led_mask(mask);
+                }
+            } else if led_frames.remove(&pad.serial).is_some() {
+                let _ = mlock(&pad.device).set_player_led_mask(pad.player_led_mask());
+            }
+
+            if let Some((r, g, b)) = flash {
+                flashing.insert(pad.serial.clone());
+                let _ = mlock(&pad.device).set_lightbar(r, g, b, base_br.max(1));
+                continue;
+            }
+            let restore = flashing.remove(&pad.serial);
+
+            let battery_leds = matches!(
+                effect,
+                LightbarEffect::Battery {
+                    player_leds: true,
+                    ..
+                }
+            );
+            if !battery_leds && bars.remove(&pad.serial).is_some() {
+                let _ = mlock(&pad.device).set_player_led_mask(pad.player_led_mask());
+            }
+
+            let (r, g, b) = match effect {
+                // Bindings repaint from the input loop once the flash is over.
+                LightbarEffect::None if restore && !bound => (base_r, base_g, base_b),
+                LightbarEffect::None => continue,
+                LightbarEffect::Breath { speed } => {
+                    let factor = ((t * speed * std::f32::consts::TAU).sin() * 0.5 + 0.5).max(0.0);
+                    (
+                        (base_r as f32 * factor) as u8,
+                        (base_g as f32 * factor) as u8,
+                        (base_b as f32 * factor) as u8,
+                    )
+                }
+                LightbarEffect::Rainbow { speed } => {
+                    let hue = (t * speed * 360.0) % 360.0;
+                    hsv_to_rgb(hue, 1.0, 1.0)
+                }
+                LightbarEffect::Strobe { speed } => {
+                    if ((t * speed * 2.0) as u32).is_multiple_of(2) {
+                        (base_r, base_g, base_b)
+                    } else {
+                        (0, 0, 0)
+                    }
+                }
+                LightbarEffect::Battery { low, player_leds } => {
+                    let Some(battery) = rlock(&pad.latest).battery.clone() else {
+                        continue;
+                    };
+                    let (color, mask) = battery_indicator(&battery, low, t);
+                    if player_leds && bars.get(&pad.serial) != Some(&mask) {
+                        bars.insert(pad.serial.clone(), mask);
+                        let _ = mlock(&pad.device).set_player_led_mask(mask);
+                    }
+                    color
+                }
+                LightbarEffect::Custom { animation } => {
+                    let (r, g, b, br) = animation.sample(started.elapsed().as_secs_f32());
+                    let factor = br as f32 / 255.0;
+                    (
+                        (r as f32 * factor) as u8,
+                        (g as f32 * factor) as u8,
+                        (b as f32 * factor) as u8,
+                    )
+                }
+                LightbarEffect::Visualizer { .. } => {
+                    let Some(color) = rlock(&pad.inner).visualizer.as_ref().map(|v| v.color())
+                    else {
+                        continue;
+                    };
+                    color
+                }
+            };
+
+            {
+                let mut i = wlock(&pad.inner);
+                if !i.lightbar_bindings.is_empty() {
+                    i.effect_color = Some((r, g, b));
+                    continue;
+                }
+            }
+            if let Ok(mut ds) = pad.device.try_lock() {
+                let _ = ds.set_lightbar(r, g, b, base_br);
+            }
+        }
+    }
+}
+
+fn battery_indicator(battery: &BatteryInfo, low: u8, t: f32) -> ((u8, u8, u8), u8) {
+    let level = battery.capacity.min(100);
+    let lit = level.div_ceil(20).max(1);
+    let (factor, leds) = if battery.status == "Charging" {
+        let phase = (t * 0.5).fract();
+        (0.3 + 0.7 * phase, lit + (phase * (6 - lit) as f32) as u8)
+    } else if level <= low {
+        let factor = (t * 2.0 * std::f32::consts::TAU).sin() * 0.5 + 0.5;
+        (factor, (factor > 0.5) as u8)
+    } else {
+        (1.0, lit)
+    };
+    let (r, g, b) = hsv_to_rgb(level as f32 * 1.2, 1.0, 1.0);
+    let color = (
+        (r as f32 * factor) as u8,
+        (g as f32 * factor) as u8,
+        (b as f32 * factor) as u8,
+    );
+    (color, (1u8 << leds.min(5)) - 1)
+}
+
+fn haptic_loop(state: Arc<DaemonState>) {
+    let start = Instant::now();
+    let mut last_amp: HashMap<String, (u8, u8)> = HashMap::new();
+    loop {
+        sleep(Duration::from_millis(40));
         if state.update_in_progress.load(Ordering::Relaxed) {
             continue;
         }
 
-        let t = start.elapsed().as_secs_f32();
-        let (base_r, base_g, base_b, base_br) = rlock(&state.inner).lightbar_color;
+        let pads = state.pads();
+        last_amp.retain(|serial, _| pads.iter().any(|p| &p.serial == serial));
 
-        let (r, g, b) = match effect {
-            LightbarEffect::Breath { speed } => {
-                let factor = ((t * speed * std::f32::consts::TAU).sin() * 0.5 + 0.5).max(0.0);
+        let t = start.elapsed().as_secs_f32();
+        for pad in pads {
+            let (raw, (pattern, strength, speed, balance), custom, game) = {
+                let g = rlock(&pad.inner);
                 (
-                    (base_r as f32 * factor) as u8,
-                    (base_g as f32 * factor) as u8,
-                    (base_b as f32 * factor) as u8,
+                    g.raw_haptics || g.clip.is_some() || g.reactive_capture.is_some(),
+                    g.haptic,
+                    g.custom_haptic.clone(),
+                    (!g.rumble_haptics.enabled).then_some(g.ff_rumble),
                 )
+            };
+            let last = last_amp.entry(pad.serial.clone()).or_insert((255, 255));
+
+            // Game rumble from the virtual pad wins over the profile pattern while it plays.
+            if let Some((strong, weak)) = game.filter(|&(s, w)| s > 0.0 || w > 0.0) {
+                let amp = ((strong * 255.0).round() as u8, (weak * 255.0).round() as u8);
+                if amp != *last {
+                    let _ = mlock(&pad.device).set_rumble(amp.0, amp.1);
+                    *last = amp;
+                }
+                continue;
+            }
+
+            if raw || matches!(pattern, HapticPattern::None) {
+                if *last != (0, 0) {
+                    let _ = mlock(&pad.device).set_rumble(0, 0);
+                    *last = (0, 0);
+                }
+                continue;
             }
-            LightbarEffect::Rainbow { speed } => {
-                let hue = (t * speed * 360.0) % 360.0;
-                hsv_to_rgb(hue, 1.0, 1.0)
+
+            let [l, r] = levels(pattern, strength, speed, balance, custom.as_deref(), t);
+            let amp = ((l * 255.0).round() as u8, (r * 255.0).round() as u8);
+            if amp != *last {
+                let _ = mlock(&pad.device).set_rumble(amp.0, amp.1);
+                *last = amp;
+            }
+        }
+    }
+}
+
+fn raw_haptic_loop(state: Arc<DaemonState>) {
+    let start = Instant::now();
+    let period = Duration::from_secs_f32(HAPTICS_PACKET_FRAMES as f32 / HAPTICS_SAMPLE_RATE as f32);
+    let mut frames = [[0.0f32; 2]; HAPTICS_PACKET_FRAMES];
+    let mut voices: HashMap<String, HapticVoice> = HashMap::new();
+    let mut rumbles: HashMap<String, RumbleVoice> = HashMap::new();
+    loop {
+        sleep(period);
+        if state.update_in_progress.load(Ordering::Relaxed) {
+            continue;
+        }
+        let t0 = start.elapsed().as_secs_f32();
+        let pads = state.pads();
+        voices.retain(|serial, _| pads.iter().any(|p| &p.serial == serial));
+        rumbles.retain(|serial, _| pads.iter().any(|p| &p.serial == serial));
+        for pad in pads {
+            if !pad.is_bt {
+                continue;
             }
-            LightbarEffect::Strobe { speed } => {
-                if ((t * speed * 2.0) as u32).is_multiple_of(2) {
-                    (base_r, base_g, base_b)
+            let mut active = {
+                let mut g = wlock(&pad.inner);
+                if let Some(player) = g.clip.as_mut() {
+                    if !player.fill(&mut frames) {
+                        g.clip = None;
+                    }
+                    true
+                } else if let Some(capture) = g.reactive_capture.as_mut() {
+                    capture.fill(&mut frames);
+                    true
                 } else {
-                    (0, 0, 0)
+                    false
+                }
+            };
+            if !active {
+                let (raw, (pattern, strength, speed, balance), custom) = {
+                    let g = rlock(&pad.inner);
+                    (g.raw_haptics, g.haptic, g.custom_haptic.clone())
+                };
+                if raw && !matches!(pattern, HapticPattern::None) {
+                    let voice = voices.entry(pad.serial.clone()).or_insert_with(|| {
+                        HapticVoice::new(pattern, strength, speed, balance, None)
+                    });
+                    voice.retune(pattern, strength, speed, balance, custom);
+                    voice.render(HAPTICS_SAMPLE_RATE, t0, &mut frames);
+                    active = true;
                 }
             }
-            LightbarEffect::None => unreachable!(),
-        };
+            let (config, (strong, weak)) = {
+                let g = rlock(&pad.inner);
+                (g.rumble_haptics, g.ff_rumble)
+            };
+            if config.enabled && (strong > 0.0 || weak > 0.0) {
+                if !active {
+                    frames.fill([0.0; 2]);
+                }
+                let voice = rumbles.entry(pad.serial.clone()).or_default();
+                voice.set(config, strong, weak);
+                voice.mix(HAPTICS_SAMPLE_RATE, &mut frames);
+                active = true;
+            }
+            // Notification buzzes play on top of whatever the profile is doing.
+            {
+                let mut g = wlock(&pad.inner);
+                if let Some(player) = g.buzz.as_mut() {
+                    if !active {
+                        frames.fill([0.0; 2]);
+                    }
+                    mix_overlay(player, &mut frames);
+                    if player.finished() {
+                        g.buzz = None;
+                    }
+                    active = true;
+                }
+            }
+            if active {
+                let _ = mlock(&pad.device).set_haptics(&to_packet(&frames));
+            }
+        }
+    }
+}
 
-        if let Ok(mut dev) = state.device.try_lock()
-            && let Some(ds) = dev.as_mut()
+#[derive(Default)]
+struct CommandCheck {
+    value: Option<f32>,
+    ran: Option<Instant>,
+    running: Option<JoinHandle<Option<f32>>>,
+}
+
+impl CommandCheck {
+    // Checks can take seconds, so they run on their own thread and the loop uses the last result.
+    fn poll(&mut self, command: &str, interval: Duration) -> Option<f32> {
+        if self.running.as_ref().is_some_and(|h| h.is_finished())
+            && let Some(Ok(Some(v))) = self.running.take().map(JoinHandle::join)
         {
-            let _ = ds.set_lightbar(r, g, b, base_br);
+            self.value = Some(v);
+        }
+        if self.running.is_none() && self.ran.is_none_or(|t| t.elapsed() >= interval) {
+            self.ran = Some(Instant::now());
+            let command = command.to_string();
+            self.running = Some(thread::spawn(move || run_check(&command)));
         }
+        self.value
     }
 }
 
-fn haptic_loop(state: Arc<DaemonState>) {
-    let start = Instant::now();
-    let mut last_amp: u8 = 255;
+fn metric_loop(state: Arc<DaemonState>) {
+    let mut sampler = SystemSampler::default();
+    let mut checks: HashMap<String, CommandCheck> = HashMap::new();
     loop {
-        sleep(Duration::from_millis(40));
+        sleep(METRIC_INTERVAL);
+        let load = sampler.cpu_load();
         if state.update_in_progress.load(Ordering::Relaxed) {
             continue;
         }
+        let pads = state.pads();
+        let metrics: Vec<_> = pads
+            .iter()
+            .map(|p| rlock(&p.inner).lightbar_metric.clone())
+            .collect();
+        // Pads watching the same command share one check.
+        checks.retain(|command, _| {
+            metrics.iter().flatten().any(
+                |m| matches!(&m.source, MetricSource::Command { command: c, .. } if c == command),
+            )
+        });
+        for (pad, metric) in pads.iter().zip(metrics) {
+            let Some(metric) = metric else {
+                continue;
+            };
+            let value = match &metric.source {
+                MetricSource::CpuLoad => load,
+                MetricSource::CpuTemp => cpu_temp(),
+                MetricSource::Memory => memory_used(),
+                MetricSource::Command {
+                    command,
+                    interval_secs,
+                } => {
+                    let interval = Duration::from_secs((*interval_secs).max(1) as u64);
+                    checks
+                        .entry(command.clone())
+                        .or_default()
+                        .poll(command, interval)
+                }
+            };
+            let Some(value) = value else {
+                continue;
+            };
+            let color = metric.color(value);
+            let send = {
+                let mut inner = wlock(&pad.inner);
+                if inner.metric_color == Some(color) {
+                    continue;
+                }
+                inner.metric_color = Some(color);
+                inner.shows_base_color().then_some(inner.lightbar_color.3)
+            };
+            if let Some(brightness) = send {
+                let (r, g, b) = color;
+                let _ = mlock(&pad.device).set_lightbar(r, g, b, brightness);
+            }
+        }
+    }
+}
+
+fn bind_dsx(port: Option<u16>) -> Option<UdpSocket> {
+    let port = port?;
+    match UdpSocket::bind(("127.0.0.1", port)) {
+        Ok(socket) => {
+            let _ = socket.set_read_timeout(Some(DSX_MIN_INTERVAL));
+            println!("{} DSX server listening on udp 127.0.0.1:{}", TAG, port);
+            Some(socket)
+        }
+        Err(e) => {
+            eprintln!("{} cannot bind DSX port {}: {}", TAG, port, e);
+            None
+        }
+    }
+}
+
+fn dsx_loop(state: Arc<DaemonState>) {
+    let mut port: Option<u16> = None;
+    let mut socket: Option<UdpSocket> = None;
+    let mut checked: Option<Instant> = None;
+    let mut pending: HashMap<(String, DsxTarget), DsxAction> = HashMap::new();
+    let mut sent: HashMap<(String, DsxTarget), (DsxAction, Instant)> = HashMap::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        if checked.is_none_or(|t| t.elapsed() >= DSX_SETTINGS_POLL) {
+            checked = Some(Instant::now());
+            let settings = SettingsManager::new().load().dsx;
+            let wanted = settings.enabled.then_some(settings.port);
+            if wanted != port {
+                if socket.take().is_some() {
+                    println!("{} DSX server stopped", TAG);
+                }
+                port = wanted;
+                socket = bind_dsx(port);
+                pending.clear();
+                sent.clear();
+            }
+        }
 
-        let (raw, (pattern, strength, speed)) = {
-            let g = rlock(&state.inner);
-            (g.raw_haptics, g.haptic)
+        let Some(sock) = &socket else {
+            sleep(DSX_SETTINGS_POLL);
+            continue;
         };
-        if raw {
-            if last_amp != 0 {
-                let mut dev = mlock(&state.device);
-                if let Some(ds) = dev.as_mut() {
-                    let _ = ds.set_rumble(0, 0);
+
+        if let Ok((n, from)) = sock.recv_from(&mut buf)
+            && let Ok(actions) = dsx::parse_packet(&buf[..n])
+        {
+            let pads = state.pads();
+            for (index, action) in actions {
+                if let Some(pad) = pads.get(index) {
+                    pending.insert((pad.serial.clone(), action.target()), action);
                 }
-                last_amp = 0;
             }
+            let battery = pads
+                .first()
+                .and_then(|p| rlock(&p.latest).battery.as_ref().map(|b| b.capacity))
+                .unwrap_or(0);
+            let reply = serde_json::json!({
+                "Status": "DSX Received UDP Instructions",
+                "isControllerConnected": !pads.is_empty(),
+                "BatteryLevel": battery,
+            });
+            let _ = sock.send_to(reply.to_string().as_bytes(), from);
+        }
+
+        if state.update_in_progress.load(Ordering::Relaxed) {
             continue;
         }
+        let pads = state.pads();
+        sent.retain(|(serial, _), _| pads.iter().any(|p| &p.serial == serial));
+        let due: Vec<_> = pending
+            .keys()
+            .filter(|key| {
+                sent.get(*key)
+                    .is_none_or(|(_, at)| at.elapsed() >= DSX_MIN_INTERVAL)
+            })
+            .cloned()
+            .collect();
+        for key in due {
+            let Some(action) = pending.remove(&key) else {
+                continue;
+            };
+            let Some(pad) = pads.iter().find(|p| p.serial == key.0) else {
+                continue;
+            };
+            if let Some((last, at)) = sent.get(&key)
+                && *last == action
+                && at.elapsed() < DSX_REPEAT_INTERVAL
+            {
+                continue;
+            }
+            if action == DsxAction::Reset {
+                sent.retain(|(serial, _), _| serial != &pad.serial);
+            }
+            apply_dsx_action(&state, pad, action.clone());
+            sent.insert(key, (action, Instant::now()));
+        }
+    }
+}
+
+fn open_mute_switch(settings: &MicMuteSettings) -> Option<CaptureSwitch> {
+    let device = match settings.mixer.as_str() {
+        "" => controller_mixer()?,
+        device => device.to_string(),
+    };
+    match CaptureSwitch::open(&device, &settings.element) {
+        Ok(switch) => {
+            println!(
+                "{} mute button controls '{}' on {}",
+                TAG,
+                switch.element(),
+                switch.device()
+            );
+            let held = match settings.mode {
+                MuteMode::PushToTalk => Some(true),
+                MuteMode::PushToMute => Some(false),
+                _ => None,
+            };
+            if let Some(muted) = held {
+                let _ = switch.set_muted(muted);
+            }
+            Some(switch)
+        }
+        Err(e) => {
+            println!("{} mic mute: {:#}", TAG, e);
+            None
+        }
+    }
+}
 
-        if matches!(pattern, HapticPattern::None) {
-            if last_amp != 0 {
-                let mut dev = mlock(&state.device);
-                if let Some(ds) = dev.as_mut() {
-                    let _ = ds.set_rumble(0, 0);
+fn mute_loop(state: Arc<DaemonState>) {
+    let events = state
+        .events
+        .subscribe(&[EventTopic::Buttons, EventTopic::Connection]);
+    let mut settings = MicMuteSettings::default();
+    let mut checked: Option<Instant> = None;
+    let mut switch: Option<CaptureSwitch> = None;
+    let mut muted: Option<bool> = None;
+    loop {
+        if checked.is_none_or(|t| t.elapsed() >= MUTE_SETTINGS_POLL) {
+            checked = Some(Instant::now());
+            let wanted = SettingsManager::new().load().mic_mute;
+            if wanted != settings {
+                state
+                    .mic_synced
+                    .store(wanted.mode != MuteMode::Off, Ordering::Relaxed);
+                switch = match wanted.mode {
+                    MuteMode::Off => None,
+                    _ => open_mute_switch(&wanted),
+                };
+                muted = None;
+                settings = wanted;
+            }
+        }
+
+        let event = match events.recv_timeout(MUTE_POLL) {
+            Ok(msg) => Some(msg.event),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+        match event {
+            Some(DaemonEvent::Connected { .. }) => {
+                if settings.mode != MuteMode::Off
+                    && switch.as_ref().is_none_or(|sw| sw.muted().is_err())
+                {
+                    switch = open_mute_switch(&settings);
                 }
-                last_amp = 0;
+                muted = None;
             }
-            continue;
+            Some(DaemonEvent::Buttons {
+                pressed, released, ..
+            }) => {
+                if let Some(sw) = &switch {
+                    let target = if pressed & BTN_MUTE != 0 {
+                        match settings.mode {
+                            MuteMode::Toggle => Some(!sw.muted().unwrap_or(false)),
+                            MuteMode::PushToTalk => Some(false),
+                            MuteMode::PushToMute => Some(true),
+                            MuteMode::Off => None,
+                        }
+                    } else if released & BTN_MUTE != 0 {
+                        match settings.mode {
+                            MuteMode::PushToTalk => Some(true),
+                            MuteMode::PushToMute => Some(false),
+                            _ => None,
+                        }
+                    } else {
+                        None
+                    };
+                    if let Some(m) = target
+                        && let Err(e) = sw.set_muted(m)
+                    {
+                        println!("{} mic mute: {:#}", TAG, e);
+                    }
+                }
+            }
+            _ => {}
         }
 
-        let t = start.elapsed().as_secs_f32();
-        let s = (strength.min(7) as f32 / 7.0).clamp(0.0, 1.0);
-        let intensity = match pattern {
-            HapticPattern::None => 0.0,
-            HapticPattern::Constant => s,
-            HapticPattern::Pulse => {
-                let phase = (t * speed) % 1.0;
-                if phase < 0.5 { s } else { 0.0 }
-            }
-            HapticPattern::Ramp => {
-                let phase = (t * speed) % 1.0;
-                s * phase
-            }
-            HapticPattern::Wave => {
-                let v = (t * speed * std::f32::consts::TAU).sin() * 0.5 + 0.5;
-                s * v
+        let Some(now) = switch.as_ref().and_then(|sw| sw.muted().ok()) else {
+            continue;
+        };
+        if muted != Some(now) {
+            muted = Some(now);
+            let led = if now {
+                MicLedState::On
+            } else {
+                MicLedState::Off
+            };
+            for pad in state.pads() {
+                let _ = mlock(&pad.device).set_mic_led(led);
             }
+        }
+    }
+}
+
+fn sync_volume(
+    control: &VolumeControl,
+    synced: &mut Option<(u8, i64)>,
+    level: u8,
+) -> Result<Option<u8>> {
+    let raw = control.get()?;
+    match *synced {
+        Some((_, last)) if raw != last => {
+            let external = control.to_level(raw);
+            *synced = Some((external, raw));
+            Ok(Some(external))
+        }
+        Some((last, _)) if last == level => Ok(None),
+        _ => {
+            let raw = control.to_raw(level);
+            control.set(raw)?;
+            *synced = Some((level, raw));
+            Ok(None)
+        }
+    }
+}
+
+struct AudioSync {
+    device: String,
+    playback: Option<(VolumeControl, Option<(u8, i64)>)>,
+    capture: Option<(VolumeControl, Option<(u8, i64)>)>,
+}
+
+impl AudioSync {
+    fn open(device: String) -> Self {
+        let open = |capture| {
+            let control = VolumeControl::open(&device, capture).ok()?;
+            Some((control, None))
         };
+        let (playback, capture) = (open(false), open(true));
+        Self {
+            device,
+            playback,
+            capture,
+        }
+    }
 
-        let amp = (intensity.clamp(0.0, 1.0) * 255.0).round() as u8;
-        if amp != last_amp {
-            let mut dev = mlock(&state.device);
-            if let Some(ds) = dev.as_mut() {
-                let _ = ds.set_rumble(amp, amp);
+    // Returns false when the mixer went away and should be reopened.
+    fn sync(&mut self, pad: &Pad) -> bool {
+        let mut ok = true;
+        let levels = rlock(&pad.inner).audio_levels;
+        let mut next = levels;
+        let output = levels.headphone.max(levels.speaker);
+        if let Some((control, synced)) = &mut self.playback {
+            match sync_volume(control, synced, output) {
+                Ok(Some(level)) => {
+                    // The card volume is a master over both outputs, so keep their balance.
+                    let scale = |v: u8| match output {
+                        0 => level,
+                        _ => (v as u32 * level as u32 / output as u32) as u8,
+                    };
+                    next.headphone = scale(levels.headphone);
+                    next.speaker = scale(levels.speaker);
+                }
+                Ok(None) => {}
+                Err(_) => ok = false,
             }
-            last_amp = amp;
         }
+        if let Some((control, synced)) = &mut self.capture {
+            match sync_volume(control, synced, levels.mic) {
+                Ok(Some(level)) => next.mic = level,
+                Ok(None) => {}
+                Err(_) => ok = false,
+            }
+        }
+
+        if next != levels {
+            wlock(&pad.inner).audio_levels = next;
+            let _ = mlock(&pad.device).set_audio_levels(next);
+        }
+        ok
     }
 }
 
-fn raw_haptic_loop(state: Arc<DaemonState>) {
-    let start = Instant::now();
-    let period = Duration::from_secs_f32(HAPTICS_PACKET_FRAMES as f32 / HAPTICS_SAMPLE_RATE as f32);
+fn audio_sync_loop(state: Arc<DaemonState>) {
+    let mut synced: HashMap<String, AudioSync> = HashMap::new();
     loop {
-        sleep(period);
-        if state.update_in_progress.load(Ordering::Relaxed) {
-            continue;
+        sleep(AUDIO_SYNC_INTERVAL);
+
+        let pads: Vec<_> = state.pads().into_iter().filter(|p| !p.is_bt).collect();
+        synced.retain(|serial, _| pads.iter().any(|p| &p.serial == serial));
+        for pad in &pads {
+            let Some(card) = pad.sound_card() else {
+                synced.remove(&pad.serial);
+                continue;
+            };
+            let device = format!("hw:{card}");
+            let sync = match synced.entry(pad.serial.clone()) {
+                Entry::Occupied(e) if e.get().device == device => e.into_mut(),
+                entry => {
+                    println!(
+                        "{} syncing audio levels of {} with mixer {}",
+                        TAG, pad.serial, device
+                    );
+                    entry.insert_entry(AudioSync::open(device)).into_mut()
+                }
+            };
+            if !sync.sync(pad) {
+                synced.remove(&pad.serial);
+            }
         }
-        let (raw, (pattern, strength, speed)) = {
-            let g = rlock(&state.inner);
-            (g.raw_haptics, g.haptic)
-        };
-        if !raw || matches!(pattern, HapticPattern::None) {
+    }
+}
+
+fn virtual_pad_loop(state: Arc<DaemonState>) {
+    let mut enabled = false;
+    let mut failed: HashSet<String> = HashSet::new();
+    // Serial and player slot of each virtual gamepad, in creation order.
+    let mut created: Vec<(String, u8)> = Vec::new();
+    loop {
+        sleep(VIRTUAL_PAD_POLL);
+        let wanted = SettingsManager::new().load().virtual_pad;
+        if wanted != enabled {
+            enabled = wanted;
+            failed.clear();
+        }
+
+        let mut pads = state.pads();
+        failed.retain(|serial| pads.iter().any(|p| &p.serial == serial));
+        if !enabled {
+            for pad in &pads {
+                if wlock(&pad.virtual_pad).take().is_some() {
+                    println!("{} virtual gamepad for {} removed", TAG, pad.serial);
+                }
+            }
+            created.clear();
             continue;
         }
-        let t0 = start.elapsed().as_secs_f32();
-        let packet = generate_packet(pattern, strength, speed, t0);
-        let mut dev = mlock(&state.device);
-        if let Some(ds) = dev.as_mut().filter(|d| d.is_bluetooth()) {
-            let _ = ds.set_haptics(&packet);
+
+        // Games number pads by creation order, so create them in player-slot order.
+        pads.retain(|p| !failed.contains(&p.serial));
+        pads.sort_by_key(|p| match p.player.load(Ordering::Relaxed) {
+            0 => u8::MAX,
+            slot => slot,
+        });
+        let order: Vec<(String, u8)> = pads
+            .iter()
+            .map(|p| (p.serial.clone(), p.player.load(Ordering::Relaxed)))
+            .collect();
+        created.retain(|(serial, _)| order.iter().any(|(s, _)| s == serial));
+        if !order.starts_with(&created) {
+            println!("{} player slots changed, recreating virtual gamepads", TAG);
+            for pad in &pads {
+                wlock(&pad.virtual_pad).take();
+            }
+            created.clear();
+        }
+
+        for (pad, (serial, slot)) in pads.iter().zip(order).skip(created.len()) {
+            let (name, phys) = match slot {
+                0 => (format!("ds4u DualSense {serial}"), format!("ds4u/{serial}")),
+                slot => (
+                    format!("ds4u DualSense {serial} (player {slot})"),
+                    format!("ds4u/{serial}/player{slot}"),
+                ),
+            };
+            match VirtualPad::create(&name, &phys, pad.product_id) {
+                Ok(virtual_pad) => {
+                    let virtual_pad = Arc::new(virtual_pad);
+                    *wlock(&pad.virtual_pad) = Some(Arc::clone(&virtual_pad));
+                    println!("{} virtual gamepad created for {}", TAG, serial);
+                    created.push((serial, slot));
+                    let pad = Arc::clone(pad);
+                    thread::spawn(move || ff_loop(pad, virtual_pad));
+                }
+                Err(e) => {
+                    println!("{} virtual gamepad for {}: {:#}", TAG, serial, e);
+                    failed.insert(serial);
+                    // Later pads wait so they don't jump ahead of this slot.
+                    break;
+                }
+            }
         }
     }
 }
 
-fn dispatch(ds: &mut DualSense, cmd: DaemonCommand, transform: &InputTransform) -> DaemonResponse {
+fn ff_loop(pad: Arc<Pad>, virtual_pad: Arc<VirtualPad>) {
+    let mut last = (RumbleHaptics::default(), (0.0, 0.0));
+    while pad.connected.load(Ordering::Relaxed)
+        && rlock(&pad.virtual_pad)
+            .as_ref()
+            .is_some_and(|v| Arc::ptr_eq(v, &virtual_pad))
+    {
+        if let Err(e) = virtual_pad.wait_ff(FF_POLL) {
+            println!("{} force feedback on {}: {:#}", TAG, pad.serial, e);
+            break;
+        }
+        let (strong, weak) = virtual_pad.rumble();
+        let (scale, config) = {
+            let inner = rlock(&pad.inner);
+            (inner.rumble_scale, inner.rumble_haptics)
+        };
+        let level = ((strong * scale).min(1.0), (weak * scale).min(1.0));
+        if (config, level) != last {
+            last = (config, level);
+            wlock(&pad.inner).ff_rumble = level;
+            sync_usb_rumble(&pad, last);
+        }
+    }
+    wlock(&pad.inner).ff_rumble = (0.0, 0.0);
+    sync_usb_rumble(&pad, (RumbleHaptics::default(), (0.0, 0.0)));
+}
+
+// USB pads play voice-coil rumble through the audio mix; Bluetooth pads go through raw_haptic_loop.
+fn sync_usb_rumble(pad: &Pad, (config, (strong, weak)): (RumbleHaptics, (f32, f32))) {
+    if pad.is_bt {
+        return;
+    }
+    let (strong, weak) = if config.enabled {
+        (strong, weak)
+    } else {
+        (0.0, 0.0)
+    };
+    let Some(card) = pad.sound_card() else {
+        return;
+    };
+    mlock(&pad.usb_haptics).set_rumble(card, config, strong, weak);
+}
+
+fn apply_dsx_action(state: &DaemonState, pad: &Pad, action: DsxAction) {
+    let cmd = match action {
+        DsxAction::Trigger { left, effect } => {
+            wlock(&pad.inner).trigger_programs[if left { 0 } else { 1 }] = None;
+            DaemonCommand::SetTriggerEffects {
+                left: left.then_some(effect),
+                right: (!left).then_some(effect),
+            }
+        }
+        DsxAction::Lightbar(r, g, b) => {
+            let brightness = rlock(&pad.inner).lightbar_color.3;
+            DaemonCommand::SetLightbar {
+                r,
+                g,
+                b,
+                brightness,
+            }
+        }
+        DsxAction::PlayerLeds(leds) => DaemonCommand::SetPlayerLeds { leds },
+        DsxAction::MicLed(state) => DaemonCommand::SetMicLed { state },
+        DsxAction::Reset => {
+            apply_profile_to_pad(pad, &default_profile_name(Some(&pad.serial)));
+            push_outputs_to_device(pad);
+            return;
+        }
+    };
+    handle_pad_command(state, pad, cmd);
+}
+
+fn dispatch(ds: &mut DualSense, cmd: DaemonCommand) -> DaemonResponse {
     macro_rules! ok_or_err {
         ($e:expr) => {
             match $e {
@@ -796,23 +2600,6 @@ fn dispatch(ds: &mut DualSense, cmd: DaemonCommand, transform: &InputTransform)
     match cmd {
         DaemonCommand::Ping => DaemonResponse::Pong,
 
-        DaemonCommand::GetBattery => match ds.get_battery() {
-            Ok(b) => DaemonResponse::Battery(b),
-            Err(e) => DaemonResponse::Error {
-                message: e.to_string(),
-            },
-        },
-
-        DaemonCommand::GetInputState => match ds.get_input_state() {
-            Ok(mut s) => {
-                transform.apply(&mut s);
-                DaemonResponse::InputState(s)
-            }
-            Err(e) => DaemonResponse::Error {
-                message: e.to_string(),
-            },
-        },
-
         DaemonCommand::GetFirmwareInfo => match ds.get_firmware_info() {
             Ok((v, d, t)) => DaemonResponse::FirmwareInfo {
                 version: v,
@@ -864,10 +2651,63 @@ fn dispatch(ds: &mut DualSense, cmd: DaemonCommand, transform: &InputTransform)
             ok_or_err!(ds.set_rumble(left, right))
         }
 
-        DaemonCommand::SetSpeaker { mode } => ok_or_err!(ds.set_speaker(&mode)),
+        DaemonCommand::SetSpeaker { mode } => ok_or_err!(ds.set_speaker(mode)),
 
-        DaemonCommand::SetVolume { volume } => ok_or_err!(ds.set_volume(volume)),
+        DaemonCommand::SetAudioLevels { levels } => ok_or_err!(ds.set_audio_levels(levels)),
 
         _ => unreachable!(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        inputs::Button,
+        lightbar_react::TintWhen,
+        notify::{ActiveNotification, Notification},
+    };
+
+    #[test]
+    fn bindings_repaint_after_notification_flash() {
+        let mut inner = DaemonInner {
+            lightbar_bindings: vec![LightbarBinding::Tint {
+                when: TintWhen::Held(Button::Cross),
+                color: (255, 0, 0),
+                amount: 1.0,
+            }],
+            ..DaemonInner::default()
+        };
+        let input = ControllerState::default();
+        let base = Some(inner.base_color());
+
+        assert_eq!(evaluate_lightbar_bindings(&mut inner, &input), base);
+        assert_eq!(evaluate_lightbar_bindings(&mut inner, &input), None);
+
+        inner.notification = Some(ActiveNotification::new(Notification {
+            flash: Some((0, 255, 0)),
+            buzz: None,
+            duration_ms: 1000,
+            priority: 0,
+        }));
+        assert_eq!(evaluate_lightbar_bindings(&mut inner, &input), None);
+
+        inner.notification = None;
+        assert_eq!(evaluate_lightbar_bindings(&mut inner, &input), base);
+    }
+
+    #[test]
+    fn untargeted_switch_skips_pads_with_their_own_profile() {
+        let mut settings = Settings::default();
+        settings.binding_mut("pad-a").profile = "Racing".to_string();
+        settings.binding_mut("pad-b").nickname = "Couch".to_string();
+        settings.profile = "Shooter".to_string();
+
+        let mut pads = vec!["pad-a", "pad-b"];
+        pads.retain(|serial| settings.follows_default_profile(serial));
+
+        assert_eq!(pads, ["pad-b"]);
+        assert_eq!(settings.profile_for("pad-a"), "Racing");
+        assert_eq!(settings.profile_for("pad-b"), "Shooter");
+    }
+}
@@ -0,0 +1,323 @@
+use std::ops::RangeInclusive;
+
+use anyhow::{Result, bail};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    common::{MicLedState, TriggerMode},
+    dualsense::PLAYER_LED_PATTERNS,
+    profiles::TriggerConfig,
+};
+
+const INSTRUCTION_TYPES: &[&str] = &[
+    "Invalid",
+    "TriggerUpdate",
+    "RGBUpdate",
+    "PlayerLED",
+    "TriggerThreshold",
+    "MicLED",
+    "PlayerLEDNewRevision",
+    "ResetToUserSettings",
+];
+
+const TRIGGER_MODES: &[&str] = &[
+    "Normal",
+    "GameCube",
+    "VerySoft",
+    "Soft",
+    "Hard",
+    "VeryHard",
+    "Hardest",
+    "Rigid",
+    "VibrateTrigger",
+    "Choppy",
+    "Medium",
+    "VibrateTriggerPulse",
+    "CustomTriggerValue",
+    "Resistance",
+    "Bow",
+    "Galloping",
+    "SemiAutomaticGun",
+    "AutomaticGun",
+    "Machine",
+    "VIBRATE_TRIGGER_10Hz",
+    "OFF",
+    "FEEDBACK",
+    "WEAPON",
+    "VIBRATION",
+];
+
+// DSX's CustomTriggerValueMode, as raw effect bytes: 0x01 rigid or 0x02
+// pulse, with 0x20 (A) and 0x04 (B) variant flags. The vibrate variants
+// have no documented byte and are rejected.
+const CUSTOM_TRIGGER_MODES: &[(&str, Option<u8>)] = &[
+    ("OFF", Some(0x00)),
+    ("Rigid", Some(0x01)),
+    ("RigidA", Some(0x21)),
+    ("RigidB", Some(0x05)),
+    ("RigidAB", Some(0x25)),
+    ("Pulse", Some(0x02)),
+    ("PulseA", Some(0x22)),
+    ("PulseB", Some(0x06)),
+    ("PulseAB", Some(0x26)),
+    ("VibrateResistance", None),
+    ("VibrateResistanceA", None),
+    ("VibrateResistanceB", None),
+    ("VibrateResistanceAB", None),
+    ("VibratePulse", None),
+    ("VibratePulseA", None),
+    ("VibratePulseB", None),
+    ("VibratePulseAB", None),
+];
+
+#[derive(Deserialize)]
+struct Packet {
+    instructions: Vec<Instruction>,
+}
+
+#[derive(Deserialize)]
+struct Instruction {
+    #[serde(rename = "type")]
+    kind: Value,
+    #[serde(default)]
+    parameters: Vec<Value>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DsxTarget {
+    Trigger(bool),
+    Lightbar,
+    PlayerLeds,
+    MicLed,
+    Reset,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum DsxAction {
+    Trigger { left: bool, effect: (u8, [u8; 10]) },
+    Lightbar(u8, u8, u8),
+    PlayerLeds(u8),
+    MicLed(MicLedState),
+    Reset,
+}
+
+impl DsxAction {
+    pub fn target(&self) -> DsxTarget {
+        match self {
+            DsxAction::Trigger { left, .. } => DsxTarget::Trigger(*left),
+            DsxAction::Lightbar(..) => DsxTarget::Lightbar,
+            DsxAction::PlayerLeds(_) => DsxTarget::PlayerLeds,
+            DsxAction::MicLed(_) => DsxTarget::MicLed,
+            DsxAction::Reset => DsxTarget::Reset,
+        }
+    }
+}
+
+fn num(v: &Value) -> Option<i64> {
+    match v {
+        Value::Number(n) => n.as_i64().or_else(|| n.as_f64().map(|f| f as i64)),
+        Value::Bool(b) => Some(*b as i64),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn lookup(table: &[&'static str], v: &Value) -> Option<&'static str> {
+    match v {
+        Value::String(s) => match s.trim().parse::<usize>() {
+            Ok(i) => table.get(i).copied(),
+            Err(_) => table
+                .iter()
+                .copied()
+                .find(|n| n.eq_ignore_ascii_case(s.trim())),
+        },
+        v => num(v).and_then(|i| table.get(usize::try_from(i).ok()?).copied()),
+    }
+}
+
+fn trigger_config(mode: &str, p: &[i64]) -> Option<TriggerConfig> {
+    // Clamp each parameter to the range its effect accepts; to_effect
+    // assumes sane positions and forces.
+    let arg = |i: usize, default: u8, range: RangeInclusive<u8>| {
+        p.get(i).map_or(default, |v| {
+            (*v).clamp(*range.start() as i64, *range.end() as i64) as u8
+        })
+    };
+    let zones = |from: u8, strength: u8| {
+        let mut z = [0u8; 10];
+        z[from.min(9) as usize..].fill(strength.min(8));
+        Some(z)
+    };
+    let base = TriggerConfig::default();
+    let feedback = |zones| TriggerConfig {
+        mode: TriggerMode::Feedback,
+        zones,
+        ..base.clone()
+    };
+    let vibration = |zones, frequency| TriggerConfig {
+        mode: TriggerMode::Vibration,
+        zones,
+        frequency,
+        ..base.clone()
+    };
+
+    let cfg = match mode {
+        "Normal" | "OFF" => base.clone(),
+        "GameCube" => feedback(zones(7, 8)),
+        "VerySoft" => feedback(zones(0, 2)),
+        "Soft" => feedback(zones(0, 3)),
+        "Medium" => feedback(zones(0, 5)),
+        "Hard" => feedback(zones(0, 6)),
+        "VeryHard" => feedback(zones(0, 7)),
+        "Hardest" | "Rigid" => feedback(zones(0, 8)),
+        "Choppy" => feedback(Some([8, 0, 8, 0, 8, 0, 8, 0, 8, 0])),
+        "VibrateTrigger" => vibration(zones(0, 8), arg(0, 40, 1..=255)),
+        "VibrateTriggerPulse" => vibration(zones(0, 8), 20),
+        "VIBRATE_TRIGGER_10Hz" => vibration(zones(0, 8), 10),
+        "Resistance" | "FEEDBACK" => feedback(zones(arg(0, 0, 0..=9), arg(1, 4, 0..=8))),
+        "AutomaticGun" | "VIBRATION" => vibration(
+            zones(arg(0, 0, 0..=9), arg(1, 8, 0..=8)),
+            arg(2, 10, 1..=255),
+        ),
+        "SemiAutomaticGun" | "WEAPON" => TriggerConfig {
+            mode: TriggerMode::Weapon,
+            start: arg(0, 2, 2..=7),
+            end: arg(1, 7, 3..=8),
+            strength: arg(2, 8, 1..=8),
+            ..base.clone()
+        },
+        "Bow" => TriggerConfig {
+            mode: TriggerMode::Bow,
+            start: arg(0, 0, 0..=7),
+            end: arg(1, 8, 1..=8),
+            strength: arg(2, 2, 1..=8),
+            snap: Some(arg(3, 8, 1..=8)),
+            ..base.clone()
+        },
+        "Galloping" => TriggerConfig {
+            mode: TriggerMode::Galloping,
+            start: arg(0, 0, 0..=8),
+            end: arg(1, 9, 1..=9),
+            feet: Some((arg(2, 2, 0..=6), arg(3, 5, 1..=7))),
+            frequency: arg(4, 2, 1..=255),
+            ..base.clone()
+        },
+        "CustomTriggerValue" => {
+            let (name, byte) = usize::try_from(*p.first()?)
+                .ok()
+                .and_then(|i| CUSTOM_TRIGGER_MODES.get(i))?;
+            let Some(byte) = byte else {
+                eprintln!("[dsx] unsupported custom trigger mode {name}");
+                return None;
+            };
+            let mut forces = [0u8; 10];
+            for (i, f) in forces.iter_mut().take(7).enumerate() {
+                *f = arg(i + 1, 0, 0..=255);
+            }
+            TriggerConfig {
+                mode: TriggerMode::Raw,
+                raw: Some((*byte, forces)),
+                ..base.clone()
+            }
+        }
+        "Machine" => TriggerConfig {
+            mode: TriggerMode::Machine,
+            start: arg(0, 1, 0..=8),
+            end: arg(1, 9, 1..=9),
+            strength: arg(2, 5, 1..=8),
+            amplitude_b: Some(arg(3, 5, 1..=8)),
+            frequency: arg(4, 10, 1..=255),
+            period: arg(5, 0, 0..=255),
+            ..base.clone()
+        },
+        _ => return None,
+    };
+    Some(cfg)
+}
+
+fn player_leds(lit: &[bool]) -> u8 {
+    let mask = lit
+        .iter()
+        .take(5)
+        .enumerate()
+        .fold(0u8, |m, (i, &on)| m | ((on as u8) << i));
+    PLAYER_LED_PATTERNS
+        .iter()
+        .position(|&p| p == mask)
+        .unwrap_or_else(|| (mask.count_ones() as usize).min(5)) as u8
+}
+
+fn instruction(kind: &str, p: &[Value]) -> Option<(usize, DsxAction)> {
+    let n: Vec<i64> = p.iter().map(|v| num(v).unwrap_or(0)).collect();
+    let index = usize::try_from(*n.first()?).ok()?;
+    let byte = |i: usize| n.get(i).map_or(0, |v| (*v).clamp(0, 255) as u8);
+    let action = match kind {
+        "TriggerUpdate" => {
+            let left = match p.get(1)? {
+                Value::String(s) if s.eq_ignore_ascii_case("left") => true,
+                Value::String(s) if s.eq_ignore_ascii_case("right") => false,
+                v => num(v)? == 1,
+            };
+            let mode = lookup(TRIGGER_MODES, p.get(2)?)?;
+            let effect = trigger_config(mode, &n[3..])?.to_effect();
+            DsxAction::Trigger { left, effect }
+        }
+        "RGBUpdate" => DsxAction::Lightbar(byte(1), byte(2), byte(3)),
+        "PlayerLED" => {
+            let lit: Vec<bool> = n[1..].iter().map(|&v| v != 0).collect();
+            DsxAction::PlayerLeds(player_leds(&lit))
+        }
+        "PlayerLEDNewRevision" => DsxAction::PlayerLeds(match n.get(1)? {
+            0..=4 => byte(1) + 1,
+            _ => 0,
+        }),
+        "MicLED" => DsxAction::MicLed(match n.get(1)? {
+            0 => MicLedState::On,
+            1 => MicLedState::Pulse,
+            _ => MicLedState::Off,
+        }),
+        "ResetToUserSettings" => DsxAction::Reset,
+        _ => return None,
+    };
+    Some((index, action))
+}
+
+pub fn parse_packet(data: &[u8]) -> Result<Vec<(usize, DsxAction)>> {
+    let packet: Packet = serde_json::from_slice(data)?;
+    if packet.instructions.is_empty() {
+        bail!("packet has no instructions");
+    }
+    Ok(packet
+        .instructions
+        .iter()
+        .filter_map(|i| instruction(lookup(INSTRUCTION_TYPES, &i.kind)?, &i.parameters))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trigger(json: &str) -> Option<(u8, [u8; 10])> {
+        match parse_packet(json.as_bytes()).ok()?.pop()?.1 {
+            DsxAction::Trigger { effect, .. } => Some(effect),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn custom_trigger_value_maps_to_raw() {
+        let packet = r#"{"instructions":[{"type":1,"parameters":[0,2,"CustomTriggerValue",4,10,300,-5,40,50,60,70]}]}"#;
+        assert_eq!(
+            trigger(packet),
+            Some((0x25, [10, 255, 0, 40, 50, 60, 70, 0, 0, 0]))
+        );
+    }
+
+    #[test]
+    fn custom_trigger_vibrate_modes_are_rejected() {
+        let packet = r#"{"instructions":[{"type":1,"parameters":[0,1,12,9,1,2,3]}]}"#;
+        assert_eq!(trigger(packet), None);
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    ffi::CString,
     sync::{
         Arc,
         atomic::{AtomicBool, Ordering},
@@ -12,7 +13,7 @@ use crc::{CRC_32_ISO_HDLC, Crc};
 use hidapi::{HidApi, HidDevice};
 use serde::{Deserialize, Serialize};
 
-use crate::{common::*, inputs::*};
+use crate::{common::*, inputs::*, player_leds::PlayerLedBrightness};
 
 const OUTPUT_CRC32_SEED: u8 = 0xa2;
 
@@ -65,6 +66,8 @@ const DS_OUTPUT_AUDIO_OUTPUT_PATH_SHIFT: u8 = 4;
 const DS_STATUS_BATTERY_CAPACITY: u8 = 0x0f;
 const DS_STATUS_CHARGING: u8 = 0xf0;
 const DS_STATUS_CHARGING_SHIFT: u8 = 4;
+const DS_STATUS_HEADPHONES: u8 = 1 << 0;
+const DS_STATUS_MICROPHONE: u8 = 1 << 1;
 
 const DS_FEATURE_REPORT_FW: u8 = 0xf4;
 const DS_FEATURE_REPORT_FW_STATUS: u8 = 0xf5;
@@ -82,6 +85,10 @@ pub const HAPTICS_SAMPLE_RATE: u32 = 3000;
 pub const HAPTICS_PACKET_FRAMES: usize = 32;
 pub const HAPTICS_PACKET_SAMPLES: usize = HAPTICS_PACKET_FRAMES * 2;
 
+pub const PLAYER_LED_PATTERNS: [u8; 8] = [
+    0b00000, 0b00100, 0b01010, 0b10101, 0b11011, 0b11111, 0b10001, 0b01110,
+];
+
 const DS_FEATURE_REPORT_FIRMWARE_INFO: u8 = 0x20;
 
 const CRC32: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
@@ -103,12 +110,63 @@ struct DualSenseInputReport {
     reserved2: u8,
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct BatteryInfo {
     pub capacity: u8,
     pub status: String,
 }
 
+pub struct InputReader {
+    device: HidDevice,
+    is_bt: bool,
+    update_mode: Arc<AtomicBool>,
+}
+
+impl InputReader {
+    pub fn read_input(&self, timeout_ms: i32) -> Result<Option<(ControllerState, BatteryInfo)>> {
+        if self.update_mode.load(Ordering::Relaxed) {
+            bail!("");
+        }
+        read_report(&self.device, self.is_bt, timeout_ms)
+    }
+}
+
+fn read_report(
+    device: &HidDevice,
+    is_bt: bool,
+    timeout_ms: i32,
+) -> Result<Option<(ControllerState, BatteryInfo)>> {
+    let mut buf = vec![0u8; DS_INPUT_REPORT_BT_SIZE];
+    let size = device.read_timeout(&mut buf, timeout_ms)?;
+
+    if size == 0 {
+        return Ok(None);
+    }
+
+    let d = input_payload(is_bt, &buf, size)?;
+    Ok(Some((parse_input_state(d), parse_battery(d[52]))))
+}
+
+fn input_payload(is_bt: bool, buf: &[u8], size: usize) -> Result<&[u8]> {
+    let (id, expected_size, offset) = if is_bt {
+        (DS_INPUT_REPORT_BT, DS_INPUT_REPORT_BT_SIZE, 2)
+    } else {
+        (DS_INPUT_REPORT_USB, DS_INPUT_REPORT_USB_SIZE, 1)
+    };
+
+    if buf[0] != id || size != expected_size {
+        bail!(
+            "Unexpected input report: id=0x{:02X} size={} (expected id=0x{:02X} size={})",
+            buf[0],
+            size,
+            id,
+            expected_size
+        );
+    }
+
+    Ok(&buf[offset..])
+}
+
 pub struct DualSense {
     device: HidDevice,
     is_bt: bool,
@@ -117,6 +175,7 @@ pub struct DualSense {
     haptics_packet_counter: u8,
     product_id: u16,
     serial: String,
+    hid_path: String,
     update_mode: Arc<AtomicBool>,
 }
 
@@ -156,6 +215,7 @@ Please connect your controller via USB or Bluetooth."
 
         let product_id = device_info.product_id();
         let serial = device_info.serial_number().unwrap_or("Unknown").to_string();
+        let hid_path = device_info.path().to_string_lossy().into_owned();
         let device = device_info.open_device(api)?;
         let is_bt = device_info.interface_number() == -1;
 
@@ -167,6 +227,7 @@ Please connect your controller via USB or Bluetooth."
             haptics_packet_counter: 0,
             product_id,
             serial,
+            hid_path,
             update_mode: Arc::new(AtomicBool::new(false)),
         })
     }
@@ -188,150 +249,31 @@ Please connect your controller via USB or Bluetooth."
     }
 
     pub fn get_input_state(&mut self) -> Result<ControllerState> {
-        if self.is_updating() {
-            bail!("");
+        match self.read_input(16)? {
+            Some((state, _)) => Ok(state),
+            None => bail!("Timeout reading input state"),
         }
+    }
 
-        let mut buf = vec![0u8; DS_INPUT_REPORT_BT_SIZE];
-        let size = self.device.read_timeout(&mut buf, 16)?;
-
-        if size == 0 {
-            bail!("Timeout reading input state");
-        }
-
-        let (id, expected_size, offset) = if self.is_bt {
-            (DS_INPUT_REPORT_BT, DS_INPUT_REPORT_BT_SIZE, 2)
-        } else {
-            (DS_INPUT_REPORT_USB, DS_INPUT_REPORT_USB_SIZE, 1)
-        };
-
-        if buf[0] != id || size != expected_size {
-            bail!(
-                "Unexpected input report: id=0x{:02X} size={} (expected id=0x{:02X} size={})",
-                buf[0],
-                size,
-                id,
-                expected_size
-            );
+    pub fn read_input(
+        &mut self,
+        timeout_ms: i32,
+    ) -> Result<Option<(ControllerState, BatteryInfo)>> {
+        if self.is_updating() {
+            bail!("");
         }
 
-        let d = &buf[offset..];
-
-        let left_x = d[0];
-        let left_y = d[1];
-        let right_x = d[2];
-        let right_y = d[3];
-        let l2 = d[4];
-        let r2 = d[5];
-
-        let dpad = d[7] & 0xf;
-
-        let buttons = {
-            let b0 = d[7];
-            let b1 = d[8];
-            let b2 = d[9];
-            let mut b: u32 = 0;
-
-            if b0 & 0x10 != 0 {
-                b |= BTN_SQUARE;
-            }
-            if b0 & 0x20 != 0 {
-                b |= BTN_CROSS;
-            }
-            if b0 & 0x40 != 0 {
-                b |= BTN_CIRCLE;
-            }
-            if b0 & 0x80 != 0 {
-                b |= BTN_TRIANGLE;
-            }
-            if b1 & 0x01 != 0 {
-                b |= BTN_L1;
-            }
-            if b1 & 0x02 != 0 {
-                b |= BTN_R1;
-            }
-            if b1 & 0x04 != 0 {
-                b |= BTN_L2;
-            }
-            if b1 & 0x08 != 0 {
-                b |= BTN_R2;
-            }
-            if b1 & 0x10 != 0 {
-                b |= BTN_CREATE;
-            }
-            if b1 & 0x20 != 0 {
-                b |= BTN_OPTIONS;
-            }
-            if b1 & 0x40 != 0 {
-                b |= BTN_L3;
-            }
-            if b1 & 0x80 != 0 {
-                b |= BTN_R3;
-            }
-            if b2 & 0x01 != 0 {
-                b |= BTN_PS;
-            }
-            if b2 & 0x02 != 0 {
-                b |= BTN_TOUCHPAD;
-            }
-            if b2 & 0x04 != 0 {
-                b |= BTN_MUTE;
-            }
-
-            b
-        };
-
-        let gyro = [
-            i16::from_le_bytes([d[15], d[16]]),
-            i16::from_le_bytes([d[17], d[18]]),
-            i16::from_le_bytes([d[19], d[20]]),
-        ];
-
-        let accel = [
-            i16::from_le_bytes([d[21], d[22]]),
-            i16::from_le_bytes([d[23], d[24]]),
-            i16::from_le_bytes([d[25], d[26]]),
-        ];
-
-        let sensor_timestamp = u32::from_le_bytes([d[27], d[28], d[29], d[30]]);
-
-        let mut touch_points = [TouchPoint::default(), TouchPoint::default()];
-        let mut touch_count: u8 = 0;
-
-        for (i, tp) in touch_points.iter_mut().enumerate() {
-            let base = 32 + i * 4;
-            let b0 = d[base];
-            let active = (b0 & 0x80) == 0;
-
-            if active {
-                let x = (d[base + 1] as u16) | (((d[base + 2] & 0x0f) as u16) << 8);
-                let y = ((d[base + 2] >> 4) as u16) | ((d[base + 3] as u16) << 4);
-
-                *tp = TouchPoint {
-                    active: true,
-                    id: b0 & 0x7f,
-                    x: x.min(TOUCHPAD_MAX_X - 1),
-                    y: y.min(TOUCHPAD_MAX_Y - 1),
-                };
-
-                touch_count += 1;
-            }
-        }
+        read_report(&self.device, self.is_bt, timeout_ms)
+    }
 
-        Ok(ControllerState {
-            left_x,
-            left_y,
-            right_x,
-            right_y,
-            l2,
-            r2,
-            buttons,
-            dpad,
-            gyro,
-            accel,
-            sensor_timestamp,
-            touch_count,
-            touch_points,
+    // Opens a second handle on the same hidraw node so input can be read
+    // without holding the handle used for output reports.
+    pub fn input_reader(&self, api: &HidApi) -> Result<InputReader> {
+        let path = CString::new(self.hid_path.as_str())?;
+        Ok(InputReader {
+            device: api.open_path(&path)?,
+            is_bt: self.is_bt,
+            update_mode: Arc::clone(&self.update_mode),
         })
     }
 
@@ -373,6 +315,10 @@ Please connect your controller via USB or Bluetooth."
         &self.serial
     }
 
+    pub fn hid_path(&self) -> &str {
+        &self.hid_path
+    }
+
     fn send_output_report(&mut self, data: &mut [u8]) -> Result<()> {
         if self.is_updating() {
             return Ok(());
@@ -441,50 +387,66 @@ Please connect your controller via USB or Bluetooth."
     }
 
     pub fn set_player_leds(&mut self, n: u8) -> Result<()> {
-        const PLAYER_LED_PATTERNS: [u8; 8] = [
-            0b00000, 0b00100, 0b01010, 0b10101, 0b11011, 0b11111, 0b10001, 0b01110,
-        ];
-
         if n >= PLAYER_LED_PATTERNS.len() as u8 {
             bail!("Invalid player number");
         }
 
+        self.set_player_led_mask(PLAYER_LED_PATTERNS[n as usize])
+    }
+
+    pub fn set_player_led_mask(&mut self, mask: u8) -> Result<()> {
         let mut buf = self.init_output_report();
         let offset = if self.is_bt { 3 } else { 1 };
 
         buf[offset + 1] = DS_OUTPUT_VALID_FLAG1_PLAYER_INDICATOR_CONTROL_ENABLE;
-        buf[offset + 43] = PLAYER_LED_PATTERNS[n as usize];
+        buf[offset + 43] = mask & 0x1f;
+
+        self.send_output_report(&mut buf)
+    }
+
+    pub fn set_player_led_brightness(&mut self, brightness: PlayerLedBrightness) -> Result<()> {
+        let mut buf = self.init_output_report();
+        let offset = if self.is_bt { 3 } else { 1 };
+
+        buf[offset + 38] = DS_OUTPUT_VALID_FLAG2_LED_BRIGHTNESS_CONTROL_ENABLE;
+        buf[offset + 42] = match brightness {
+            PlayerLedBrightness::High => 0,
+            PlayerLedBrightness::Medium => 1,
+            PlayerLedBrightness::Low => 2,
+        };
 
         self.send_output_report(&mut buf)
     }
 
-    pub fn set_speaker(&mut self, mode: &str) -> Result<()> {
+    pub fn set_speaker(&mut self, mode: SpeakerMode) -> Result<()> {
         let mut buf = self.init_output_report();
         let offset = if self.is_bt { 3 } else { 1 };
 
         buf[offset] = DS_OUTPUT_VALID_FLAG0_AUDIO_CONTROL_ENABLE;
 
         buf[offset + 7] = match mode {
-            "internal" => 3 << DS_OUTPUT_AUDIO_OUTPUT_PATH_SHIFT,
-            "headphone" => 0,
-            "both" => 2 << DS_OUTPUT_AUDIO_OUTPUT_PATH_SHIFT,
-            _ => 0,
+            SpeakerMode::Internal => 3 << DS_OUTPUT_AUDIO_OUTPUT_PATH_SHIFT,
+            SpeakerMode::Headphone => 0,
+            SpeakerMode::Both => 2 << DS_OUTPUT_AUDIO_OUTPUT_PATH_SHIFT,
         };
 
         self.send_output_report(&mut buf)
     }
 
-    pub fn set_volume(&mut self, volume: u8) -> Result<()> {
+    pub fn set_audio_levels(&mut self, levels: AudioLevels) -> Result<()> {
         let mut buf = self.init_output_report();
         let offset = if self.is_bt { 3 } else { 1 };
 
-        let max_volume = 255u16;
+        let scale = |level: u8, max: u16| (level as u16 * max / 255) as u8;
 
         buf[offset] = DS_OUTPUT_VALID_FLAG0_HEADPHONE_VOLUME_ENABLE;
-        buf[offset + 4] = (volume as u16 * 0x7f / max_volume) as u8;
+        buf[offset + 4] = scale(levels.headphone, 0x7f);
 
         buf[offset] |= DS_OUTPUT_VALID_FLAG0_SPEAKER_VOLUME_ENABLE;
-        buf[offset + 5] = (volume as u16 * 0x64 / max_volume) as u8;
+        buf[offset + 5] = scale(levels.speaker, 0x64);
+
+        buf[offset] |= DS_OUTPUT_VALID_FLAG0_MICROPHONE_VOLUME_ENABLE;
+        buf[offset + 6] = scale(levels.mic, 0x40);
 
         self.send_output_report(&mut buf)
     }
@@ -634,44 +596,10 @@ Please connect your controller via USB or Bluetooth."
     }
 
     pub fn get_battery(&mut self) -> Result<BatteryInfo> {
-        if self.is_updating() {
-            bail!("");
+        match self.read_input(1000)? {
+            Some((_, battery)) => Ok(battery),
+            None => bail!("Timeout"),
         }
-
-        let mut buf = vec![0u8; DS_INPUT_REPORT_BT_SIZE];
-        let size = self.device.read_timeout(&mut buf, 1000)?;
-
-        if size == 0 {
-            bail!("Timeout");
-        }
-
-        let (report, report_size, status_offset) = if self.is_bt {
-            (DS_INPUT_REPORT_BT, DS_INPUT_REPORT_BT_SIZE, 54)
-        } else {
-            (DS_INPUT_REPORT_USB, DS_INPUT_REPORT_USB_SIZE, 53)
-        };
-
-        if buf[0] != report || size != report_size {
-            bail!("Invalid report received");
-        }
-
-        let status_byte = buf[status_offset];
-
-        let bat_data = status_byte & DS_STATUS_BATTERY_CAPACITY;
-        let charging_status = (status_byte & DS_STATUS_CHARGING) >> DS_STATUS_CHARGING_SHIFT;
-
-        let (capacity, status) = match charging_status {
-            0x0 => ((bat_data * 10 + 5).min(100), "Discharging"),
-            0x1 => ((bat_data * 10 + 5).min(100), "Charging"),
-            0x2 => ((bat_data * 10 + 5).min(100), "Full"),
-            0xa | 0xb => (0, "Not charging"),
-            _ => (0, "Unknown"),
-        };
-
-        Ok(BatteryInfo {
-            capacity,
-            status: status.to_string(),
-        })
     }
 
     pub fn update_firmware(
@@ -929,6 +857,147 @@ Please connect your controller via USB or Bluetooth."
     }
 }
 
+fn parse_input_state(d: &[u8]) -> ControllerState {
+    let left_x = d[0];
+    let left_y = d[1];
+    let right_x = d[2];
+    let right_y = d[3];
+    let l2 = d[4];
+    let r2 = d[5];
+
+    let dpad = d[7] & 0xf;
+
+    let buttons = {
+        let b0 = d[7];
+        let b1 = d[8];
+        let b2 = d[9];
+        let mut b: u32 = 0;
+
+        if b0 & 0x10 != 0 {
+            b |= BTN_SQUARE;
+        }
+        if b0 & 0x20 != 0 {
+            b |= BTN_CROSS;
+        }
+        if b0 & 0x40 != 0 {
+            b |= BTN_CIRCLE;
+        }
+        if b0 & 0x80 != 0 {
+            b |= BTN_TRIANGLE;
+        }
+        if b1 & 0x01 != 0 {
+            b |= BTN_L1;
+        }
+        if b1 & 0x02 != 0 {
+            b |= BTN_R1;
+        }
+        if b1 & 0x04 != 0 {
+            b |= BTN_L2;
+        }
+        if b1 & 0x08 != 0 {
+            b |= BTN_R2;
+        }
+        if b1 & 0x10 != 0 {
+            b |= BTN_CREATE;
+        }
+        if b1 & 0x20 != 0 {
+            b |= BTN_OPTIONS;
+        }
+        if b1 & 0x40 != 0 {
+            b |= BTN_L3;
+        }
+        if b1 & 0x80 != 0 {
+            b |= BTN_R3;
+        }
+        if b2 & 0x01 != 0 {
+            b |= BTN_PS;
+        }
+        if b2 & 0x02 != 0 {
+            b |= BTN_TOUCHPAD;
+        }
+        if b2 & 0x04 != 0 {
+            b |= BTN_MUTE;
+        }
+
+        b
+    };
+
+    let gyro = [
+        i16::from_le_bytes([d[15], d[16]]),
+        i16::from_le_bytes([d[17], d[18]]),
+        i16::from_le_bytes([d[19], d[20]]),
+    ];
+
+    let accel = [
+        i16::from_le_bytes([d[21], d[22]]),
+        i16::from_le_bytes([d[23], d[24]]),
+        i16::from_le_bytes([d[25], d[26]]),
+    ];
+
+    let sensor_timestamp = u32::from_le_bytes([d[27], d[28], d[29], d[30]]);
+
+    let mut touch_points = [TouchPoint::default(), TouchPoint::default()];
+    let mut touch_count: u8 = 0;
+
+    for (i, tp) in touch_points.iter_mut().enumerate() {
+        let base = 32 + i * 4;
+        let b0 = d[base];
+        let active = (b0 & 0x80) == 0;
+
+        if active {
+            let x = (d[base + 1] as u16) | (((d[base + 2] & 0x0f) as u16) << 8);
+            let y = ((d[base + 2] >> 4) as u16) | ((d[base + 3] as u16) << 4);
+
+            *tp = TouchPoint {
+                active: true,
+                id: b0 & 0x7f,
+                x: x.min(TOUCHPAD_MAX_X - 1),
+                y: y.min(TOUCHPAD_MAX_Y - 1),
+            };
+
+            touch_count += 1;
+        }
+    }
+
+    ControllerState {
+        left_x,
+        left_y,
+        right_x,
+        right_y,
+        l2,
+        r2,
+        buttons,
+        dpad,
+        gyro,
+        accel,
+        sensor_timestamp,
+        touch_count,
+        touch_points,
+        jack: JackState {
+            headphones: d[53] & DS_STATUS_HEADPHONES != 0,
+            microphone: d[53] & DS_STATUS_MICROPHONE != 0,
+        },
+    }
+}
+
+fn parse_battery(status_byte: u8) -> BatteryInfo {
+    let bat_data = status_byte & DS_STATUS_BATTERY_CAPACITY;
+    let charging_status = (status_byte & DS_STATUS_CHARGING) >> DS_STATUS_CHARGING_SHIFT;
+
+    let (capacity, status) = match charging_status {
+        0x0 => ((bat_data * 10 + 5).min(100), "Discharging"),
+        0x1 => ((bat_data * 10 + 5).min(100), "Charging"),
+        0x2 => ((bat_data * 10 + 5).min(100), "Full"),
+        0xa | 0xb => (0, "Not charging"),
+        _ => (0, "Unknown"),
+    };
+
+    BatteryInfo {
+        capacity,
+        status: status.to_string(),
+    }
+}
+
 pub fn list_devices(api: &HidApi) -> Vec<String> {
     api.device_list()
         .filter(|info| {
@@ -946,3 +1015,17 @@ pub fn list_devices(api: &HidApi) -> Vec<String> {
         })
         .collect()
 }
+
+pub fn list_serials(api: &HidApi) -> Vec<String> {
+    let mut serials: Vec<String> = api
+        .device_list()
+        .filter(|info| {
+            info.vendor_id() == DS_VID
+                && (info.product_id() == DS_PID || info.product_id() == DSE_PID)
+        })
+        .filter_map(|info| info.serial_number().map(str::to_string))
+        .collect();
+    serials.sort();
+    serials.dedup();
+    serials
+}
@@ -0,0 +1,199 @@
+use std::f32::consts::TAU;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow, bail};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as DecodeError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::haptic_synth::balance_gains;
+
+pub const CLIP_EXTENSIONS: &[&str] = &["wav", "flac", "ogg"];
+const TEST_TONE_HZ: f32 = 440.0;
+const TEST_TONE_SECS: f32 = 1.0;
+
+pub struct HapticClip {
+    pub name: String,
+    pub rate: u32,
+    pub frames: Vec<[f32; 2]>,
+}
+
+impl HapticClip {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(ext);
+        }
+
+        let probed = symphonia::default::get_probe().format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?;
+        let mut format = probed.format;
+
+        let track = format
+            .default_track()
+            .ok_or_else(|| anyhow!("No audio track in {}", path.display()))?;
+        let track_id = track.id;
+        let rate = track
+            .codec_params
+            .sample_rate
+            .ok_or_else(|| anyhow!("Unknown sample rate"))?;
+        let mut decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())?;
+
+        let mut frames = Vec::new();
+        loop {
+            let packet = match format.next_packet() {
+                Ok(p) => p,
+                Err(DecodeError::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            };
+            if packet.track_id() != track_id {
+                continue;
+            }
+
+            let decoded = match decoder.decode(&packet) {
+                Ok(d) => d,
+                Err(DecodeError::DecodeError(_)) => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            let spec = *decoded.spec();
+            let channels = spec.channels.count().max(1);
+            let mut buf = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+            buf.copy_interleaved_ref(decoded);
+            for f in buf.samples().chunks(channels) {
+                let l = f[0];
+                let r = if channels > 1 { f[1] } else { l };
+                frames.push([l, r]);
+            }
+        }
+
+        if frames.is_empty() {
+            bail!("{} contains no audio", path.display());
+        }
+
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Ok(Self { name, rate, frames })
+    }
+
+    pub fn test_tone(rate: u32) -> Self {
+        let len = (TEST_TONE_SECS * rate as f32) as usize;
+        let fade = (rate as usize / 100).min(len / 2).max(1);
+        let frames = (0..len)
+            .map(|i| {
+                let env = (i.min(len - 1 - i) as f32 / fade as f32).min(1.0);
+                let v = (TAU * TEST_TONE_HZ * i as f32 / rate as f32).sin() * env * 0.5;
+                [v, v]
+            })
+            .collect();
+        Self {
+            name: format!("{} Hz test tone", TEST_TONE_HZ),
+            rate,
+            frames,
+        }
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.frames.len() as f32 / self.rate as f32
+    }
+
+    pub fn resample(&self, rate: u32) -> Vec<[f32; 2]> {
+        if rate == self.rate {
+            return self.frames.clone();
+        }
+
+        let len = self.frames.len();
+        let ratio = self.rate as f64 / rate as f64;
+        let out_len = (len as f64 / ratio).floor() as usize;
+
+        (0..out_len)
+            .map(|i| {
+                let start = i as f64 * ratio;
+                if ratio > 1.0 {
+                    // Average the span each output sample covers so downsampling doesn't alias.
+                    let a = start as usize;
+                    let b = ((start + ratio) as usize).clamp(a + 1, len);
+                    let mut acc = [0.0f32; 2];
+                    for f in &self.frames[a..b] {
+                        acc[0] += f[0];
+                        acc[1] += f[1];
+                    }
+                    let n = (b - a) as f32;
+                    [acc[0] / n, acc[1] / n]
+                } else {
+                    let a = (start as usize).min(len - 1);
+                    let b = (a + 1).min(len - 1);
+                    let frac = (start - a as f64) as f32;
+                    let (fa, fb) = (self.frames[a], self.frames[b]);
+                    [
+                        fa[0] + (fb[0] - fa[0]) * frac,
+                        fa[1] + (fb[1] - fa[1]) * frac,
+                    ]
+                }
+            })
+            .collect()
+    }
+}
+
+pub struct ClipPlayer {
+    frames: Arc<Vec<[f32; 2]>>,
+    pos: usize,
+    gain: [f32; 2],
+    looping: bool,
+}
+
+impl ClipPlayer {
+    pub fn new(clip: &HapticClip, rate: u32, gain: f32, balance: f32, looping: bool) -> Self {
+        let gain = gain.max(0.0);
+        Self {
+            frames: Arc::new(clip.resample(rate)),
+            pos: 0,
+            gain: balance_gains(balance).map(|g| g * gain),
+            looping,
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        !self.looping && self.pos >= self.frames.len()
+    }
+
+    pub fn fill(&mut self, out: &mut [[f32; 2]]) -> bool {
+        for f in out.iter_mut() {
+            *f = self.next_frame().unwrap_or([0.0; 2]);
+        }
+        !self.finished()
+    }
+
+    pub fn next_frame(&mut self) -> Option<[f32; 2]> {
+        if self.pos >= self.frames.len() {
+            if !self.looping || self.frames.is_empty() {
+                return None;
+            }
+            self.pos = 0;
+        }
+        let f = self.frames[self.pos];
+        self.pos += 1;
+        Some([
+            (f[0] * self.gain[0]).clamp(-1.0, 1.0),
+            (f[1] * self.gain[1]).clamp(-1.0, 1.0),
+        ])
+    }
+}
@@ -0,0 +1,393 @@
+use std::{fs, path::PathBuf, sync::Arc};
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::{common::HapticPattern, profiles::ProfileManager};
+
+pub const HAPTIC_CARRIER_HZ: f32 = 160.0;
+pub const MAX_CARRIER_HZ: f32 = 400.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug, Default)]
+pub enum Waveform {
+    #[default]
+    Sine,
+    Square,
+    Saw,
+    Noise,
+}
+
+impl Waveform {
+    pub const ALL: [Waveform; 4] = [
+        Waveform::Sine,
+        Waveform::Square,
+        Waveform::Saw,
+        Waveform::Noise,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Waveform::Sine => "Sine",
+            Waveform::Square => "Square",
+            Waveform::Saw => "Saw",
+            Waveform::Noise => "Noise",
+        }
+    }
+
+    fn sample(self, phase: f32, rng: &mut u32) -> f32 {
+        match self {
+            Waveform::Sine => (phase * std::f32::consts::TAU).sin(),
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Saw => phase * 2.0 - 1.0,
+            Waveform::Noise => {
+                *rng ^= *rng << 13;
+                *rng ^= *rng >> 17;
+                *rng ^= *rng << 5;
+                *rng as f32 / u32::MAX as f32 * 2.0 - 1.0
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Debug)]
+pub struct Keyframe {
+    pub t: f32,
+    pub value: f32,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug, Default)]
+pub struct Track {
+    pub keys: Vec<Keyframe>,
+}
+
+impl Track {
+    pub fn constant(value: f32) -> Self {
+        Self {
+            keys: vec![Keyframe { t: 0.0, value }],
+        }
+    }
+
+    pub fn sample(&self, t: f32) -> f32 {
+        let Some(first) = self.keys.first() else {
+            return 0.0;
+        };
+        if t <= first.t {
+            return first.value;
+        }
+        for w in self.keys.windows(2) {
+            let (a, b) = (w[0], w[1]);
+            if t <= b.t {
+                let span = (b.t - a.t).max(f32::EPSILON);
+                return a.value + (b.value - a.value) * ((t - a.t) / span);
+            }
+        }
+        self.keys[self.keys.len() - 1].value
+    }
+
+    pub fn insert(&mut self, key: Keyframe) -> usize {
+        let idx = self.keys.partition_point(|k| k.t <= key.t);
+        self.keys.insert(idx, key);
+        idx
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct HapticChannel {
+    pub waveform: Waveform,
+    pub envelope: Track,
+    pub carrier: Track,
+}
+
+impl Default for HapticChannel {
+    fn default() -> Self {
+        Self {
+            waveform: Waveform::Sine,
+            envelope: Track::constant(1.0),
+            carrier: Track::constant(HAPTIC_CARRIER_HZ),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct CustomPattern {
+    pub name: String,
+    pub duration: f32,
+    pub looping: bool,
+    pub left: HapticChannel,
+    pub right: HapticChannel,
+}
+
+impl CustomPattern {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            duration: 1.0,
+            looping: true,
+            left: HapticChannel::default(),
+            right: HapticChannel::default(),
+        }
+    }
+
+    pub fn channels(&self) -> [&HapticChannel; 2] {
+        [&self.left, &self.right]
+    }
+
+    fn local_time(&self, t: f32) -> Option<f32> {
+        let d = self.duration.max(0.01);
+        if self.looping {
+            Some(t.rem_euclid(d))
+        } else if t <= d {
+            Some(t)
+        } else {
+            None
+        }
+    }
+}
+
+fn builtin_envelope(pattern: HapticPattern, s: f32, speed: f32, t: f32) -> f32 {
+    match pattern {
+        HapticPattern::None | HapticPattern::Custom => 0.0,
+        HapticPattern::Constant => s,
+        HapticPattern::Pulse => {
+            let phase = (t * speed).rem_euclid(1.0);
+            if phase < 0.5 { s } else { 0.0 }
+        }
+        HapticPattern::Ramp => {
+            let phase = (t * speed).rem_euclid(1.0);
+            s * phase
+        }
+        HapticPattern::Wave => {
+            let v = (t * speed * std::f32::consts::TAU).sin() * 0.5 + 0.5;
+            s * v
+        }
+    }
+}
+
+pub fn balance_gains(balance: f32) -> [f32; 2] {
+    let b = balance.clamp(-1.0, 1.0);
+    [(1.0 - b).min(1.0), (1.0 + b).min(1.0)]
+}
+
+pub fn levels(
+    pattern: HapticPattern,
+    strength: u8,
+    speed: f32,
+    balance: f32,
+    custom: Option<&CustomPattern>,
+    t: f32,
+) -> [f32; 2] {
+    let s = (strength.min(7) as f32 / 7.0).clamp(0.0, 1.0);
+    let [gl, gr] = balance_gains(balance);
+    let [l, r] = match (pattern, custom) {
+        (HapticPattern::Custom, Some(c)) => match c.local_time(t * speed) {
+            Some(lt) => c
+                .channels()
+                .map(|ch| (ch.envelope.sample(lt) * s).clamp(0.0, 1.0)),
+            None => [0.0; 2],
+        },
+        _ => {
+            let e = builtin_envelope(pattern, s, speed, t).clamp(0.0, 1.0);
+            [e, e]
+        }
+    };
+    [l * gl, r * gr]
+}
+
+pub struct HapticVoice {
+    pattern: HapticPattern,
+    strength: u8,
+    speed: f32,
+    balance: f32,
+    custom: Option<Arc<CustomPattern>>,
+    phase: [f32; 2],
+    rng: u32,
+}
+
+impl HapticVoice {
+    pub fn new(
+        pattern: HapticPattern,
+        strength: u8,
+        speed: f32,
+        balance: f32,
+        custom: Option<Arc<CustomPattern>>,
+    ) -> Self {
+        Self {
+            pattern,
+            strength,
+            speed,
+            balance,
+            custom,
+            phase: [0.0; 2],
+            rng: 0x9e37_79b9,
+        }
+    }
+
+    pub fn retune(
+        &mut self,
+        pattern: HapticPattern,
+        strength: u8,
+        speed: f32,
+        balance: f32,
+        custom: Option<Arc<CustomPattern>>,
+    ) {
+        self.pattern = pattern;
+        self.strength = strength;
+        self.speed = speed;
+        self.balance = balance;
+        self.custom = custom;
+    }
+
+    pub fn render(&mut self, rate: u32, t0: f32, out: &mut [[f32; 2]]) {
+        let dt = 1.0 / rate as f32;
+        let custom = match self.pattern {
+            HapticPattern::Custom => self.custom.clone(),
+            _ => None,
+        };
+        for (i, f) in out.iter_mut().enumerate() {
+            let t = t0 + i as f32 * dt;
+            let env = levels(
+                self.pattern,
+                self.strength,
+                self.speed,
+                self.balance,
+                custom.as_deref(),
+                t,
+            );
+            let lt = custom.as_ref().and_then(|c| c.local_time(t * self.speed));
+            for ch in 0..2 {
+                let (waveform, freq) = match (&custom, lt) {
+                    (Some(c), Some(lt)) => {
+                        let chan = c.channels()[ch];
+                        (chan.waveform, chan.carrier.sample(lt))
+                    }
+                    _ => (Waveform::Sine, HAPTIC_CARRIER_HZ),
+                };
+                self.phase[ch] = (self.phase[ch] + freq.clamp(0.0, MAX_CARRIER_HZ) * dt).fract();
+                f[ch] = waveform.sample(self.phase[ch], &mut self.rng) * env[ch];
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq, Debug)]
+#[serde(default)]
+pub struct RumbleHaptics {
+    pub enabled: bool,
+    pub low_hz: f32,
+    pub high_hz: f32,
+}
+
+impl Default for RumbleHaptics {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            low_hz: 60.0,
+            high_hz: 180.0,
+        }
+    }
+}
+
+// Plays game rumble on the voice coils: the heavy motor as a low tone, the light one as a high tone.
+#[derive(Default)]
+pub struct RumbleVoice {
+    config: RumbleHaptics,
+    levels: (f32, f32),
+    phase: [f32; 2],
+}
+
+impl RumbleVoice {
+    pub fn set(&mut self, config: RumbleHaptics, strong: f32, weak: f32) {
+        self.config = config;
+        self.levels = (strong.clamp(0.0, 1.0), weak.clamp(0.0, 1.0));
+    }
+
+    pub fn mix(&mut self, rate: u32, out: &mut [[f32; 2]]) {
+        let dt = 1.0 / rate as f32;
+        let hz = [self.config.low_hz, self.config.high_hz].map(|f| f.clamp(1.0, MAX_CARRIER_HZ));
+        let (strong, weak) = self.levels;
+        for f in out.iter_mut() {
+            for (phase, hz) in self.phase.iter_mut().zip(hz) {
+                *phase = (*phase + hz * dt).fract();
+            }
+            let low = (self.phase[0] * std::f32::consts::TAU).sin() * strong;
+            let high = (self.phase[1] * std::f32::consts::TAU).sin() * weak;
+            // The heavy motor sits in the left grip and the light one in the right.
+            f[0] = (f[0] + low * 0.7 + high * 0.3).clamp(-1.0, 1.0);
+            f[1] = (f[1] + high * 0.7 + low * 0.3).clamp(-1.0, 1.0);
+        }
+    }
+}
+
+pub struct PatternLibrary {
+    dir: PathBuf,
+}
+
+impl PatternLibrary {
+    pub fn new() -> Self {
+        let dir = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("ds4u")
+            .join("haptics");
+        if !dir.exists() {
+            let _ = fs::create_dir_all(&dir);
+        }
+        Self { dir }
+    }
+
+    fn path_for(&self, name: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}.json", ProfileManager::sanitize_filename(name)))
+    }
+
+    pub fn list(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        if let Ok(entries) = fs::read_dir(&self.dir) {
+            for e in entries.flatten() {
+                let path = e.path();
+                if path.extension().and_then(|s| s.to_str()) == Some("json")
+                    && let Ok(json) = fs::read_to_string(&path)
+                    && let Ok(p) = serde_json::from_str::<CustomPattern>(&json)
+                {
+                    names.push(p.name);
+                }
+            }
+        }
+        names.sort_by_key(|n| n.to_lowercase());
+        names
+    }
+
+    pub fn load(&self, name: &str) -> Result<CustomPattern> {
+        let path = self.path_for(name);
+        if !path.exists() {
+            bail!("Haptic pattern '{}' not found", name);
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, pattern: &CustomPattern) -> Result<()> {
+        let name = pattern.name.trim();
+        if name.is_empty() {
+            bail!("Pattern name cannot be empty");
+        }
+        if ProfileManager::sanitize_filename(name) != name {
+            bail!("Pattern name '{}' contains invalid characters", name);
+        }
+        fs::write(self.path_for(name), serde_json::to_string_pretty(pattern)?)?;
+        Ok(())
+    }
+
+    pub fn delete(&self, name: &str) -> Result<()> {
+        let path = self.path_for(name);
+        if !path.exists() {
+            bail!("Haptic pattern '{}' not found", name);
+        }
+        fs::remove_file(path)?;
+        Ok(())
+    }
+}
@@ -3,51 +3,78 @@ use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
+use crate::audio_reactive::{AudioReactive, ReactiveConfig};
 use crate::common::HapticPattern;
 use crate::dualsense::{
     DualSense, HAPTICS_PACKET_FRAMES, HAPTICS_PACKET_SAMPLES, HAPTICS_SAMPLE_RATE,
 };
+use crate::haptic_clip::ClipPlayer;
+use crate::haptic_synth::{CustomPattern, HapticVoice};
 use crate::util::mlock;
 
-pub const HAPTIC_CARRIER_HZ: f32 = 160.0;
+pub enum HapticSource {
+    Pattern(HapticVoice),
+    Clip(ClipPlayer),
+    Reactive(AudioReactive),
+}
 
-type Params = (HapticPattern, u8, f32);
+impl HapticSource {
+    pub fn pattern(
+        pattern: HapticPattern,
+        strength: u8,
+        speed: f32,
+        balance: f32,
+        custom: Option<Arc<CustomPattern>>,
+    ) -> Self {
+        Self::Pattern(HapticVoice::new(pattern, strength, speed, balance, custom))
+    }
 
-fn envelope(pattern: HapticPattern, strength: u8, speed: f32, t: f32) -> f32 {
-    let s = (strength.min(7) as f32 / 7.0).clamp(0.0, 1.0);
-    match pattern {
-        HapticPattern::None => 0.0,
-        HapticPattern::Constant => s,
-        HapticPattern::Pulse => {
-            let phase = (t * speed).rem_euclid(1.0);
-            if phase < 0.5 { s } else { 0.0 }
+    pub fn retune(
+        &mut self,
+        pattern: HapticPattern,
+        strength: u8,
+        speed: f32,
+        balance: f32,
+        custom: Option<Arc<CustomPattern>>,
+    ) {
+        if let Self::Pattern(voice) = self {
+            voice.retune(pattern, strength, speed, balance, custom);
         }
-        HapticPattern::Ramp => {
-            let phase = (t * speed).rem_euclid(1.0);
-            s * phase
+    }
+
+    pub fn is_reactive(&self) -> bool {
+        matches!(self, Self::Reactive(_))
+    }
+
+    pub fn update_reactive(&self, config: &ReactiveConfig) -> bool {
+        match self {
+            Self::Reactive(capture)
+                if capture.is_running() && capture.device() == config.device =>
+            {
+                capture.set_config(config.clone());
+                true
+            }
+            _ => false,
         }
-        HapticPattern::Wave => {
-            let v = (t * speed * std::f32::consts::TAU).sin() * 0.5 + 0.5;
-            s * v
+    }
+
+    pub fn render(&mut self, rate: u32, t0: f32, out: &mut [[f32; 2]]) -> bool {
+        match self {
+            Self::Pattern(voice) => {
+                voice.render(rate, t0, out);
+                true
+            }
+            Self::Clip(player) => player.fill(out),
+            Self::Reactive(capture) => capture.fill(out),
         }
     }
 }
 
-pub fn generate_packet(
-    pattern: HapticPattern,
-    strength: u8,
-    speed: f32,
-    t0: f32,
-) -> [i8; HAPTICS_PACKET_SAMPLES] {
+pub fn to_packet(frames: &[[f32; 2]; HAPTICS_PACKET_FRAMES]) -> [i8; HAPTICS_PACKET_SAMPLES] {
     let mut out = [0i8; HAPTICS_PACKET_SAMPLES];
-    let dt = 1.0 / HAPTICS_SAMPLE_RATE as f32;
-    for frame in 0..HAPTICS_PACKET_FRAMES {
-        let t = t0 + frame as f32 * dt;
-        let env = envelope(pattern, strength, speed, t).clamp(0.0, 1.0);
-        let carrier = (t * HAPTIC_CARRIER_HZ * std::f32::consts::TAU).sin();
-        let v = (carrier * env * 127.0).round().clamp(-127.0, 127.0) as i8;
-        out[frame * 2] = v;
-        out[frame * 2 + 1] = v;
+    for (i, f) in frames.iter().enumerate() {
+        out[i * 2] = (f[0] * 127.0).round().clamp(-127.0, 127.0) as i8;
+        out[i * 2 + 1] = (f[1] * 127.0).round().clamp(-127.0, 127.0) as i8;
     }
     out
 }
@@ -55,7 +82,7 @@ pub fn generate_packet(
 pub struct HapticStream {
     stop: Option<Arc<AtomicBool>>,
     thread: Option<JoinHandle<()>>,
-    params: Arc<Mutex<Params>>,
+    source: Arc<Mutex<HapticSource>>,
 }
 
 impl HapticStream {
@@ -63,7 +90,13 @@ impl HapticStream {
         Self {
             stop: None,
             thread: None,
-            params: Arc::new(Mutex::new((HapticPattern::None, 0, 1.0))),
+            source: Arc::new(Mutex::new(HapticSource::pattern(
+                HapticPattern::None,
+                0,
+                1.0,
+                0.0,
+                None,
+            ))),
         }
     }
 
@@ -71,21 +104,30 @@ impl HapticStream {
         self.thread.as_ref().is_some_and(|h| !h.is_finished())
     }
 
-    pub fn set_params(&self, pattern: HapticPattern, strength: u8, speed: f32) {
-        *mlock(&self.params) = (pattern, strength, speed);
-    }
-
-    pub fn start(
-        &mut self,
-        ctrl: Arc<Mutex<DualSense>>,
+    pub fn set_params(
+        &self,
         pattern: HapticPattern,
         strength: u8,
         speed: f32,
+        balance: f32,
+        custom: Option<Arc<CustomPattern>>,
     ) {
+        mlock(&self.source).retune(pattern, strength, speed, balance, custom);
+    }
+
+    pub fn is_reactive(&self) -> bool {
+        self.is_active() && mlock(&self.source).is_reactive()
+    }
+
+    pub fn update_reactive(&self, config: &ReactiveConfig) -> bool {
+        self.is_active() && mlock(&self.source).update_reactive(config)
+    }
+
+    pub fn start(&mut self, ctrl: Arc<Mutex<DualSense>>, source: HapticSource) {
         self.stop();
 
-        *mlock(&self.params) = (pattern, strength, speed);
-        let params = Arc::clone(&self.params);
+        *mlock(&self.source) = source;
+        let source = Arc::clone(&self.source);
 
         let stop = Arc::new(AtomicBool::new(false));
         let stop_c = Arc::clone(&stop);
@@ -96,16 +138,19 @@ impl HapticStream {
 
         let handle = thread::spawn(move || {
             let mut next = Instant::now();
+            let mut frames = [[0.0f32; 2]; HAPTICS_PACKET_FRAMES];
             while !stop_c.load(Ordering::Relaxed) {
-                let (pattern, strength, speed) = *mlock(&params);
                 let t0 = start.elapsed().as_secs_f32();
-                let packet = generate_packet(pattern, strength, speed, t0);
+                let playing = mlock(&source).render(HAPTICS_SAMPLE_RATE, t0, &mut frames);
                 {
                     let mut dev = mlock(&ctrl);
-                    if dev.set_haptics(&packet).is_err() {
+                    if dev.set_haptics(&to_packet(&frames)).is_err() {
                         break;
                     }
                 }
+                if !playing {
+                    break;
+                }
                 next += period;
                 let now = Instant::now();
                 if next > now {
@@ -129,6 +174,7 @@ impl HapticStream {
         if let Some(handle) = self.thread.take() {
             let _ = handle.join();
         }
+        *mlock(&self.source) = HapticSource::pattern(HapticPattern::None, 0, 1.0, 0.0, None);
     }
 }
 
@@ -3,9 +3,12 @@ use std::sync::{Arc, atomic::AtomicBool};
 use std::thread::JoinHandle;
 
 use crate::inputs::ControllerState;
+use crate::ipc::{EventMessage, EventStreamCloser};
 
 pub(crate) struct InputPoller {
     pub(crate) state_rx: Option<Receiver<ControllerState>>,
+    pub(crate) event_rx: Option<Receiver<EventMessage>>,
+    pub(crate) closer: Option<EventStreamCloser>,
     pub(crate) stop: Option<Arc<AtomicBool>>,
     pub(crate) thread: Option<JoinHandle<()>>,
     pub(crate) polling: bool,
@@ -16,6 +19,8 @@ impl InputPoller {
     pub(crate) fn new() -> Self {
         Self {
             state_rx: None,
+            event_rx: None,
+            closer: None,
             stop: None,
             thread: None,
             polling: false,
@@ -27,10 +32,14 @@ impl InputPoller {
         if let Some(flag) = self.stop.take() {
             flag.store(true, std::sync::atomic::Ordering::Relaxed);
         }
+        if let Some(c) = self.closer.take() {
+            c.close();
+        }
         if let Some(h) = self.thread.take() {
             let _ = h.join();
         }
         self.state_rx = None;
+        self.event_rx = None;
         self.polling = false;
         self.controller_state = None;
     }
@@ -27,7 +27,7 @@ pub const BTN_PS: u32 = 1 << 12;
 pub const BTN_TOUCHPAD: u32 = 1 << 13;
 pub const BTN_MUTE: u32 = 1 << 14;
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Clone, Serialize, Deserialize, Default)]
 pub struct TouchPoint {
     pub active: bool,
     pub id: u8,
@@ -35,7 +35,13 @@ pub struct TouchPoint {
     pub y: u16,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug, Default)]
+pub struct JackState {
+    pub headphones: bool,
+    pub microphone: bool,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct ControllerState {
     pub left_x: u8,
     pub left_y: u8,
@@ -50,9 +56,11 @@ pub struct ControllerState {
     pub sensor_timestamp: u32,
     pub touch_count: u8,
     pub touch_points: [TouchPoint; 2],
+    #[serde(default)]
+    pub jack: JackState,
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[derive(Clone, PartialEq, Eq, Hash, Deserialize, Serialize, Debug)]
 pub enum Button {
     Create,
     L3,
@@ -9,10 +9,16 @@ use anyhow::{Result, bail};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    common::{HapticPattern, LightbarEffect, MicLedState},
+    audio_reactive::ReactiveConfig,
+    common::{AudioLevels, AudioRouting, HapticPattern, LightbarEffect, MicLedState, SpeakerMode},
     dualsense::BatteryInfo,
-    inputs::ControllerState,
-    profiles::Profile,
+    haptic_synth::{CustomPattern, RumbleHaptics},
+    inputs::{ControllerState, JackState},
+    lightbar_react::LightbarBinding,
+    notify::Notification,
+    player_leds::PlayerLedConfig,
+    profiles::{Profile, TriggerProgram},
+    system_metrics::MetricBinding,
     transform::InputTransform,
 };
 
@@ -32,8 +38,11 @@ mod transport {
     pub fn bind(addr: &Addr) -> io::Result<Listener> {
         UnixListener::bind(addr)
     }
-    pub fn set_timeout(s: &Stream, d: Duration) -> io::Result<()> {
-        s.set_read_timeout(Some(d))
+    pub fn set_timeout(s: &Stream, d: Option<Duration>) -> io::Result<()> {
+        s.set_read_timeout(d)
+    }
+    pub fn shutdown(s: &Stream) -> io::Result<()> {
+        s.shutdown(std::net::Shutdown::Both)
     }
     pub fn addr_to_string(addr: &Addr) -> String {
         addr.display().to_string()
@@ -70,10 +79,19 @@ pub fn addr_display(addr: &DaemonAddr) -> String {
     transport::addr_to_string(addr)
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct DaemonRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+    #[serde(flatten)]
+    pub cmd: DaemonCommand,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(tag = "cmd", content = "args")]
 pub enum DaemonCommand {
     Ping,
+    ListControllers,
     GetBattery,
     GetInputState,
     GetFirmwareInfo,
@@ -90,6 +108,9 @@ pub enum DaemonCommand {
     SetPlayerLeds {
         leds: u8,
     },
+    SetPlayerLedConfig {
+        config: PlayerLedConfig,
+    },
     SetMic {
         enabled: bool,
     },
@@ -107,6 +128,10 @@ pub enum DaemonCommand {
         left: Option<(u8, [u8; 10])>,
         right: Option<(u8, [u8; 10])>,
     },
+    SetTriggerPrograms {
+        left: Option<TriggerProgram>,
+        right: Option<TriggerProgram>,
+    },
     SetVibration {
         rumble: u8,
         trigger: u8,
@@ -115,13 +140,29 @@ pub enum DaemonCommand {
         left: u8,
         right: u8,
     },
+    SetLightbarBindings {
+        bindings: Vec<LightbarBinding>,
+    },
+    SetLightbarMetric {
+        metric: Option<MetricBinding>,
+    },
+    SetRumbleScale {
+        scale: f32,
+    },
+    SetRumbleHaptics {
+        config: RumbleHaptics,
+    },
 
     SetSpeaker {
-        mode: String,
+        mode: SpeakerMode,
+    },
+    SetAudioRouting {
+        routing: Option<AudioRouting>,
     },
-    SetVolume {
-        volume: u8,
+    SetAudioLevels {
+        levels: AudioLevels,
     },
+    GetAudioLevels,
     SetUpdateMode {
         active: bool,
     },
@@ -136,10 +177,36 @@ pub enum DaemonCommand {
         pattern: HapticPattern,
         strength: u8,
         speed: f32,
+        #[serde(default)]
+        balance: f32,
+    },
+    SetCustomHaptic {
+        pattern: Option<CustomPattern>,
     },
     SetRawHaptics {
         active: bool,
     },
+    SetAudioReactive {
+        config: ReactiveConfig,
+    },
+    PlayHapticClip {
+        path: String,
+        gain: f32,
+        #[serde(default)]
+        balance: f32,
+        looping: bool,
+    },
+    StopHapticClip,
+    PlaySound {
+        path: String,
+        gain: f32,
+        looping: bool,
+    },
+    PlayTestTone,
+    StopSound,
+    Notify {
+        notification: Notification,
+    },
     SetGyro {
         enabled: bool,
         smoothing: f32,
@@ -151,15 +218,108 @@ pub enum DaemonCommand {
     ListProfiles,
     ReloadProfile,
     SaveProfile {
-        profile: Profile,
+        profile: Box<Profile>,
     },
     DeleteProfile {
         name: String,
     },
     GetActiveProfile,
+    ReassignPlayers,
+    ReportFirmwareProgress {
+        progress: u32,
+    },
+    Subscribe {
+        topics: Vec<EventTopic>,
+        #[serde(default = "default_input_rate")]
+        input_rate_hz: u32,
+    },
     Shutdown,
 }
 
+fn default_input_rate() -> u32 {
+    DEFAULT_INPUT_RATE_HZ
+}
+
+pub const DEFAULT_INPUT_RATE_HZ: u32 = 125;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EventTopic {
+    Input,
+    Buttons,
+    Battery,
+    Connection,
+    Profile,
+    Firmware,
+    Audio,
+}
+
+impl EventTopic {
+    pub const ALL: [EventTopic; 7] = [
+        EventTopic::Input,
+        EventTopic::Buttons,
+        EventTopic::Battery,
+        EventTopic::Connection,
+        EventTopic::Profile,
+        EventTopic::Firmware,
+        EventTopic::Audio,
+    ];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|t| format!("{:?}", t).eq_ignore_ascii_case(s))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "event", content = "data")]
+pub enum DaemonEvent {
+    Input(ControllerState),
+    Buttons {
+        buttons: u32,
+        dpad: u8,
+        pressed: u32,
+        released: u32,
+    },
+    Battery(BatteryInfo),
+    Connected {
+        serial: String,
+        product_id: u16,
+        is_bt: bool,
+    },
+    Disconnected,
+    ProfileSwitched {
+        name: String,
+    },
+    Firmware {
+        active: bool,
+        progress: u32,
+    },
+    Jack(JackState),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EventMessage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+    #[serde(flatten)]
+    pub event: DaemonEvent,
+}
+
+impl DaemonEvent {
+    pub fn topic(&self) -> EventTopic {
+        match self {
+            DaemonEvent::Input(_) => EventTopic::Input,
+            DaemonEvent::Buttons { .. } => EventTopic::Buttons,
+            DaemonEvent::Battery(_) => EventTopic::Battery,
+            DaemonEvent::Connected { .. } | DaemonEvent::Disconnected => EventTopic::Connection,
+            DaemonEvent::ProfileSwitched { .. } => EventTopic::Profile,
+            DaemonEvent::Firmware { .. } => EventTopic::Firmware,
+            DaemonEvent::Jack(_) => EventTopic::Audio,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
 pub enum DaemonResponse {
@@ -187,25 +347,82 @@ pub enum DaemonResponse {
     ActiveProfile {
         name: String,
     },
+    AudioLevels {
+        levels: AudioLevels,
+    },
+    ControllerList {
+        controllers: Vec<ControllerSummary>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ControllerSummary {
+    pub serial: String,
+    #[serde(default)]
+    pub nickname: String,
+    pub product_id: u16,
+    pub is_bt: bool,
+    pub profile: String,
+    pub battery: Option<BatteryInfo>,
+    #[serde(default)]
+    pub player: u8,
 }
 
 pub struct IpcClient {
     reader: BufReader<DaemonStream>,
     writer: DaemonStream,
+    target: Option<String>,
+}
+
+pub struct EventStream {
+    reader: BufReader<DaemonStream>,
+    _writer: DaemonStream,
+}
+
+impl EventStream {
+    pub fn closer(&self) -> Result<EventStreamCloser> {
+        Ok(EventStreamCloser(self.reader.get_ref().try_clone()?))
+    }
+}
+
+impl Iterator for EventStream {
+    type Item = Result<EventMessage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => Some(serde_json::from_str(line.trim()).map_err(Into::into)),
+            Err(e) => Some(Err(e.into())),
+        }
+    }
+}
+
+pub struct EventStreamCloser(DaemonStream);
+
+impl EventStreamCloser {
+    pub fn close(&self) {
+        let _ = transport::shutdown(&self.0);
+    }
 }
 
 impl IpcClient {
     pub fn connect(addr: &DaemonAddr) -> Result<Self> {
         let stream = transport::connect(addr)?;
-        transport::set_timeout(&stream, Duration::from_secs(5))?;
+        transport::set_timeout(&stream, Some(Duration::from_secs(5)))?;
         let writer = stream.try_clone()?;
 
         Ok(Self {
             reader: BufReader::new(stream),
             writer,
+            target: None,
         })
     }
 
+    pub fn set_target(&mut self, serial: Option<String>) {
+        self.target = serial;
+    }
+
     pub fn try_connect(addr: &DaemonAddr) -> Option<Self> {
         let mut c = Self::connect(addr).ok()?;
         c.send(DaemonCommand::Ping).ok()?;
@@ -213,7 +430,11 @@ impl IpcClient {
     }
 
     pub fn send(&mut self, cmd: DaemonCommand) -> Result<()> {
-        let mut line = serde_json::to_string(&cmd)?;
+        let req = DaemonRequest {
+            serial: self.target.clone(),
+            cmd,
+        };
+        let mut line = serde_json::to_string(&req)?;
         line.push('\n');
         self.writer.write_all(line.as_bytes())?;
         Ok(())
@@ -241,9 +462,9 @@ impl IpcClient {
         }
     }
 
-    pub fn get_input_state(&mut self) -> Result<ControllerState> {
-        match self.request(DaemonCommand::GetInputState)? {
-            DaemonResponse::InputState(s) => Ok(s),
+    pub fn list_controllers(&mut self) -> Result<Vec<ControllerSummary>> {
+        match self.request(DaemonCommand::ListControllers)? {
+            DaemonResponse::ControllerList { controllers } => Ok(controllers),
             DaemonResponse::Error { message } => bail!("{}", message),
             _ => bail!("Unexpected response"),
         }
@@ -284,8 +505,8 @@ impl IpcClient {
         .map(|_| ())
     }
 
-    pub fn set_player_leds(&mut self, leds: u8) -> Result<()> {
-        match self.request(DaemonCommand::SetPlayerLeds { leds })? {
+    pub fn set_player_led_config(&mut self, config: PlayerLedConfig) -> Result<()> {
+        match self.request(DaemonCommand::SetPlayerLedConfig { config })? {
             DaemonResponse::Ok => Ok(()),
             DaemonResponse::Error { message } => bail!("{}", message),
             _ => Ok(()),
@@ -347,6 +568,18 @@ impl IpcClient {
         }
     }
 
+    pub fn set_trigger_programs(
+        &mut self,
+        left: Option<TriggerProgram>,
+        right: Option<TriggerProgram>,
+    ) -> Result<()> {
+        match self.request(DaemonCommand::SetTriggerPrograms { left, right })? {
+            DaemonResponse::Ok => Ok(()),
+            DaemonResponse::Error { message } => bail!("{}", message),
+            _ => Ok(()),
+        }
+    }
+
     pub fn set_vibration(&mut self, rumble: u8, trigger: u8) -> Result<()> {
         match self.request(DaemonCommand::SetVibration { rumble, trigger })? {
             DaemonResponse::Ok => Ok(()),
@@ -355,6 +588,38 @@ impl IpcClient {
         }
     }
 
+    pub fn set_lightbar_bindings(&mut self, bindings: Vec<LightbarBinding>) -> Result<()> {
+        match self.request(DaemonCommand::SetLightbarBindings { bindings })? {
+            DaemonResponse::Ok => Ok(()),
+            DaemonResponse::Error { message } => bail!("{}", message),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn set_lightbar_metric(&mut self, metric: Option<MetricBinding>) -> Result<()> {
+        match self.request(DaemonCommand::SetLightbarMetric { metric })? {
+            DaemonResponse::Ok => Ok(()),
+            DaemonResponse::Error { message } => bail!("{}", message),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn set_rumble_scale(&mut self, scale: f32) -> Result<()> {
+        match self.request(DaemonCommand::SetRumbleScale { scale })? {
+            DaemonResponse::Ok => Ok(()),
+            DaemonResponse::Error { message } => bail!("{}", message),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn set_rumble_haptics(&mut self, config: RumbleHaptics) -> Result<()> {
+        match self.request(DaemonCommand::SetRumbleHaptics { config })? {
+            DaemonResponse::Ok => Ok(()),
+            DaemonResponse::Error { message } => bail!("{}", message),
+            _ => Ok(()),
+        }
+    }
+
     pub fn set_rumble(&mut self, left: u8, right: u8) -> Result<()> {
         match self.request(DaemonCommand::SetRumble { left, right })? {
             DaemonResponse::Ok => Ok(()),
@@ -363,23 +628,37 @@ impl IpcClient {
         }
     }
 
-    pub fn set_speaker(&mut self, mode: &str) -> Result<()> {
-        match self.request(DaemonCommand::SetSpeaker {
-            mode: mode.to_string(),
-        })? {
+    pub fn set_speaker(&mut self, mode: SpeakerMode) -> Result<()> {
+        match self.request(DaemonCommand::SetSpeaker { mode })? {
             DaemonResponse::Ok => Ok(()),
             DaemonResponse::Error { message } => bail!("{}", message),
             _ => Ok(()),
         }
     }
 
-    pub fn set_volume(&mut self, volume: u8) -> Result<()> {
-        match self.request(DaemonCommand::SetVolume { volume })? {
+    pub fn set_audio_routing(&mut self, routing: Option<AudioRouting>) -> Result<()> {
+        match self.request(DaemonCommand::SetAudioRouting { routing })? {
             DaemonResponse::Ok => Ok(()),
             DaemonResponse::Error { message } => bail!("{}", message),
             _ => Ok(()),
         }
     }
+
+    pub fn set_audio_levels(&mut self, levels: AudioLevels) -> Result<()> {
+        match self.request(DaemonCommand::SetAudioLevels { levels })? {
+            DaemonResponse::Ok => Ok(()),
+            DaemonResponse::Error { message } => bail!("{}", message),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn get_audio_levels(&mut self) -> Result<AudioLevels> {
+        match self.request(DaemonCommand::GetAudioLevels)? {
+            DaemonResponse::AudioLevels { levels } => Ok(levels),
+            DaemonResponse::Error { message } => bail!("{}", message),
+            _ => bail!("Unexpected response"),
+        }
+    }
     pub fn set_update_mode(&mut self, active: bool) -> Result<()> {
         self.request(DaemonCommand::SetUpdateMode { active })
             .map(|_| ())
@@ -414,11 +693,13 @@ impl IpcClient {
         pattern: HapticPattern,
         strength: u8,
         speed: f32,
+        balance: f32,
     ) -> Result<()> {
         match self.request(DaemonCommand::SetHapticPattern {
             pattern,
             strength,
             speed,
+            balance,
         })? {
             DaemonResponse::Ok => Ok(()),
             DaemonResponse::Error { message } => bail!("{}", message),
@@ -426,11 +707,86 @@ impl IpcClient {
         }
     }
 
+    pub fn set_custom_haptic(&mut self, pattern: Option<CustomPattern>) -> Result<()> {
+        match self.request(DaemonCommand::SetCustomHaptic { pattern })? {
+            DaemonResponse::Ok => Ok(()),
+            DaemonResponse::Error { message } => bail!("{}", message),
+            _ => Ok(()),
+        }
+    }
+
     pub fn set_raw_haptics(&mut self, active: bool) -> Result<()> {
         self.request(DaemonCommand::SetRawHaptics { active })
             .map(|_| ())
     }
 
+    pub fn set_audio_reactive(&mut self, config: ReactiveConfig) -> Result<()> {
+        match self.request(DaemonCommand::SetAudioReactive { config })? {
+            DaemonResponse::Ok => Ok(()),
+            DaemonResponse::Error { message } => bail!("{}", message),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn play_haptic_clip(
+        &mut self,
+        path: &str,
+        gain: f32,
+        balance: f32,
+        looping: bool,
+    ) -> Result<()> {
+        match self.request(DaemonCommand::PlayHapticClip {
+            path: path.to_string(),
+            gain,
+            balance,
+            looping,
+        })? {
+            DaemonResponse::Ok => Ok(()),
+            DaemonResponse::NoDevice => bail!("Controller not connected"),
+            DaemonResponse::Error { message } => bail!("{}", message),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn stop_haptic_clip(&mut self) -> Result<()> {
+        self.request(DaemonCommand::StopHapticClip).map(|_| ())
+    }
+
+    pub fn play_sound(&mut self, path: &str, gain: f32, looping: bool) -> Result<()> {
+        match self.request(DaemonCommand::PlaySound {
+            path: path.to_string(),
+            gain,
+            looping,
+        })? {
+            DaemonResponse::Ok => Ok(()),
+            DaemonResponse::NoDevice => bail!("Controller not connected"),
+            DaemonResponse::Error { message } => bail!("{}", message),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn play_test_tone(&mut self) -> Result<()> {
+        match self.request(DaemonCommand::PlayTestTone)? {
+            DaemonResponse::Ok => Ok(()),
+            DaemonResponse::NoDevice => bail!("Controller not connected"),
+            DaemonResponse::Error { message } => bail!("{}", message),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn stop_sound(&mut self) -> Result<()> {
+        self.request(DaemonCommand::StopSound).map(|_| ())
+    }
+
+    pub fn notify(&mut self, notification: Notification) -> Result<()> {
+        match self.request(DaemonCommand::Notify { notification })? {
+            DaemonResponse::Ok => Ok(()),
+            DaemonResponse::NoDevice => bail!("Controller not connected"),
+            DaemonResponse::Error { message } => bail!("{}", message),
+            _ => Ok(()),
+        }
+    }
+
     pub fn set_gyro(&mut self, enabled: bool, smoothing: f32, sensitivity: f32) -> Result<()> {
         match self.request(DaemonCommand::SetGyro {
             enabled,
@@ -449,6 +805,7 @@ impl IpcClient {
         })? {
             DaemonResponse::Ok => Ok(()),
             DaemonResponse::Error { message } => bail!("{}", message),
+            DaemonResponse::NoDevice => bail!("Controller not connected"),
             _ => Ok(()),
         }
     }
@@ -470,7 +827,9 @@ impl IpcClient {
     }
 
     pub fn save_profile(&mut self, profile: Profile) -> Result<()> {
-        match self.request(DaemonCommand::SaveProfile { profile })? {
+        match self.request(DaemonCommand::SaveProfile {
+            profile: Box::new(profile),
+        })? {
             DaemonResponse::Ok => Ok(()),
             DaemonResponse::Error { message } => bail!("{}", message),
             _ => Ok(()),
@@ -495,6 +854,35 @@ impl IpcClient {
         }
     }
 
+    pub fn reassign_players(&mut self) -> Result<()> {
+        match self.request(DaemonCommand::ReassignPlayers)? {
+            DaemonResponse::Ok => Ok(()),
+            DaemonResponse::Error { message } => bail!("{}", message),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn report_firmware_progress(&mut self, progress: u32) -> Result<()> {
+        self.request(DaemonCommand::ReportFirmwareProgress { progress })
+            .map(|_| ())
+    }
+
+    pub fn subscribe(mut self, topics: &[EventTopic], input_rate_hz: u32) -> Result<EventStream> {
+        match self.request(DaemonCommand::Subscribe {
+            topics: topics.to_vec(),
+            input_rate_hz,
+        })? {
+            DaemonResponse::Ok => {}
+            DaemonResponse::Error { message } => bail!("{}", message),
+            _ => bail!("Unexpected response"),
+        }
+        transport::set_timeout(self.reader.get_ref(), None)?;
+        Ok(EventStream {
+            reader: self.reader,
+            _writer: self.writer,
+        })
+    }
+
     pub fn shutdown(&mut self) -> Result<()> {
         match self.request(DaemonCommand::Shutdown)? {
             DaemonResponse::Ok => Ok(()),
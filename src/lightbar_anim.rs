@@ -0,0 +1,261 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::profiles::ProfileManager;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    Ease,
+    Step,
+}
+
+impl Easing {
+    pub const ALL: [Easing; 3] = [Easing::Linear, Easing::Ease, Easing::Step];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Easing::Linear => "Linear",
+            Easing::Ease => "Ease",
+            Easing::Step => "Step",
+        }
+    }
+
+    fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::Ease => t * t * (3.0 - 2.0 * t),
+            Easing::Step => 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Debug)]
+pub struct ColorKey {
+    pub color: (u8, u8, u8),
+    pub brightness: u8,
+    pub duration_ms: u32,
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+impl ColorKey {
+    pub fn new(color: (u8, u8, u8), duration_ms: u32) -> Self {
+        Self {
+            color,
+            brightness: 255,
+            duration_ms,
+            easing: Easing::Linear,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct LightbarAnimation {
+    pub name: String,
+    pub looping: bool,
+    pub keys: Vec<ColorKey>,
+}
+
+fn to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn from_linear(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let s = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (s * 255.0).round() as u8
+}
+
+fn to_oklab((r, g, b): (u8, u8, u8)) -> [f32; 3] {
+    let (r, g, b) = (to_linear(r), to_linear(g), to_linear(b));
+    let l = (0.41222147 * r + 0.53633254 * g + 0.051445993 * b).cbrt();
+    let m = (0.2119035 * r + 0.6806995 * g + 0.10739696 * b).cbrt();
+    let s = (0.08830246 * r + 0.28171884 * g + 0.6299787 * b).cbrt();
+    [
+        0.21045426 * l + 0.7936178 * m - 0.004072047 * s,
+        1.9779985 * l - 2.4285922 * m + 0.4505937 * s,
+        0.025904037 * l + 0.78277177 * m - 0.80867577 * s,
+    ]
+}
+
+fn from_oklab([l, a, b]: [f32; 3]) -> (u8, u8, u8) {
+    let l_ = (l + 0.39633778 * a + 0.21580376 * b).powi(3);
+    let m_ = (l - 0.105561346 * a - 0.06385417 * b).powi(3);
+    let s_ = (l - 0.08948418 * a - 1.2914855 * b).powi(3);
+    (
+        from_linear(4.0767417 * l_ - 3.3077116 * m_ + 0.23096993 * s_),
+        from_linear(-1.268438 * l_ + 2.6097574 * m_ - 0.34131938 * s_),
+        from_linear(-0.0041960863 * l_ - 0.7034186 * m_ + 1.7076147 * s_),
+    )
+}
+
+pub fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (u8, u8, u8) {
+    let c = v * s;
+    let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+    let m = v - c;
+    let (r, g, b) = if h < 60.0 {
+        (c, x, 0.0)
+    } else if h < 120.0 {
+        (x, c, 0.0)
+    } else if h < 180.0 {
+        (0.0, c, x)
+    } else if h < 240.0 {
+        (0.0, x, c)
+    } else if h < 300.0 {
+        (x, 0.0, c)
+    } else {
+        (c, 0.0, x)
+    };
+    (
+        ((r + m) * 255.0) as u8,
+        ((g + m) * 255.0) as u8,
+        ((b + m) * 255.0) as u8,
+    )
+}
+
+pub fn mix(from: (u8, u8, u8), to: (u8, u8, u8), t: f32) -> (u8, u8, u8) {
+    let (a, b) = (to_oklab(from), to_oklab(to));
+    from_oklab([0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t))
+}
+
+impl LightbarAnimation {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            looping: true,
+            keys: vec![
+                ColorKey::new((0, 128, 255), 1000),
+                ColorKey::new((255, 0, 128), 1000),
+            ],
+        }
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keys.iter().map(|k| k.duration_ms as f32).sum::<f32>() / 1000.0
+    }
+
+    pub fn key_start(&self, index: usize) -> f32 {
+        self.keys[..index.min(self.keys.len())]
+            .iter()
+            .map(|k| k.duration_ms as f32)
+            .sum::<f32>()
+            / 1000.0
+    }
+
+    pub fn sample(&self, t: f32) -> (u8, u8, u8, u8) {
+        let Some(last) = self.keys.last() else {
+            return (0, 0, 0, 0);
+        };
+        let total = self.duration();
+        if total <= 0.0 || (!self.looping && t >= total) {
+            let (r, g, b) = last.color;
+            return (r, g, b, last.brightness);
+        }
+        let mut t = if self.looping { t % total } else { t.max(0.0) };
+        for (i, key) in self.keys.iter().enumerate() {
+            let span = key.duration_ms as f32 / 1000.0;
+            if t < span {
+                let next = match self.keys.get(i + 1) {
+                    Some(next) => next,
+                    None if self.looping => &self.keys[0],
+                    None => key,
+                };
+                let f = key.easing.apply(t / span);
+                let (r, g, b) = mix(key.color, next.color, f);
+                let br =
+                    key.brightness as f32 + (next.brightness as f32 - key.brightness as f32) * f;
+                return (r, g, b, br.round() as u8);
+            }
+            t -= span;
+        }
+        let (r, g, b) = last.color;
+        (r, g, b, last.brightness)
+    }
+}
+
+pub struct AnimationLibrary {
+    dir: PathBuf,
+}
+
+impl AnimationLibrary {
+    pub fn new() -> Self {
+        let dir = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("ds4u")
+            .join("lightbar");
+        if !dir.exists() {
+            let _ = fs::create_dir_all(&dir);
+        }
+        Self { dir }
+    }
+
+    fn path_for(&self, name: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}.json", ProfileManager::sanitize_filename(name)))
+    }
+
+    pub fn list(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        if let Ok(entries) = fs::read_dir(&self.dir) {
+            for e in entries.flatten() {
+                let path = e.path();
+                if path.extension().and_then(|s| s.to_str()) == Some("json")
+                    && let Ok(json) = fs::read_to_string(&path)
+                    && let Ok(a) = serde_json::from_str::<LightbarAnimation>(&json)
+                {
+                    names.push(a.name);
+                }
+            }
+        }
+        names.sort_by_key(|n| n.to_lowercase());
+        names
+    }
+
+    pub fn load(&self, name: &str) -> Result<LightbarAnimation> {
+        let path = self.path_for(name);
+        if !path.exists() {
+            bail!("Lightbar animation '{}' not found", name);
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, animation: &LightbarAnimation) -> Result<()> {
+        let name = animation.name.trim();
+        if name.is_empty() {
+            bail!("Animation name cannot be empty");
+        }
+        if ProfileManager::sanitize_filename(name) != name {
+            bail!("Animation name '{}' contains invalid characters", name);
+        }
+        if animation.keys.is_empty() {
+            bail!("Animation needs at least one keyframe");
+        }
+        fs::write(
+            self.path_for(name),
+            serde_json::to_string_pretty(animation)?,
+        )?;
+        Ok(())
+    }
+
+    pub fn delete(&self, name: &str) -> Result<()> {
+        let path = self.path_for(name);
+        if !path.exists() {
+            bail!("Lightbar animation '{}' not found", name);
+        }
+        fs::remove_file(path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    inputs::{Button, ControllerState},
+    lightbar_anim::{hsv_to_rgb, mix},
+};
+
+const FLASH_TIME: Duration = Duration::from_millis(150);
+const STICK_DEADZONE: f32 = 0.3;
+const GYRO_AIM_THRESHOLD: f32 = 1500.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum Analog {
+    L2,
+    R2,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum Stick {
+    Left,
+    Right,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub enum TintWhen {
+    Held(Button),
+    Gyro,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub enum LightbarBinding {
+    Brightness {
+        source: Analog,
+        min: u8,
+    },
+    Hue {
+        stick: Stick,
+    },
+    Flash {
+        button: Button,
+        color: (u8, u8, u8),
+    },
+    Tint {
+        when: TintWhen,
+        color: (u8, u8, u8),
+        amount: f32,
+    },
+}
+
+impl LightbarBinding {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LightbarBinding::Brightness { .. } => "Brightness",
+            LightbarBinding::Hue { .. } => "Hue",
+            LightbarBinding::Flash { .. } => "Flash",
+            LightbarBinding::Tint { .. } => "Tint",
+        }
+    }
+}
+
+fn held(button: &Button, buttons: u32) -> bool {
+    button.to_bitmask().is_some_and(|m| buttons & m != 0)
+}
+
+fn stick_angle(x: u8, y: u8) -> Option<f32> {
+    let (dx, dy) = ((x as f32 - 128.0) / 128.0, (128.0 - y as f32) / 128.0);
+    if dx.hypot(dy) < STICK_DEADZONE {
+        return None;
+    }
+    Some(dy.atan2(dx).to_degrees().rem_euclid(360.0))
+}
+
+#[derive(Default)]
+pub struct ReactiveLightbar {
+    flashes: Vec<Option<Instant>>,
+    prev_buttons: u32,
+    last: Option<((u8, u8, u8), Instant)>,
+}
+
+impl ReactiveLightbar {
+    pub fn evaluate(
+        &mut self,
+        bindings: &[LightbarBinding],
+        input: &ControllerState,
+        base: (u8, u8, u8),
+    ) -> (u8, u8, u8) {
+        self.flashes.resize(bindings.len(), None);
+        let pressed = input.buttons & !self.prev_buttons;
+        self.prev_buttons = input.buttons;
+
+        let mut color = base;
+        let mut level = 1.0;
+        for (binding, flash) in bindings.iter().zip(self.flashes.iter_mut()) {
+            match binding {
+                LightbarBinding::Brightness { source, min } => {
+                    let travel = match source {
+                        Analog::L2 => input.l2,
+                        Analog::R2 => input.r2,
+                    } as f32
+                        / 255.0;
+                    let min = *min as f32 / 255.0;
+                    level *= min + (1.0 - min) * travel;
+                }
+                LightbarBinding::Hue { stick } => {
+                    let angle = match stick {
+                        Stick::Left => stick_angle(input.left_x, input.left_y),
+                        Stick::Right => stick_angle(input.right_x, input.right_y),
+                    };
+                    if let Some(hue) = angle {
+                        color = hsv_to_rgb(hue, 1.0, 1.0);
+                    }
+                }
+                LightbarBinding::Flash { button, color: c } => {
+                    if held(button, pressed) {
+                        *flash = Some(Instant::now());
+                    }
+                    if flash.is_some_and(|t| t.elapsed() < FLASH_TIME) {
+                        color = *c;
+                    }
+                }
+                LightbarBinding::Tint {
+                    when,
+                    color: c,
+                    amount,
+                } => {
+                    let active = match when {
+                        TintWhen::Held(button) => held(button, input.buttons),
+                        TintWhen::Gyro => {
+                            let [x, y, z] = input.gyro.map(|v| v as f32);
+                            (x * x + y * y + z * z).sqrt() > GYRO_AIM_THRESHOLD
+                        }
+                    };
+                    if active {
+                        color = mix(color, *c, amount.clamp(0.0, 1.0));
+                    }
+                }
+            }
+        }
+        (
+            (color.0 as f32 * level) as u8,
+            (color.1 as f32 * level) as u8,
+            (color.2 as f32 * level) as u8,
+        )
+    }
+
+    pub fn should_send(&mut self, color: (u8, u8, u8), interval: Duration) -> bool {
+        match self.last {
+            Some((last, _)) if last == color => false,
+            Some((_, at)) if at.elapsed() < interval => false,
+            _ => {
+                self.last = Some((color, Instant::now()));
+                true
+            }
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        self.last.is_some()
+    }
+
+    // Another writer (a notification flash) owns the lightbar; send the next colour regardless.
+    pub fn interrupt(&mut self) {
+        self.last = None;
+    }
+
+    pub fn reset(&mut self) {
+        self.flashes.clear();
+        self.last = None;
+    }
+}
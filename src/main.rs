@@ -5,25 +5,38 @@ use crate::app::DS4UApp;
 use self::ipc::IpcClient;
 
 mod app;
+mod audio_reactive;
+mod audio_visualizer;
 mod backend;
 mod common;
 mod daemon;
+mod dsx;
 mod dualsense;
 mod firmware;
 mod firmware_controller;
+mod haptic_clip;
+mod haptic_synth;
+mod haptics_stream;
 mod input_poller;
 mod inputs;
-mod haptics_stream;
-mod usb_haptics;
 mod ipc;
+mod lightbar_anim;
+mod lightbar_react;
+mod mixer;
+mod notify;
+mod player_leds;
 mod profiles;
 mod settings;
 mod state;
 mod style;
+mod system_metrics;
 mod theme;
 mod transform;
+mod trigger_presets;
 mod ui;
+mod usb_haptics;
 mod util;
+mod virtual_pad;
 
 fn main() -> Result<(), eframe::Error> {
     let args: Vec<String> = env::args().collect();
@@ -59,12 +72,208 @@ fn main() -> Result<(), eframe::Error> {
                 return Ok(());
             }
             "--switch-profile" => {
-                let name = args.get(2).map(|s| s.as_str()).unwrap_or_else(|| {
-                    eprintln!("usage: ds4u --switch-profile <name>");
+                let usage = || -> ! {
+                    eprintln!("usage: ds4u --switch-profile [--serial <serial>] <name>");
                     std::process::exit(1);
-                });
+                };
+                let mut serial = None;
+                let mut name = None;
+                let mut rest = args[2..].iter();
+                while let Some(arg) = rest.next() {
+                    match arg.as_str() {
+                        "--serial" => serial = Some(rest.next().unwrap_or_else(|| usage()).clone()),
+                        _ if name.is_none() => name = Some(arg.as_str()),
+                        _ => usage(),
+                    }
+                }
+                let name = name.unwrap_or_else(|| usage());
+                let target = serial.as_deref().unwrap_or("all controllers").to_string();
+                client.set_target(serial);
                 match client.switch_profile(name) {
-                    Ok(_) => println!("switched to profile '{}'", name),
+                    Ok(_) => println!("switched {} to profile '{}'", target, name),
+                    Err(e) => {
+                        eprintln!("error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+                return Ok(());
+            }
+            "--play-haptic" => {
+                let usage = || -> ! {
+                    eprintln!(
+                        "usage: ds4u --play-haptic [--serial <serial>] [--gain <gain>] [--balance <-1..1>] [--loop] <file>"
+                    );
+                    std::process::exit(1);
+                };
+                let mut serial = None;
+                let mut gain = 1.0;
+                let mut balance = 0.0;
+                let mut looping = false;
+                let mut file = None;
+                let mut rest = args[2..].iter();
+                while let Some(arg) = rest.next() {
+                    match arg.as_str() {
+                        "--serial" => serial = Some(rest.next().unwrap_or_else(|| usage()).clone()),
+                        "--gain" => {
+                            gain = rest
+                                .next()
+                                .and_then(|g| g.parse().ok())
+                                .unwrap_or_else(|| usage())
+                        }
+                        "--balance" => {
+                            balance = rest
+                                .next()
+                                .and_then(|b| b.parse().ok())
+                                .unwrap_or_else(|| usage())
+                        }
+                        "--loop" => looping = true,
+                        _ if file.is_none() => file = Some(arg.as_str()),
+                        _ => usage(),
+                    }
+                }
+                let file = file.unwrap_or_else(|| usage());
+                let path = match std::fs::canonicalize(file) {
+                    Ok(p) => p,
+                    Err(e) => {
+                        eprintln!("error: {}: {}", file, e);
+                        std::process::exit(1);
+                    }
+                };
+                client.set_target(serial);
+                match client.play_haptic_clip(&path.to_string_lossy(), gain, balance, looping) {
+                    Ok(_) => println!("playing {}", path.display()),
+                    Err(e) => {
+                        eprintln!("error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+                return Ok(());
+            }
+            "--stop-haptic" => {
+                if let Some(serial) = args.get(3).filter(|_| args[2] == "--serial") {
+                    client.set_target(Some(serial.clone()));
+                }
+                match client.stop_haptic_clip() {
+                    Ok(_) => println!("haptic clip stopped"),
+                    Err(e) => {
+                        eprintln!("error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+                return Ok(());
+            }
+            "--play-sound" => {
+                let usage = || -> ! {
+                    eprintln!(
+                        "usage: ds4u --play-sound [--serial <serial>] [--gain <gain>] [--loop] <file>"
+                    );
+                    std::process::exit(1);
+                };
+                let mut serial = None;
+                let mut gain = 1.0;
+                let mut looping = false;
+                let mut file = None;
+                let mut rest = args[2..].iter();
+                while let Some(arg) = rest.next() {
+                    match arg.as_str() {
+                        "--serial" => serial = Some(rest.next().unwrap_or_else(|| usage()).clone()),
+                        "--gain" => {
+                            gain = rest
+                                .next()
+                                .and_then(|g| g.parse().ok())
+                                .unwrap_or_else(|| usage())
+                        }
+                        "--loop" => looping = true,
+                        _ if file.is_none() => file = Some(arg.as_str()),
+                        _ => usage(),
+                    }
+                }
+                let file = file.unwrap_or_else(|| usage());
+                let path = match std::fs::canonicalize(file) {
+                    Ok(p) => p,
+                    Err(e) => {
+                        eprintln!("error: {}: {}", file, e);
+                        std::process::exit(1);
+                    }
+                };
+                client.set_target(serial);
+                match client.play_sound(&path.to_string_lossy(), gain, looping) {
+                    Ok(_) => println!("playing {}", path.display()),
+                    Err(e) => {
+                        eprintln!("error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+                return Ok(());
+            }
+            "--test-speaker" | "--stop-sound" => {
+                if let Some(serial) = args.get(3).filter(|_| args[2] == "--serial") {
+                    client.set_target(Some(serial.clone()));
+                }
+                let result = if args[1] == "--test-speaker" {
+                    client.play_test_tone().map(|_| "playing test tone")
+                } else {
+                    client.stop_sound().map(|_| "sound stopped")
+                };
+                match result {
+                    Ok(msg) => println!("{}", msg),
+                    Err(e) => {
+                        eprintln!("error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+                return Ok(());
+            }
+            "--notify" => {
+                let usage = || -> ! {
+                    eprintln!(
+                        "usage: ds4u --notify [--serial <serial>] [--flash <rrggbb>] [--buzz short|long|double] [--duration <ms|500ms|2s>] [--priority <n>]"
+                    );
+                    std::process::exit(1);
+                };
+                let mut serial = None;
+                let mut notification = notify::Notification {
+                    flash: None,
+                    buzz: None,
+                    duration_ms: 1000,
+                    priority: 0,
+                };
+                let mut rest = args[2..].iter();
+                while let Some(arg) = rest.next() {
+                    match arg.as_str() {
+                        "--serial" => serial = Some(rest.next().unwrap_or_else(|| usage()).clone()),
+                        "--flash" => {
+                            notification.flash = Some(
+                                rest.next()
+                                    .and_then(|c| notify::parse_color(c))
+                                    .unwrap_or_else(|| usage()),
+                            )
+                        }
+                        "--buzz" => {
+                            notification.buzz = Some(
+                                rest.next()
+                                    .and_then(|b| notify::Buzz::parse(b))
+                                    .unwrap_or_else(|| usage()),
+                            )
+                        }
+                        "--duration" => {
+                            notification.duration_ms = rest
+                                .next()
+                                .and_then(|d| notify::parse_duration(d))
+                                .unwrap_or_else(|| usage())
+                        }
+                        "--priority" => {
+                            notification.priority = rest
+                                .next()
+                                .and_then(|p| p.parse().ok())
+                                .unwrap_or_else(|| usage())
+                        }
+                        _ => usage(),
+                    }
+                }
+                client.set_target(serial);
+                match client.notify(notification) {
+                    Ok(_) => println!("notification sent"),
                     Err(e) => {
                         eprintln!("error: {}", e);
                         std::process::exit(1);
@@ -83,21 +292,67 @@ fn main() -> Result<(), eframe::Error> {
                 return Ok(());
             }
             "--status" => {
-                match client.get_controller_info() {
-                    Ok(Some((serial, pid, is_bt))) => println!(
-                        "connected  serial={}  pid={:#06x}  {}",
-                        serial,
-                        pid,
-                        if is_bt { "bluetooth" } else { "usb" }
-                    ),
-                    Ok(None) => println!("no device"),
+                match client.list_controllers() {
+                    Ok(list) if list.is_empty() => println!("no device"),
+                    Ok(list) => {
+                        for c in list {
+                            println!(
+                                "{}  pid={:#06x}  {}  profile={}",
+                                c.serial,
+                                c.product_id,
+                                if c.is_bt { "bluetooth" } else { "usb" },
+                                c.profile
+                            );
+                            if !c.nickname.is_empty() {
+                                println!("    nickname   {}", c.nickname);
+                            }
+                            if c.player != 0 {
+                                println!("    player     {}", c.player);
+                            }
+                            if let Some(b) = c.battery {
+                                println!("    battery    {}% ({})", b.capacity, b.status);
+                            }
+                        }
+                    }
                     Err(e) => {
                         eprintln!("error: {}", e);
                         std::process::exit(1);
                     }
                 }
-                if let Ok(b) = client.get_battery() {
-                    println!("battery    {:?}", b)
+                return Ok(());
+            }
+            "--watch" => {
+                let mut topics = Vec::new();
+                for name in &args[2..] {
+                    match ipc::EventTopic::parse(name) {
+                        Some(t) => topics.push(t),
+                        None => {
+                            eprintln!("unknown topic '{}'", name);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                if topics.is_empty() {
+                    topics = ipc::EventTopic::ALL
+                        .into_iter()
+                        .filter(|t| *t != ipc::EventTopic::Input)
+                        .collect();
+                }
+                let events = match client.subscribe(&topics, ipc::DEFAULT_INPUT_RATE_HZ) {
+                    Ok(e) => e,
+                    Err(e) => {
+                        eprintln!("error: {}", e);
+                        std::process::exit(1);
+                    }
+                };
+                for event in events {
+                    match event.and_then(|m| Ok(serde_json::to_string(&m)?)) {
+                        Ok(line) => println!("{}", line),
+                        Err(e) => {
+                            eprintln!("error: {}", e);
+                            std::process::exit(1);
+                        }
+                    }
                 }
                 return Ok(());
             }
@@ -0,0 +1,162 @@
+use alsa::mixer::{Mixer, Selem, SelemChannelId, SelemId};
+use anyhow::{Context, Result, anyhow};
+
+use crate::usb_haptics::find_dualsense_card;
+
+pub fn controller_mixer() -> Option<String> {
+    find_dualsense_card().map(|card| format!("hw:{card}"))
+}
+
+pub fn list_mixers() -> Vec<(String, String)> {
+    let mut mixers = vec![("default".to_string(), "Default".to_string())];
+    for card in alsa::card::Iter::new().flatten() {
+        let name = card.get_name().unwrap_or_default();
+        mixers.push((format!("hw:{}", card.get_index()), name));
+    }
+    mixers
+}
+
+pub fn capture_switches(device: &str) -> Vec<String> {
+    let Ok(mixer) = Mixer::new(device, false) else {
+        return Vec::new();
+    };
+    mixer
+        .iter()
+        .filter_map(Selem::new)
+        .filter(|s| s.has_capture_switch())
+        .filter_map(|s| s.get_id().get_name().ok().map(str::to_string))
+        .collect()
+}
+
+pub struct CaptureSwitch {
+    mixer: Mixer,
+    id: SelemId,
+    device: String,
+    element: String,
+}
+
+impl CaptureSwitch {
+    pub fn open(device: &str, element: &str) -> Result<Self> {
+        let mixer =
+            Mixer::new(device, false).with_context(|| format!("opening ALSA mixer {device}"))?;
+        let id = if element.is_empty() {
+            mixer
+                .iter()
+                .filter_map(Selem::new)
+                .find(|s| s.has_capture_switch())
+                .map(|s| s.get_id())
+        } else {
+            let id = SelemId::new(element, 0);
+            mixer
+                .find_selem(&id)
+                .filter(|s| s.has_capture_switch())
+                .map(|_| id)
+        }
+        .ok_or_else(|| anyhow!("no capture switch '{}' on {}", element, device))?;
+        let element = id.get_name()?.to_string();
+        Ok(Self {
+            mixer,
+            id,
+            device: device.to_string(),
+            element,
+        })
+    }
+
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    pub fn element(&self) -> &str {
+        &self.element
+    }
+
+    fn selem(&self) -> Result<Selem<'_>> {
+        self.mixer
+            .find_selem(&self.id)
+            .ok_or_else(|| anyhow!("mixer element '{}' disappeared", self.element))
+    }
+
+    pub fn muted(&self) -> Result<bool> {
+        self.mixer.handle_events()?;
+        Ok(self.selem()?.get_capture_switch(SelemChannelId::mono())? == 0)
+    }
+
+    pub fn set_muted(&self, muted: bool) -> Result<()> {
+        self.selem()?.set_capture_switch_all(!muted as i32)?;
+        Ok(())
+    }
+}
+
+pub struct VolumeControl {
+    mixer: Mixer,
+    id: SelemId,
+    capture: bool,
+    range: (i64, i64),
+}
+
+impl VolumeControl {
+    pub fn open(device: &str, capture: bool) -> Result<Self> {
+        let mixer =
+            Mixer::new(device, false).with_context(|| format!("opening ALSA mixer {device}"))?;
+        let selem = mixer
+            .iter()
+            .filter_map(Selem::new)
+            .find(|s| {
+                if capture {
+                    s.has_capture_volume()
+                } else {
+                    s.has_playback_volume()
+                }
+            })
+            .ok_or_else(|| anyhow!("no volume control on {}", device))?;
+        let range = if capture {
+            selem.get_capture_volume_range()
+        } else {
+            selem.get_playback_volume_range()
+        };
+        let id = selem.get_id();
+        Ok(Self {
+            mixer,
+            id,
+            capture,
+            range,
+        })
+    }
+
+    fn selem(&self) -> Result<Selem<'_>> {
+        self.mixer
+            .find_selem(&self.id)
+            .ok_or_else(|| anyhow!("mixer volume control disappeared"))
+    }
+
+    pub fn get(&self) -> Result<i64> {
+        self.mixer.handle_events()?;
+        let selem = self.selem()?;
+        Ok(if self.capture {
+            selem.get_capture_volume(SelemChannelId::mono())?
+        } else {
+            selem.get_playback_volume(SelemChannelId::mono())?
+        })
+    }
+
+    pub fn set(&self, raw: i64) -> Result<()> {
+        let selem = self.selem()?;
+        if self.capture {
+            selem.set_capture_volume_all(raw)?;
+        } else {
+            selem.set_playback_volume_all(raw)?;
+        }
+        Ok(())
+    }
+
+    pub fn to_level(&self, raw: i64) -> u8 {
+        let (min, max) = self.range;
+        let span = (max - min).max(1);
+        (((raw - min).clamp(0, span) * 255 + span / 2) / span) as u8
+    }
+
+    pub fn to_raw(&self, level: u8) -> i64 {
+        let (min, max) = self.range;
+        min + ((max - min) * level as i64 + 127) / 255
+    }
+}
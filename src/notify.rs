@@ -0,0 +1,156 @@
+use std::f32::consts::TAU;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::haptic_clip::HapticClip;
+
+const FLASH_HZ: f32 = 4.0;
+const BUZZ_HZ: f32 = 160.0;
+const BUZZ_RATE: u32 = 48_000;
+const MAX_DURATION_MS: u64 = 60_000;
+
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Debug)]
+pub enum Buzz {
+    Short,
+    Long,
+    Double,
+}
+
+impl Buzz {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "short" => Some(Buzz::Short),
+            "long" => Some(Buzz::Long),
+            "double" => Some(Buzz::Double),
+            _ => None,
+        }
+    }
+
+    // One cycle of the pattern, including the pause before it repeats.
+    fn period(self) -> f32 {
+        match self {
+            Buzz::Short => 0.5,
+            Buzz::Long => 1.0,
+            Buzz::Double => 0.8,
+        }
+    }
+
+    fn level(self, t: f32) -> f32 {
+        let on = match self {
+            Buzz::Short => t < 0.12,
+            Buzz::Long => t < 0.5,
+            Buzz::Double => t < 0.1 || (0.2..0.3).contains(&t),
+        };
+        if on { 1.0 } else { 0.0 }
+    }
+
+    // Rendered as a voice-coil tone so it plays over the profile's own haptics.
+    // The pattern repeats until the notification's duration runs out.
+    pub fn clip(self, duration_ms: u64) -> HapticClip {
+        let len = (duration_ms * BUZZ_RATE as u64 / 1000) as usize;
+        let frames = (0..len)
+            .map(|i| {
+                let t = i as f32 / BUZZ_RATE as f32;
+                let v = (TAU * BUZZ_HZ * t).sin() * self.level(t % self.period());
+                [v, v]
+            })
+            .collect();
+        HapticClip {
+            name: format!("{self:?} buzz").to_lowercase(),
+            rate: BUZZ_RATE,
+            frames,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct Notification {
+    pub flash: Option<(u8, u8, u8)>,
+    pub buzz: Option<Buzz>,
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub priority: u8,
+}
+
+impl Notification {
+    pub fn validate(&self) -> Result<()> {
+        if self.flash.is_none() && self.buzz.is_none() {
+            bail!("Notification needs a flash color or a buzz");
+        }
+        if self.duration_ms == 0 || self.duration_ms > MAX_DURATION_MS {
+            bail!(
+                "Notification duration must be between 1 ms and {} s",
+                MAX_DURATION_MS / 1000
+            );
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_color(s: &str) -> Option<(u8, u8, u8)> {
+    let s = s.trim_start_matches('#');
+    if s.len() != 6 {
+        return None;
+    }
+    let v = u32::from_str_radix(s, 16).ok()?;
+    Some(((v >> 16) as u8, (v >> 8) as u8, v as u8))
+}
+
+pub fn parse_duration(s: &str) -> Option<u64> {
+    if let Some(ms) = s.strip_suffix("ms") {
+        ms.parse().ok()
+    } else if let Some(secs) = s.strip_suffix('s') {
+        secs.parse::<f32>().ok().map(|v| (v * 1000.0) as u64)
+    } else {
+        s.parse().ok()
+    }
+}
+
+pub struct ActiveNotification {
+    spec: Notification,
+    started: Instant,
+}
+
+impl ActiveNotification {
+    pub fn new(spec: Notification) -> Self {
+        Self {
+            spec,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn priority(&self) -> u8 {
+        self.spec.priority
+    }
+
+    pub fn expired(&self) -> bool {
+        self.started.elapsed() >= Duration::from_millis(self.spec.duration_ms)
+    }
+
+    pub fn flash(&self) -> Option<(u8, u8, u8)> {
+        let (r, g, b) = self.spec.flash?;
+        let t = self.started.elapsed().as_secs_f32();
+        if (t * FLASH_HZ).fract() < 0.5 {
+            Some((r, g, b))
+        } else {
+            Some((0, 0, 0))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buzz_repeats_for_the_notification_duration() {
+        let clip = Buzz::Short.clip(2000);
+        assert_eq!(clip.frames.len(), 2 * BUZZ_RATE as usize);
+
+        let second = (1.5 * BUZZ_RATE as f32) as usize;
+        let window = &clip.frames[second..second + BUZZ_RATE as usize / 10];
+        assert!(window.iter().any(|f| f[0].abs() > 0.5));
+    }
+}
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+use crate::dualsense::PLAYER_LED_PATTERNS;
+
+pub const PLAYER_LED_COUNT: u8 = 5;
+const PLAYER_LED_MASK: u8 = (1 << PLAYER_LED_COUNT) - 1;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug, Default)]
+pub enum PlayerLedBrightness {
+    #[default]
+    High,
+    Medium,
+    Low,
+}
+
+impl PlayerLedBrightness {
+    pub const ALL: [PlayerLedBrightness; 3] = [
+        PlayerLedBrightness::High,
+        PlayerLedBrightness::Medium,
+        PlayerLedBrightness::Low,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PlayerLedBrightness::High => "High",
+            PlayerLedBrightness::Medium => "Medium",
+            PlayerLedBrightness::Low => "Low",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum PlayerLedAnimation {
+    Chase,
+    Blink,
+    CountUp,
+}
+
+impl PlayerLedAnimation {
+    pub const ALL: [PlayerLedAnimation; 3] = [
+        PlayerLedAnimation::Chase,
+        PlayerLedAnimation::Blink,
+        PlayerLedAnimation::CountUp,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PlayerLedAnimation::Chase => "Chase",
+            PlayerLedAnimation::Blink => "Blink",
+            PlayerLedAnimation::CountUp => "Count Up",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct PlayerLedConfig {
+    pub pattern: u8,
+    pub brightness: PlayerLedBrightness,
+    pub animation: Option<PlayerLedAnimation>,
+    pub speed: f32,
+}
+
+impl Default for PlayerLedConfig {
+    fn default() -> Self {
+        Self::from_index(1)
+    }
+}
+
+impl PlayerLedConfig {
+    pub fn from_index(index: u8) -> Self {
+        Self {
+            pattern: PLAYER_LED_PATTERNS
+                .get(index as usize)
+                .copied()
+                .unwrap_or(0),
+            brightness: PlayerLedBrightness::High,
+            animation: None,
+            speed: 1.0,
+        }
+    }
+
+    pub fn preset_index(&self) -> Option<u8> {
+        PLAYER_LED_PATTERNS
+            .iter()
+            .position(|&p| p == self.pattern & PLAYER_LED_MASK)
+            .map(|i| i as u8)
+    }
+
+    pub fn frame(&self, t: f32) -> u8 {
+        let phase = (t * self.speed.max(0.05)).fract();
+        match self.animation {
+            None => self.pattern & PLAYER_LED_MASK,
+            Some(PlayerLedAnimation::Chase) => {
+                let span = PLAYER_LED_COUNT as usize * 2 - 2;
+                let step = (phase * span as f32) as usize;
+                let pos = if step < PLAYER_LED_COUNT as usize {
+                    step
+                } else {
+                    span - step
+                };
+                1 << pos
+            }
+            Some(PlayerLedAnimation::Blink) => {
+                if phase < 0.5 {
+                    self.pattern & PLAYER_LED_MASK
+                } else {
+                    0
+                }
+            }
+            Some(PlayerLedAnimation::CountUp) => {
+                let lit = (phase * (PLAYER_LED_COUNT + 1) as f32) as u8;
+                (1u8 << lit) - 1
+            }
+        }
+    }
+}
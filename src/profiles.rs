@@ -8,8 +8,14 @@ use anyhow::{Result, bail};
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    audio_reactive::ReactiveConfig,
+    audio_visualizer::VisualizerConfig,
     common::*,
+    haptic_synth::RumbleHaptics,
     inputs::Button,
+    lightbar_react::LightbarBinding,
+    player_leds::PlayerLedConfig,
+    system_metrics::MetricBinding,
     transform::{GyroProcessor, InputTransform, TriggerDeadband},
 };
 
@@ -20,6 +26,18 @@ pub struct TriggerConfig {
     pub end: u8,
     pub strength: u8,
     pub frequency: u8,
+    #[serde(default)]
+    pub zones: Option<[u8; 10]>, // 0 off, 1-8 strength
+    #[serde(default)]
+    pub snap: Option<u8>,
+    #[serde(default)]
+    pub amplitude_b: Option<u8>,
+    #[serde(default)]
+    pub period: u8,
+    #[serde(default)]
+    pub feet: Option<(u8, u8)>,
+    #[serde(default)]
+    pub raw: Option<(u8, [u8; 10])>,
     pub deadband: TriggerDeadband,
 }
 
@@ -31,33 +49,68 @@ impl Default for TriggerConfig {
             end: 7,
             strength: 5,
             frequency: 30,
+            zones: None,
+            snap: None,
+            amplitude_b: None,
+            period: 0,
+            feet: None,
+            raw: None,
             deadband: TriggerDeadband::default(),
         }
     }
 }
 
+fn pack_zones(zones: &[u8; 10]) -> [u8; 6] {
+    let mut active: u16 = 0;
+    let mut values: u32 = 0;
+    for (i, &z) in zones.iter().enumerate() {
+        if z > 0 {
+            active |= 1 << i;
+            values |= ((z.min(8) - 1) as u32 & 0x07) << (3 * i);
+        }
+    }
+    let mut p = [0u8; 6];
+    p[..2].copy_from_slice(&active.to_le_bytes());
+    p[2..].copy_from_slice(&values.to_le_bytes());
+    p
+}
+
 impl TriggerConfig {
+    // Profiles saved before these had their own fields derived them from
+    // strength, so unset values keep feeling the same.
+    pub fn snap(&self) -> u8 {
+        self.snap.unwrap_or(self.strength)
+    }
+
+    pub fn amplitude_b(&self) -> u8 {
+        self.amplitude_b.unwrap_or(self.strength)
+    }
+
+    pub fn feet(&self) -> (u8, u8) {
+        self.feet.unwrap_or_else(|| {
+            let first = self.strength.saturating_sub(1);
+            (first, first + 1)
+        })
+    }
+
+    pub fn zone_strengths(&self) -> [u8; 10] {
+        if let Some(zones) = self.zones {
+            return zones.map(|z| z.min(8));
+        }
+        let start = self.start.min(9);
+        let end = self.end.clamp(start, 9) as usize;
+        let start = start as usize;
+        let mut zones = [0u8; 10];
+        zones[start..=end].fill(self.strength.clamp(1, 8));
+        zones
+    }
+
     pub fn to_effect(&self) -> (u8, [u8; 10]) {
         match self.mode {
             TriggerMode::Off => (DS_TRIGGER_EFFECT_OFF, [0; 10]),
             TriggerMode::Feedback => {
-                let start = self.start.min(9) as usize;
-                let end = self.end.clamp(self.start, 9) as usize;
-                let strength = self.strength.clamp(1, 8);
-                let mut active_zones: u16 = 0;
-                let mut strength_zones: u32 = 0;
-                for i in start..=end {
-                    let sv = ((strength - 1) & 0x07) as u32;
-                    strength_zones |= sv << (3 * i);
-                    active_zones |= 1 << i;
-                }
                 let mut p = [0u8; 10];
-                p[0] = (active_zones & 0xff) as u8;
-                p[1] = ((active_zones >> 8) & 0xff) as u8;
-                p[2] = (strength_zones & 0xff) as u8;
-                p[3] = ((strength_zones >> 8) & 0xff) as u8;
-                p[4] = ((strength_zones >> 16) & 0xff) as u8;
-                p[5] = ((strength_zones >> 24) & 0xff) as u8;
+                p[..6].copy_from_slice(&pack_zones(&self.zone_strengths()));
                 (DS_TRIGGER_EFFECT_FEEDBACK, p)
             }
             TriggerMode::Weapon => {
@@ -72,10 +125,10 @@ impl TriggerConfig {
                 (DS_TRIGGER_EFFECT_WEAPON, p)
             }
             TriggerMode::Bow => {
-                let start = self.start.min(8);
+                let start = self.start.min(7);
                 let end = self.end.clamp(start + 1, 8);
                 let strength = self.strength.clamp(1, 8);
-                let snap = self.strength.clamp(1, 8);
+                let snap = self.snap().clamp(1, 8);
                 let mut p = [0u8; 10];
                 let positions: u16 = (1u16 << start) | (1u16 << end);
                 let force: u32 = ((strength - 1) as u32 & 0x07) | (((snap - 1) as u32 & 0x07) << 3);
@@ -88,8 +141,9 @@ impl TriggerConfig {
             TriggerMode::Galloping => {
                 let start = self.start.min(8);
                 let end = self.end.clamp(start + 1, 9);
-                let first_foot = self.strength.saturating_sub(1).min(7);
-                let second_foot = first_foot.saturating_add(1).min(7);
+                let (first_foot, second_foot) = self.feet();
+                let first_foot = first_foot.min(6);
+                let second_foot = second_foot.clamp(first_foot + 1, 7);
                 let freq = self.frequency.max(1);
                 let mut p = [0u8; 10];
                 p[0] = start;
@@ -99,40 +153,88 @@ impl TriggerConfig {
                 (DS_TRIGGER_EFFECT_GALLOPING, p)
             }
             TriggerMode::Vibration => {
-                let start = self.start.min(9) as usize;
-                let end = self.end.clamp(self.start, 9) as usize;
-                let strength = self.strength.clamp(1, 8);
-                let freq = self.frequency.max(1);
-                let mut active_zones: u16 = 0;
-                let mut amp_zones: u32 = 0;
-                for i in start..=end {
-                    active_zones |= 1 << i;
-                    amp_zones |= ((strength - 1) as u32 & 0x07) << (3 * i);
-                }
                 let mut p = [0u8; 10];
-                p[0] = (active_zones & 0xff) as u8;
-                p[1] = ((active_zones >> 8) & 0xff) as u8;
-                p[2] = (amp_zones & 0xff) as u8;
-                p[3] = ((amp_zones >> 8) & 0xff) as u8;
-                p[4] = ((amp_zones >> 16) & 0xff) as u8;
-                p[5] = ((amp_zones >> 24) & 0xff) as u8;
-                p[6] = freq;
+                p[..6].copy_from_slice(&pack_zones(&self.zone_strengths()));
+                p[6] = self.frequency.max(1);
                 (DS_TRIGGER_EFFECT_VIBRATION, p)
             }
             TriggerMode::Machine => {
                 let start = self.start.min(8);
                 let end = self.end.clamp(start + 1, 9);
                 let amp_a = self.strength.clamp(1, 8);
-                let amp_b = self.strength.clamp(1, 8);
+                let amp_b = self.amplitude_b().clamp(1, 8);
                 let freq = self.frequency.max(1);
                 let mut p = [0u8; 10];
                 p[0] = start;
                 p[1] = end;
                 p[2] = ((amp_b - 1) << 4) | (amp_a - 1);
                 p[3] = freq;
-                p[4] = 0;
+                p[4] = self.period;
                 (DS_TRIGGER_EFFECT_MACHINE, p)
             }
+            TriggerMode::Raw => self.raw.unwrap_or((DS_TRIGGER_EFFECT_OFF, [0; 10])),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub enum TriggerProgram {
+    Ramp {
+        min: u8,
+        max: u8,
+    },
+    Threshold {
+        threshold: u8,
+        effect: TriggerConfig,
+    },
+    Hold {
+        button: Button,
+        effect: TriggerConfig,
+    },
+}
+
+impl TriggerProgram {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TriggerProgram::Ramp { .. } => "Ramp",
+            TriggerProgram::Threshold { .. } => "Threshold",
+            TriggerProgram::Hold { .. } => "Hold",
+        }
+    }
+
+    pub fn evaluate(
+        &self,
+        base: Option<(u8, [u8; 10])>,
+        travel: u8,
+        buttons: u32,
+    ) -> Option<(u8, [u8; 10])> {
+        let active = |effect: &TriggerConfig| match effect.mode {
+            TriggerMode::Off => None,
+            _ => Some(effect.to_effect()),
+        };
+        match self {
+            TriggerProgram::Ramp { min, max } => {
+                let (min, max) = ((*min).min(8) as f32, (*max).min(8) as f32);
+                let strength = (min + (max - min) * travel as f32 / 255.0).round() as u8;
+                if strength == 0 {
+                    return None;
+                }
+                let ramp = TriggerConfig {
+                    mode: TriggerMode::Feedback,
+                    zones: Some([strength; 10]),
+                    ..TriggerConfig::default()
+                };
+                Some(ramp.to_effect())
+            }
+            TriggerProgram::Threshold { threshold, effect } if travel >= *threshold => {
+                active(effect)
+            }
+            TriggerProgram::Hold { button, effect }
+                if button.to_bitmask().is_some_and(|m| buttons & m != 0) =>
+            {
+                active(effect)
+            }
+            _ => base,
         }
     }
 }
@@ -141,6 +243,10 @@ fn default_touchpad_sensitivity() -> f32 {
     1.0
 }
 
+fn default_rumble_scale() -> f32 {
+    1.0
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 pub struct Profile {
     pub name: String,
@@ -190,6 +296,10 @@ pub struct Profile {
     pub trigger_left_config: TriggerConfig,
     #[serde(default)]
     pub trigger_right_config: TriggerConfig,
+    #[serde(default)]
+    pub trigger_left_program: Option<TriggerProgram>,
+    #[serde(default)]
+    pub trigger_right_program: Option<TriggerProgram>,
 
     #[serde(default)]
     pub gyro: GyroProcessor,
@@ -200,6 +310,32 @@ pub struct Profile {
     pub haptic_strength: u8, // 0-7
     #[serde(default)]
     pub haptic_speed: f32, // Hz
+    #[serde(default)]
+    pub haptic_custom: String,
+    #[serde(default)]
+    pub haptic_balance: f32, // -1 left .. 1 right
+    #[serde(default = "default_rumble_scale")]
+    pub rumble_scale: f32,
+    #[serde(default)]
+    pub rumble_haptics: RumbleHaptics,
+    #[serde(default)]
+    pub audio_reactive: ReactiveConfig,
+    #[serde(default)]
+    pub lightbar_animation: String,
+    #[serde(default)]
+    pub lightbar_bindings: Vec<LightbarBinding>,
+    #[serde(default)]
+    pub lightbar_metric: Option<MetricBinding>,
+    #[serde(default)]
+    pub lightbar_visualizer: Option<VisualizerConfig>,
+    #[serde(default)]
+    pub player_led: Option<PlayerLedConfig>,
+    #[serde(default)]
+    pub audio_levels: AudioLevels,
+    #[serde(default)]
+    pub speaker_mode: SpeakerMode,
+    #[serde(default)]
+    pub audio_routing: Option<AudioRouting>,
 
     #[serde(default)]
     pub touchpad_show_overlay: bool,
@@ -228,6 +364,12 @@ impl Profile {
         }
     }
 
+    pub fn to_player_led_config(&self) -> PlayerLedConfig {
+        self.player_led
+            .clone()
+            .unwrap_or_else(|| PlayerLedConfig::from_index(self.player_leds))
+    }
+
     pub fn to_gyro_processor(&self) -> GyroProcessor {
         GyroProcessor {
             enabled: self.gyro.enabled,
@@ -275,7 +417,7 @@ impl ProfileManager {
         }
     }
 
-    fn sanitize_filename(name: &str) -> String {
+    pub fn sanitize_filename(name: &str) -> String {
         name.chars()
             .map(|c| {
                 if c.is_alphanumeric() || c == '-' || c == '_' {
@@ -406,12 +548,27 @@ impl Default for Profile {
 
             trigger_left_config: TriggerConfig::default(),
             trigger_right_config: TriggerConfig::default(),
+            trigger_left_program: None,
+            trigger_right_program: None,
 
             gyro: GyroProcessor::default(),
 
             haptic_pattern: HapticPattern::None,
             haptic_strength: 0,
             haptic_speed: 1.0,
+            haptic_custom: String::new(),
+            haptic_balance: 0.0,
+            rumble_scale: 1.0,
+            rumble_haptics: RumbleHaptics::default(),
+            audio_reactive: ReactiveConfig::default(),
+            lightbar_animation: String::new(),
+            lightbar_bindings: Vec::new(),
+            lightbar_metric: None,
+            lightbar_visualizer: None,
+            player_led: None,
+            audio_levels: AudioLevels::default(),
+            speaker_mode: SpeakerMode::Internal,
+            audio_routing: None,
 
             touchpad_show_overlay: true,
         }
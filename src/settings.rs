@@ -1,4 +1,4 @@
-use std::{fs, path::PathBuf};
+use std::{collections::BTreeMap, fs, path::PathBuf};
 
 use serde::{Deserialize, Serialize};
 
@@ -6,6 +6,124 @@ use serde::{Deserialize, Serialize};
 pub struct Settings {
     pub theme_id: String,
     pub profile: String,
+    #[serde(default)]
+    pub controllers: BTreeMap<String, ControllerBinding>,
+    #[serde(default)]
+    pub player_slots: PlayerSlotMode,
+    #[serde(default)]
+    pub dsx: DsxSettings,
+    #[serde(default)]
+    pub mic_mute: MicMuteSettings,
+    #[serde(default)]
+    pub virtual_pad: bool,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum PlayerSlotMode {
+    #[default]
+    Profile,
+    Order,
+    Sticky,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct DsxSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_dsx_port")]
+    pub port: u16,
+}
+
+fn default_dsx_port() -> u16 {
+    6969
+}
+
+impl Default for DsxSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_dsx_port(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum MuteMode {
+    #[default]
+    Off,
+    Toggle,
+    PushToTalk,
+    PushToMute,
+}
+
+impl MuteMode {
+    pub const ALL: [MuteMode; 4] = [
+        MuteMode::Off,
+        MuteMode::Toggle,
+        MuteMode::PushToTalk,
+        MuteMode::PushToMute,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MuteMode::Off => "Off",
+            MuteMode::Toggle => "Toggle",
+            MuteMode::PushToTalk => "Push to Talk",
+            MuteMode::PushToMute => "Push to Mute",
+        }
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct MicMuteSettings {
+    #[serde(default)]
+    pub mode: MuteMode,
+    #[serde(default)]
+    pub mixer: String,
+    #[serde(default)]
+    pub element: String,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ControllerBinding {
+    #[serde(default)]
+    pub nickname: String,
+    #[serde(default)]
+    pub profile: String,
+    #[serde(default)]
+    pub player: u8,
+}
+
+impl Settings {
+    pub fn profile_for(&self, serial: &str) -> &str {
+        match self.controllers.get(serial) {
+            Some(b) if !b.profile.is_empty() => &b.profile,
+            _ => &self.profile,
+        }
+    }
+
+    // True when the controller has no profile of its own and uses the global default.
+    pub fn follows_default_profile(&self, serial: &str) -> bool {
+        self.controllers
+            .get(serial)
+            .is_none_or(|b| b.profile.is_empty())
+    }
+
+    pub fn nickname_for(&self, serial: &str) -> &str {
+        self.controllers
+            .get(serial)
+            .map(|b| b.nickname.as_str())
+            .unwrap_or("")
+    }
+
+    pub fn binding_mut(&mut self, serial: &str) -> &mut ControllerBinding {
+        self.controllers.entry(serial.to_string()).or_default()
+    }
+
+    pub fn prune_bindings(&mut self) {
+        self.controllers
+            .retain(|_, b| !b.nickname.is_empty() || !b.profile.is_empty() || b.player != 0);
+    }
 }
 
 impl Default for Settings {
@@ -13,6 +131,11 @@ impl Default for Settings {
         Self {
             theme_id: "default".into(),
             profile: String::new(),
+            controllers: BTreeMap::new(),
+            player_slots: PlayerSlotMode::default(),
+            dsx: DsxSettings::default(),
+            mic_mute: MicMuteSettings::default(),
+            virtual_pad: false,
         }
     }
 }
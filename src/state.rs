@@ -1,6 +1,20 @@
-use crate::common::{HapticPattern, MicLedState, SpeakerMode, TouchpadMode};
-use crate::profiles::TriggerConfig;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::audio_reactive::ReactiveConfig;
+use crate::audio_visualizer::VisualizerConfig;
+use crate::common::{
+    AudioLevels, AudioRouting, HapticPattern, MicLedState, SpeakerMode, TouchpadMode,
+};
+use crate::haptic_clip::HapticClip;
+use crate::haptic_synth::{CustomPattern, RumbleHaptics};
+use crate::lightbar_anim::LightbarAnimation;
+use crate::lightbar_react::LightbarBinding;
+use crate::profiles::{TriggerConfig, TriggerProgram};
+use crate::system_metrics::MetricBinding;
 use crate::transform::GyroProcessor;
+use crate::trigger_presets::TriggerPreset;
 
 #[derive(Debug, Clone)]
 pub(crate) enum ProgressUpdate {
@@ -32,11 +46,21 @@ pub(crate) struct LightbarState {
     pub(crate) b: f32,
     pub(crate) brightness: f32,
     pub(crate) enabled: bool,
+    pub(crate) animation: Option<LightbarAnimation>,
+    pub(crate) animations: Vec<String>,
+    pub(crate) animation_key: usize,
+    pub(crate) animation_started: Instant,
+    pub(crate) bindings: Vec<LightbarBinding>,
+    pub(crate) metric: Option<MetricBinding>,
+    pub(crate) visualizer: VisualizerConfig,
+    pub(crate) capture_devices: Vec<String>,
 }
 
 pub(crate) struct MicrophoneState {
     pub(crate) enabled: bool,
     pub(crate) led_state: MicLedState,
+    pub(crate) mixers: Vec<(String, String)>,
+    pub(crate) mute_elements: Vec<String>,
 }
 
 pub(crate) struct StickSettings {
@@ -54,13 +78,16 @@ pub(crate) struct StickSettings {
 }
 
 pub(crate) struct AudioSettings {
-    pub(crate) volume: u8,
+    pub(crate) levels: AudioLevels,
+    pub(crate) levels_polled: Instant,
     pub(crate) speaker_mode: SpeakerMode,
+    pub(crate) routing: Option<AudioRouting>,
 }
 
 pub(crate) struct VibrationSettings {
-    pub(crate) rumble: u8,
     pub(crate) trigger: u8,
+    pub(crate) rumble_scale: f32,
+    pub(crate) rumble_haptics: RumbleHaptics,
 }
 pub(crate) struct GyroState {
     pub(crate) processor: GyroProcessor,
@@ -78,9 +105,26 @@ pub(crate) struct HapticState {
     pub(crate) pattern: HapticPattern,
     pub(crate) strength: u8,
     pub(crate) speed: f32,
+    pub(crate) balance: f32,
+    pub(crate) custom: Option<CustomPattern>,
+    pub(crate) custom_library: Vec<String>,
+    pub(crate) edit_channel: usize,
+    pub(crate) clip_path: Option<PathBuf>,
+    pub(crate) clip: Option<Arc<HapticClip>>,
+    pub(crate) clip_gain: f32,
+    pub(crate) clip_balance: f32,
+    pub(crate) clip_loop: bool,
+    pub(crate) reactive: ReactiveConfig,
+    pub(crate) capture_devices: Vec<String>,
 }
 
 pub(crate) struct TriggersState {
     pub(crate) left: TriggerConfig,
     pub(crate) right: TriggerConfig,
+    pub(crate) left_program: Option<TriggerProgram>,
+    pub(crate) right_program: Option<TriggerProgram>,
+    pub(crate) presets: Vec<TriggerPreset>,
+    pub(crate) selected_preset: Option<String>,
+    pub(crate) preset_name: String,
+    pub(crate) preview_until: Option<Instant>,
 }
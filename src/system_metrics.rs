@@ -0,0 +1,142 @@
+use std::{
+    fs,
+    process::{Command, Stdio},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::lightbar_anim::mix;
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub enum MetricSource {
+    CpuLoad,
+    CpuTemp,
+    Memory,
+    Command { command: String, interval_secs: u32 },
+}
+
+impl MetricSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MetricSource::CpuLoad => "CPU",
+            MetricSource::CpuTemp => "Temperature",
+            MetricSource::Memory => "Memory",
+            MetricSource::Command { .. } => "Command",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct MetricBinding {
+    pub source: MetricSource,
+    pub min: f32,
+    pub max: f32,
+    pub colors: [(u8, u8, u8); 3],
+}
+
+impl MetricBinding {
+    pub fn new(source: MetricSource) -> Self {
+        let (min, max) = match source {
+            MetricSource::CpuTemp => (40.0, 90.0),
+            MetricSource::Command { .. } => (0.0, 1.0),
+            _ => (0.0, 100.0),
+        };
+        Self {
+            source,
+            min,
+            max,
+            colors: [(0, 255, 0), (255, 200, 0), (255, 0, 0)],
+        }
+    }
+
+    pub fn color(&self, value: f32) -> (u8, u8, u8) {
+        let span = (self.max - self.min).max(f32::EPSILON);
+        let t = ((value - self.min) / span).clamp(0.0, 1.0);
+        let [low, mid, high] = self.colors;
+        if t < 0.5 {
+            mix(low, mid, t * 2.0)
+        } else {
+            mix(mid, high, t * 2.0 - 1.0)
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SystemSampler {
+    prev_cpu: Option<(u64, u64)>,
+}
+
+impl SystemSampler {
+    pub fn cpu_load(&mut self) -> Option<f32> {
+        let stat = fs::read_to_string("/proc/stat").ok()?;
+        let fields: Vec<u64> = stat
+            .lines()
+            .next()?
+            .split_whitespace()
+            .skip(1)
+            .filter_map(|v| v.parse().ok())
+            .collect();
+        let idle = fields.get(3)? + fields.get(4).unwrap_or(&0);
+        let total: u64 = fields.iter().take(8).sum();
+        let prev = self.prev_cpu.replace((idle, total));
+        let (prev_idle, prev_total) = prev?;
+        let dt = total.saturating_sub(prev_total);
+        if dt == 0 {
+            return None;
+        }
+        let busy = dt.saturating_sub(idle.saturating_sub(prev_idle));
+        Some(busy as f32 / dt as f32 * 100.0)
+    }
+}
+
+pub fn cpu_temp() -> Option<f32> {
+    fs::read_dir("/sys/class/thermal")
+        .ok()?
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().starts_with("thermal_zone"))
+        .filter_map(|e| fs::read_to_string(e.path().join("temp")).ok())
+        .filter_map(|t| t.trim().parse::<f32>().ok())
+        .map(|milli| milli / 1000.0)
+        .reduce(f32::max)
+}
+
+pub fn memory_used() -> Option<f32> {
+    let info = fs::read_to_string("/proc/meminfo").ok()?;
+    let field = |name: &str| {
+        info.lines()
+            .find(|l| l.starts_with(name))?
+            .split_whitespace()
+            .nth(1)?
+            .parse::<f32>()
+            .ok()
+    };
+    let total = field("MemTotal:")?;
+    let available = field("MemAvailable:")?;
+    (total > 0.0).then(|| (total - available) / total * 100.0)
+}
+
+pub fn run_check(command: &str) -> Option<f32> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait().ok()? {
+            return Some(if status.success() { 0.0 } else { 1.0 });
+        }
+        if started.elapsed() >= CHECK_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Some(1.0);
+        }
+        sleep(Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::TriggerMode,
+    profiles::{ProfileManager, TriggerConfig},
+};
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub enum PresetEffect {
+    Config(TriggerConfig),
+    Raw { effect_type: u8, params: [u8; 10] },
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct TriggerPreset {
+    pub name: String,
+    pub effect: PresetEffect,
+}
+
+impl TriggerPreset {
+    pub fn to_config(&self, deadband_from: &TriggerConfig) -> TriggerConfig {
+        let mut cfg = match &self.effect {
+            PresetEffect::Config(cfg) => cfg.clone(),
+            PresetEffect::Raw {
+                effect_type,
+                params,
+            } => TriggerConfig {
+                mode: TriggerMode::Raw,
+                raw: Some((*effect_type, *params)),
+                ..TriggerConfig::default()
+            },
+        };
+        cfg.deadband = deadband_from.deadband.clone();
+        cfg
+    }
+}
+
+fn preset(name: &str, cfg: TriggerConfig) -> TriggerPreset {
+    TriggerPreset {
+        name: name.to_string(),
+        effect: PresetEffect::Config(cfg),
+    }
+}
+
+pub fn builtin_presets() -> Vec<TriggerPreset> {
+    let base = TriggerConfig::default();
+    vec![
+        preset(
+            "Pistol",
+            TriggerConfig {
+                mode: TriggerMode::Weapon,
+                start: 4,
+                end: 6,
+                strength: 8,
+                ..base.clone()
+            },
+        ),
+        preset(
+            "Auto Rifle",
+            TriggerConfig {
+                mode: TriggerMode::Vibration,
+                zones: Some([0, 0, 0, 6, 6, 7, 7, 8, 8, 8]),
+                frequency: 12,
+                ..base.clone()
+            },
+        ),
+        preset(
+            "Bow",
+            TriggerConfig {
+                mode: TriggerMode::Bow,
+                start: 1,
+                end: 6,
+                strength: 3,
+                snap: Some(8),
+                ..base.clone()
+            },
+        ),
+        preset(
+            "Brake Pedal",
+            TriggerConfig {
+                mode: TriggerMode::Feedback,
+                zones: Some([1, 2, 3, 4, 5, 6, 7, 8, 8, 8]),
+                ..base.clone()
+            },
+        ),
+        preset(
+            "Accelerator",
+            TriggerConfig {
+                mode: TriggerMode::Feedback,
+                zones: Some([1, 1, 1, 2, 2, 2, 2, 3, 3, 3]),
+                ..base
+            },
+        ),
+    ]
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    builtin_presets().iter().any(|p| p.name == name)
+}
+
+pub struct TriggerPresetLibrary {
+    dir: PathBuf,
+}
+
+impl TriggerPresetLibrary {
+    pub fn new() -> Self {
+        let dir = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("ds4u")
+            .join("triggers");
+        if !dir.exists() {
+            let _ = fs::create_dir_all(&dir);
+        }
+        Self { dir }
+    }
+
+    fn path_for(&self, name: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}.json", ProfileManager::sanitize_filename(name)))
+    }
+
+    pub fn list(&self) -> Vec<TriggerPreset> {
+        let mut user = Vec::new();
+        if let Ok(entries) = fs::read_dir(&self.dir) {
+            for e in entries.flatten() {
+                let path = e.path();
+                if path.extension().and_then(|s| s.to_str()) == Some("json")
+                    && let Ok(json) = fs::read_to_string(&path)
+                    && let Ok(p) = serde_json::from_str::<TriggerPreset>(&json)
+                    && !is_builtin(&p.name)
+                {
+                    user.push(p);
+                }
+            }
+        }
+        user.sort_by_key(|p| p.name.to_lowercase());
+        let mut presets = builtin_presets();
+        presets.extend(user);
+        presets
+    }
+
+    pub fn save(&self, preset: &TriggerPreset) -> Result<()> {
+        let name = preset.name.trim();
+        if name.is_empty() {
+            bail!("Preset name cannot be empty");
+        }
+        if ProfileManager::sanitize_filename(name) != name {
+            bail!("Preset name '{}' contains invalid characters", name);
+        }
+        if is_builtin(name) {
+            bail!("'{}' is a built-in preset", name);
+        }
+        fs::write(self.path_for(name), serde_json::to_string_pretty(preset)?)?;
+        Ok(())
+    }
+
+    pub fn delete(&self, name: &str) -> Result<()> {
+        if is_builtin(name) {
+            bail!("Built-in presets cannot be deleted");
+        }
+        let path = self.path_for(name);
+        if !path.exists() {
+            bail!("Trigger preset '{}' not found", name);
+        }
+        fs::remove_file(path)?;
+        Ok(())
+    }
+
+    pub fn import(&self, path: &Path) -> Result<TriggerPreset> {
+        let preset: TriggerPreset = serde_json::from_str(&fs::read_to_string(path)?)?;
+        self.save(&preset)?;
+        Ok(preset)
+    }
+
+    pub fn export(preset: &TriggerPreset, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(preset)?)?;
+        Ok(())
+    }
+}
@@ -1,11 +1,13 @@
-use egui::Ui;
+use egui::{RichText, Ui};
 
 use crate::app::DS4UApp;
-use crate::common::{MicLedState, SpeakerMode};
+use crate::common::{AudioRouting, MicLedState, SpeakerMode};
+use crate::mixer::{capture_switches, controller_mixer, list_mixers};
+use crate::settings::MuteMode;
 use crate::ui::widgets::{
     ds_label, ds_pill_button, ds_row, ds_section, ds_slider_int, ds_toggle, ds_value_pct,
+    ds_value_text,
 };
-use crate::util::mlock;
 
 impl DS4UApp {
     pub(crate) fn render_audio_settings(&mut self, ui: &mut Ui) {
@@ -22,8 +24,13 @@ impl DS4UApp {
                         self.sync_profile();
                     }
                 });
+                let synced = self.ipc.is_some() && self.settings.mic_mute.mode != MuteMode::Off;
                 ds_row(ui, |ui| {
                     ds_label(ui, "Mic LED");
+                    if synced {
+                        ds_value_text(ui, "follows mute state");
+                        return;
+                    }
                     ui.horizontal_wrapped(|ui| {
                         for (state, label) in [
                             (MicLedState::Off, "Off"),
@@ -39,45 +46,193 @@ impl DS4UApp {
                     });
                 });
 
-                ds_section(ui, &c, "Speaker");
-                ds_row(ui, |ui| {
-                    ds_label(ui, "Output");
-                    let mut switch = |ui: &mut Ui, mode: SpeakerMode, label: &str, key: &str| {
-                        let active = self.audio.speaker_mode == mode;
-                        if ds_pill_button(ui, &c, label, active).clicked() && !active {
-                            self.audio.speaker_mode = mode;
-                            if let Some(ipc) = self.ipc.clone() {
-                                let _ = mlock(&ipc).set_speaker(key);
-                            } else if let Some(controller) = &self.controller
-                                && let Ok(mut ctrl) = controller.lock()
-                            {
-                                let _ = ctrl.set_speaker(key);
+                if self.ipc.is_some() {
+                    let mut mute = self.settings.mic_mute.clone();
+                    let mic = &mut self.microphone;
+                    ds_row(ui, |ui| {
+                        ds_label(ui, "Mute Button");
+                        ui.horizontal_wrapped(|ui| {
+                            for mode in MuteMode::ALL {
+                                let active = mute.mode == mode;
+                                if ds_pill_button(ui, &c, mode.label(), active).clicked() && !active
+                                {
+                                    mute.mode = mode;
+                                }
                             }
+                        });
+                    });
+                    if mute.mode != MuteMode::Off {
+                        ds_row(ui, |ui| {
+                            ds_label(ui, "Mixer");
+                            let selected = match mute.mixer.as_str() {
+                                "" => "Controller".to_string(),
+                                device => mic
+                                    .mixers
+                                    .iter()
+                                    .find(|(d, _)| d == device)
+                                    .map_or(device.to_string(), |(_, name)| name.clone()),
+                            };
+                            egui::ComboBox::from_id_salt("mute_mixer")
+                                .selected_text(RichText::new(selected).size(16.0))
+                                .width(280.0)
+                                .show_ui(ui, |ui| {
+                                    if mic.mixers.is_empty() {
+                                        mic.mixers = list_mixers();
+                                    }
+                                    if ui
+                                        .selectable_label(mute.mixer.is_empty(), "Controller")
+                                        .clicked()
+                                    {
+                                        mute.mixer.clear();
+                                    }
+                                    for (device, name) in &mic.mixers {
+                                        let label = format!("{} ({})", name, device);
+                                        if ui
+                                            .selectable_label(mute.mixer == *device, label)
+                                            .clicked()
+                                        {
+                                            mute.mixer = device.clone();
+                                        }
+                                    }
+                                });
+                            ui.add_space(8.0);
+                            if ds_pill_button(ui, &c, "Refresh", false).clicked() {
+                                mic.mixers = list_mixers();
+                                mic.mute_elements.clear();
+                            }
+                        });
+                        ds_row(ui, |ui| {
+                            ds_label(ui, "Control");
+                            let selected = match mute.element.as_str() {
+                                "" => "Auto",
+                                element => element,
+                            };
+                            egui::ComboBox::from_id_salt("mute_element")
+                                .selected_text(RichText::new(selected).size(16.0))
+                                .width(280.0)
+                                .show_ui(ui, |ui| {
+                                    if mic.mute_elements.is_empty() {
+                                        let device = match mute.mixer.as_str() {
+                                            "" => controller_mixer(),
+                                            device => Some(device.to_string()),
+                                        };
+                                        mic.mute_elements = device
+                                            .map(|d| capture_switches(&d))
+                                            .unwrap_or_default();
+                                    }
+                                    if ui
+                                        .selectable_label(mute.element.is_empty(), "Auto")
+                                        .clicked()
+                                    {
+                                        mute.element.clear();
+                                    }
+                                    for element in &mic.mute_elements {
+                                        if ui
+                                            .selectable_label(mute.element == *element, element)
+                                            .clicked()
+                                        {
+                                            mute.element = element.clone();
+                                        }
+                                    }
+                                });
+                        });
+                    }
+                    if mute != self.settings.mic_mute {
+                        if mute.mixer != self.settings.mic_mute.mixer {
+                            mute.element.clear();
+                            self.microphone.mute_elements.clear();
                         }
-                    };
+                        self.settings.mic_mute = mute;
+                        self.settings_manager.save(&self.settings);
+                    }
+                }
+
+                ds_section(ui, &c, "Speaker");
+                if let Some(state) = &self.input.controller_state {
+                    ds_row(ui, |ui| {
+                        ds_label(ui, "Headphones");
+                        ds_value_text(
+                            ui,
+                            if state.jack.headphones {
+                                "plugged in"
+                            } else {
+                                "not connected"
+                            },
+                        );
+                    });
+                }
+                let mut routing = self.audio.routing;
+                let mut mode = self.audio.speaker_mode;
+                if self.ipc.is_some() {
+                    ds_row(ui, |ui| {
+                        ds_label(ui, "Auto Routing");
+                        let mut auto = routing.is_some();
+                        if ds_toggle(ui, &c, &mut auto).changed() {
+                            routing = auto.then(AudioRouting::default);
+                        }
+                    });
+                }
+                let pills = |ui: &mut Ui, label: &str, current: &mut SpeakerMode| {
+                    ds_row(ui, |ui| {
+                        ds_label(ui, label);
+                        ui.horizontal_wrapped(|ui| {
+                            for m in SpeakerMode::ALL {
+                                if ds_pill_button(ui, &c, m.label(), *current == m).clicked() {
+                                    *current = m;
+                                }
+                            }
+                        });
+                    });
+                };
+                match &mut routing {
+                    Some(r) => {
+                        pills(ui, "Unplugged", &mut r.unplugged);
+                        pills(ui, "Plugged In", &mut r.plugged);
+                    }
+                    None => pills(ui, "Output", &mut mode),
+                }
+                ds_row(ui, |ui| {
+                    ds_label(ui, "Test");
                     ui.horizontal_wrapped(|ui| {
-                        switch(ui, SpeakerMode::Internal, "Internal", "internal");
-                        switch(ui, SpeakerMode::Headphone, "Headphone", "headphone");
-                        switch(ui, SpeakerMode::Both, "Both", "both");
+                        if ds_pill_button(ui, &c, "Play Tone", false).clicked() {
+                            self.play_speaker_sound(None);
+                        }
+                        if ds_pill_button(ui, &c, "Play File…", false).clicked() {
+                            self.open_speaker_sound();
+                        }
+                        if ds_pill_button(ui, &c, "Stop", false).clicked() {
+                            self.stop_speaker_sound();
+                        }
                     });
                 });
+                if routing != self.audio.routing || mode != self.audio.speaker_mode {
+                    self.audio.routing = routing;
+                    self.audio.speaker_mode = mode;
+                    self.apply_speaker();
+                    self.sync_profile();
+                }
 
                 ds_section(ui, &c, "Volume");
-                ds_row(ui, |ui| {
-                    ds_label(ui, "Level");
-                    let mut vol = self.audio.volume as i32;
-                    if ds_slider_int(ui, &c, &mut vol, 0..=255).changed() {
-                        self.audio.volume = vol as u8;
-                        if let Some(ipc) = self.ipc.clone() {
-                            let _ = mlock(&ipc).set_volume(vol as u8);
-                        } else if let Some(controller) = &self.controller
-                            && let Ok(mut ctrl) = controller.lock()
-                        {
-                            let _ = ctrl.set_volume(vol as u8);
+                let mut changed = false;
+                for (label, level) in [
+                    ("Headphone", &mut self.audio.levels.headphone),
+                    ("Speaker", &mut self.audio.levels.speaker),
+                    ("Microphone", &mut self.audio.levels.mic),
+                ] {
+                    ds_row(ui, |ui| {
+                        ds_label(ui, label);
+                        let mut vol = *level as i32;
+                        if ds_slider_int(ui, &c, &mut vol, 0..=255).changed() {
+                            *level = vol as u8;
+                            changed = true;
                         }
-                    }
-                    ds_value_pct(ui, (self.audio.volume as f32 / 255.0) * 100.0);
-                });
+                        ds_value_pct(ui, (*level as f32 / 255.0) * 100.0);
+                    });
+                }
+                if changed {
+                    self.apply_audio_levels();
+                    self.sync_profile();
+                }
             });
     }
 }
@@ -1,11 +1,14 @@
 use egui::{Color32, CornerRadius, Pos2, RichText, Sense, Stroke, StrokeKind, Ui, pos2, vec2};
 
 use crate::app::DS4UApp;
+use crate::audio_reactive::{ChannelMapping, list_capture_devices};
 use crate::common::HapticPattern;
+use crate::haptic_synth::{Keyframe, MAX_CARRIER_HZ, Track, Waveform, levels};
+use crate::state::HapticState;
 use crate::theme::ThemeColors;
 use crate::ui::widgets::{ds_pill_button, ds_slider};
 
-use super::widgets::{ds_label, ds_row, ds_section, ds_slider_int, ds_value_pct};
+use super::widgets::{ds_label, ds_row, ds_section, ds_slider_int, ds_value_pct, ds_value_text};
 
 const PATTERNS: &[(HapticPattern, &str)] = &[
     (HapticPattern::None, "Off"),
@@ -13,37 +16,141 @@ const PATTERNS: &[(HapticPattern, &str)] = &[
     (HapticPattern::Pulse, "Pulse"),
     (HapticPattern::Ramp, "Ramp"),
     (HapticPattern::Wave, "Wave"),
+    (HapticPattern::Custom, "Custom"),
 ];
 
-fn haptic_intensity(pattern: HapticPattern, strength: u8, speed: f32, elapsed: f32) -> f32 {
-    let s = (strength.min(7) as f32 / 7.0).clamp(0.0, 1.0);
-    match pattern {
-        HapticPattern::None => 0.0,
-        HapticPattern::Constant => s,
-        HapticPattern::Pulse => {
-            let phase = (elapsed * speed).rem_euclid(1.0);
-            if phase < 0.5 { s } else { 0.0 }
-        }
-        HapticPattern::Ramp => {
-            let phase = (elapsed * speed).rem_euclid(1.0);
-            s * phase
-        }
-        HapticPattern::Wave => {
-            let v = (elapsed * speed * std::f32::consts::TAU).sin() * 0.5 + 0.5;
-            s * v
-        }
+enum ClipAction {
+    Open,
+    Play,
+    Stop,
+}
+
+enum PatternAction {
+    New,
+    Save,
+    Delete,
+    Select(String),
+}
+
+fn balance_text(balance: f32) -> String {
+    let pct = (balance.abs() * 100.0).round();
+    if pct == 0.0 {
+        "C".to_string()
+    } else if balance < 0.0 {
+        format!("L{pct:.0}")
+    } else {
+        format!("R{pct:.0}")
     }
 }
 
+fn keyframe_editor(
+    ui: &mut Ui,
+    c: &ThemeColors,
+    id: egui::Id,
+    label: &str,
+    track: &mut Track,
+    duration: f32,
+    max: f32,
+) -> bool {
+    let (rect, resp) = ui.allocate_exact_size(vec2(520.0, 120.0), Sense::click_and_drag());
+    let painter = ui.painter_at(rect.expand(2.0));
+    let rounding = CornerRadius::same(6);
+    painter.rect_filled(rect, rounding, c.extreme_bg());
+    painter.rect_stroke(
+        rect,
+        rounding,
+        Stroke::new(1.0, c.widget_inactive()),
+        StrokeKind::Inside,
+    );
+
+    let plot = rect.shrink(10.0);
+    let duration = duration.max(0.01);
+    let to_screen = |k: &Keyframe| {
+        pos2(
+            plot.left() + (k.t / duration).clamp(0.0, 1.0) * plot.width(),
+            plot.bottom() - (k.value / max).clamp(0.0, 1.0) * plot.height(),
+        )
+    };
+    let from_screen = |p: Pos2| Keyframe {
+        t: ((p.x - plot.left()) / plot.width()).clamp(0.0, 1.0) * duration,
+        value: ((plot.bottom() - p.y) / plot.height()).clamp(0.0, 1.0) * max,
+    };
+    let positions: Vec<Pos2> = track.keys.iter().map(to_screen).collect();
+    let hit = |p: Pos2| positions.iter().position(|k| k.distance(p) < 9.0);
+
+    let mut changed = false;
+    let mut dragging = ui.data(|d| d.get_temp::<Option<usize>>(id)).flatten();
+    if resp.drag_started() {
+        dragging = resp.interact_pointer_pos().and_then(hit);
+    }
+    if resp.dragged()
+        && let (Some(i), Some(p)) = (dragging, resp.interact_pointer_pos())
+        && i < track.keys.len()
+    {
+        let mut k = from_screen(p);
+        let lo = if i > 0 { track.keys[i - 1].t } else { 0.0 };
+        let hi = track.keys.get(i + 1).map_or(duration, |n| n.t);
+        k.t = k.t.clamp(lo, hi);
+        track.keys[i] = k;
+        changed = true;
+    }
+    if resp.drag_stopped() {
+        dragging = None;
+    }
+    ui.data_mut(|d| d.insert_temp(id, dragging));
+
+    if resp.clicked()
+        && let Some(p) = resp.interact_pointer_pos()
+        && hit(p).is_none()
+    {
+        track.insert(from_screen(p));
+        changed = true;
+    }
+    if resp.secondary_clicked()
+        && let Some(i) = resp.interact_pointer_pos().and_then(hit)
+        && track.keys.len() > 1
+    {
+        track.keys.remove(i);
+        changed = true;
+    }
+
+    for frac in [0.25, 0.5, 0.75] {
+        let y = plot.bottom() - frac * plot.height();
+        painter.line_segment(
+            [pos2(plot.left(), y), pos2(plot.right(), y)],
+            Stroke::new(0.4, c.widget_inactive()),
+        );
+    }
+    let steps = 128;
+    let points: Vec<Pos2> = (0..=steps)
+        .map(|i| {
+            let t = i as f32 / steps as f32 * duration;
+            to_screen(&Keyframe {
+                t,
+                value: track.sample(t),
+            })
+        })
+        .collect();
+    painter.add(egui::Shape::line(points, Stroke::new(2.0, c.accent())));
+    for k in &track.keys {
+        let p = to_screen(k);
+        painter.circle_filled(p, 4.5, c.accent());
+        painter.circle_stroke(p, 4.5, Stroke::new(1.0, Color32::WHITE));
+    }
+    painter.text(
+        rect.min + vec2(10.0, 8.0),
+        egui::Align2::LEFT_TOP,
+        label,
+        egui::FontId::proportional(12.0),
+        c.text_dim(),
+    );
+
+    changed
+}
+
 impl DS4UApp {
-    fn render_haptic_visual(
-        ui: &mut Ui,
-        pattern: HapticPattern,
-        strength: u8,
-        speed: f32,
-        time: f32,
-        c: &ThemeColors,
-    ) {
+    fn render_haptic_visual(ui: &mut Ui, hs: &HapticState, time: f32, c: &ThemeColors) {
+        let custom = hs.custom.as_ref();
         let w = 520.0;
         let h = 110.0;
         let (rect, _) = ui.allocate_exact_size(vec2(w, h), Sense::hover());
@@ -76,25 +183,174 @@ impl DS4UApp {
 
         let window: f32 = 3.0;
         let steps = 256usize;
-        let mut points: Vec<Pos2> = Vec::with_capacity(steps + 1);
+        let stereo = hs.balance != 0.0
+            || (hs.pattern == HapticPattern::Custom
+                && custom.is_some_and(|p| p.left.envelope != p.right.envelope));
+        let mut lines: [Vec<Pos2>; 2] = Default::default();
         for i in 0..=steps {
             let t = i as f32 / steps as f32;
             let elapsed = (time - (1.0 - t) * window).max(0.0);
-            let val = haptic_intensity(pattern, strength, speed, elapsed);
+            let val = levels(
+                hs.pattern,
+                hs.strength,
+                hs.speed,
+                hs.balance,
+                custom,
+                elapsed,
+            );
             let x = left + t * plot_w;
-            let y = bottom - val * plot_h;
-            points.push(pos2(x, y));
+            for (line, v) in lines.iter_mut().zip(val) {
+                line.push(pos2(x, bottom - v * plot_h));
+            }
         }
-        painter.add(egui::Shape::line(points, Stroke::new(2.0, c.accent())));
+        let [l, r] = lines;
+        if stereo {
+            painter.add(egui::Shape::line(r, Stroke::new(2.0, c.warning())));
+        }
+        painter.add(egui::Shape::line(l, Stroke::new(2.0, c.accent())));
 
         painter.line_segment(
             [pos2(right, top), pos2(right, bottom)],
             Stroke::new(0.6, c.text_dim()),
         );
-        let current = haptic_intensity(pattern, strength, speed, time);
-        let dot = pos2(right, bottom - current * plot_h);
-        painter.circle_filled(dot, 5.0, c.accent());
-        painter.circle_stroke(dot, 5.0, Stroke::new(1.0, Color32::WHITE));
+        let current = levels(hs.pattern, hs.strength, hs.speed, hs.balance, custom, time);
+        let colors = [c.accent(), c.warning()];
+        for ch in (0..if stereo { 2 } else { 1 }).rev() {
+            let dot = pos2(right, bottom - current[ch] * plot_h);
+            painter.circle_filled(dot, 5.0, colors[ch]);
+            painter.circle_stroke(dot, 5.0, Stroke::new(1.0, Color32::WHITE));
+        }
+    }
+
+    fn render_custom_pattern_editor(
+        &mut self,
+        ui: &mut Ui,
+        c: &ThemeColors,
+        action: &mut Option<PatternAction>,
+        changed: &mut bool,
+    ) {
+        ds_section(ui, c, "Custom Pattern");
+        let current = self
+            .haptic_state
+            .custom
+            .as_ref()
+            .map(|p| p.name.clone())
+            .unwrap_or_else(|| "None".to_string());
+        ds_row(ui, |ui| {
+            ds_label(ui, "Pattern");
+            let library = &mut self.haptic_state.custom_library;
+            let library_ref = &self.pattern_library;
+            egui::ComboBox::from_id_salt("custom_haptic_pattern")
+                .selected_text(RichText::new(&current).size(16.0))
+                .width(200.0)
+                .show_ui(ui, |ui| {
+                    if library.is_empty() {
+                        *library = library_ref.list();
+                    }
+                    for name in library.iter() {
+                        if ui.selectable_label(*name == current, name).clicked() {
+                            *action = Some(PatternAction::Select(name.clone()));
+                        }
+                    }
+                });
+            ui.add_space(8.0);
+            if ds_pill_button(ui, c, "New", false).clicked() {
+                *action = Some(PatternAction::New);
+            }
+            ui.add_space(8.0);
+            if ds_pill_button(ui, c, "Save", false).clicked() {
+                *action = Some(PatternAction::Save);
+            }
+            ui.add_space(8.0);
+            if ds_pill_button(ui, c, "Delete", false).clicked() {
+                *action = Some(PatternAction::Delete);
+            }
+        });
+
+        let edit_channel = &mut self.haptic_state.edit_channel;
+        let Some(p) = self.haptic_state.custom.as_mut() else {
+            return;
+        };
+
+        ds_row(ui, |ui| {
+            ds_label(ui, "Name");
+            ui.add(egui::TextEdit::singleline(&mut p.name).desired_width(240.0));
+        });
+        ds_row(ui, |ui| {
+            ds_label(ui, "Duration");
+            if ds_slider(ui, c, &mut p.duration, 0.1..=10.0).changed() {
+                *changed = true;
+            }
+            ui.label(RichText::new(format!("{:.1} s", p.duration)).size(16.0));
+        });
+        ds_row(ui, |ui| {
+            ds_label(ui, "Loop");
+            let label = if p.looping { "On" } else { "Off" };
+            if ds_pill_button(ui, c, label, p.looping).clicked() {
+                p.looping = !p.looping;
+                *changed = true;
+            }
+        });
+        ds_row(ui, |ui| {
+            ds_label(ui, "Channel");
+            for (i, label) in ["Left", "Right"].into_iter().enumerate() {
+                if ds_pill_button(ui, c, label, *edit_channel == i).clicked() {
+                    *edit_channel = i;
+                }
+                ui.add_space(8.0);
+            }
+            ui.add_space(8.0);
+            if ds_pill_button(ui, c, "Copy to other", false).clicked() {
+                if *edit_channel == 0 {
+                    p.right = p.left.clone();
+                } else {
+                    p.left = p.right.clone();
+                }
+                *changed = true;
+            }
+        });
+
+        let duration = p.duration;
+        let chan = if *edit_channel == 0 {
+            &mut p.left
+        } else {
+            &mut p.right
+        };
+        ds_row(ui, |ui| {
+            ds_label(ui, "Waveform");
+            ui.horizontal_wrapped(|ui| {
+                for w in Waveform::ALL {
+                    let active = chan.waveform == w;
+                    if ds_pill_button(ui, c, w.label(), active).clicked() && !active {
+                        chan.waveform = w;
+                        *changed = true;
+                    }
+                }
+            });
+        });
+
+        let id = ui.id().with("custom_haptic").with(*edit_channel);
+        for (label, track, max, key) in [
+            ("Amplitude", &mut chan.envelope, 1.0, "env"),
+            ("Carrier (Hz)", &mut chan.carrier, MAX_CARRIER_HZ, "carrier"),
+        ] {
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                ui.add_space(crate::ui::widgets::ROW_PAD_X);
+                if keyframe_editor(ui, c, id.with(key), label, track, duration, max) {
+                    *changed = true;
+                }
+            });
+        }
+        ui.add_space(4.0);
+        ui.horizontal(|ui| {
+            ui.add_space(crate::ui::widgets::ROW_PAD_X);
+            ui.label(
+                RichText::new("Click to add a key, drag to move, right-click to remove")
+                    .size(13.0)
+                    .color(c.text_dim()),
+            );
+        });
     }
 
     pub(crate) fn render_haptics_settings(&mut self, ui: &mut Ui) {
@@ -106,6 +362,12 @@ impl DS4UApp {
         let mut params_changed = false;
         let mut raw_action: Option<bool> = None;
         let mut live_update = false;
+        let mut clip_action: Option<ClipAction> = None;
+        let mut reactive_changed = false;
+        let mut rumble_scale_changed = false;
+        let mut rumble_haptics_changed = false;
+        let mut pattern_action: Option<PatternAction> = None;
+        let mut custom_changed = false;
 
         egui::ScrollArea::vertical()
             .auto_shrink([false, false])
@@ -157,19 +419,32 @@ impl DS4UApp {
                         });
                     }
 
+                    ds_row(ui, |ui| {
+                        ds_label(ui, "Balance");
+                        if ds_slider(ui, &c, &mut self.haptic_state.balance, -1.0..=1.0).changed() {
+                            if in_daemon {
+                                params_changed = true;
+                            }
+                            live_update = streaming;
+                        }
+                        ds_value_text(ui, &balance_text(self.haptic_state.balance));
+                    });
+
+                    if self.haptic_state.pattern == HapticPattern::Custom {
+                        self.render_custom_pattern_editor(
+                            ui,
+                            &c,
+                            &mut pattern_action,
+                            &mut custom_changed,
+                        );
+                    }
+
                     ds_section(ui, &c, "Live preview");
                     ui.add_space(8.0);
                     ui.horizontal(|ui| {
                         ui.add_space(crate::ui::widgets::ROW_PAD_X);
                         let time = ui.input(|i| i.time) as f32;
-                        Self::render_haptic_visual(
-                            ui,
-                            self.haptic_state.pattern,
-                            self.haptic_state.strength,
-                            self.haptic_state.speed,
-                            time,
-                            &c,
-                        );
+                        Self::render_haptic_visual(ui, &self.haptic_state, time, &c);
                     });
                 }
 
@@ -194,17 +469,161 @@ impl DS4UApp {
                     });
                 }
 
-                ds_section(ui, &c, "Vibration Attenuation");
-                let mut rum = self.vibration.rumble as i32;
-                let mut trg = self.vibration.trigger as i32;
+                if can_stream {
+                    ds_section(ui, &c, "Audio Clip");
+                    ds_row(ui, |ui| {
+                        ds_label(ui, "File");
+                        if ds_pill_button(ui, &c, "Open", false).clicked() {
+                            clip_action = Some(ClipAction::Open);
+                        }
+                        ui.add_space(8.0);
+                        let name = match &self.haptic_state.clip {
+                            Some(clip) => format!("{}  ({:.1}s)", clip.name, clip.duration()),
+                            None => "No clip loaded".to_string(),
+                        };
+                        ui.label(RichText::new(name).size(16.0).color(c.text_dim()));
+                    });
+                    if self.haptic_state.clip.is_some() {
+                        ds_row(ui, |ui| {
+                            ds_label(ui, "Gain");
+                            ds_slider(ui, &c, &mut self.haptic_state.clip_gain, 0.0..=4.0);
+                            ds_value_pct(ui, self.haptic_state.clip_gain * 100.0);
+                        });
+                        ds_row(ui, |ui| {
+                            ds_label(ui, "Balance");
+                            ds_slider(ui, &c, &mut self.haptic_state.clip_balance, -1.0..=1.0);
+                            ds_value_text(ui, &balance_text(self.haptic_state.clip_balance));
+                        });
+                        ds_row(ui, |ui| {
+                            ds_label(ui, "Loop");
+                            let looping = self.haptic_state.clip_loop;
+                            let label = if looping { "On" } else { "Off" };
+                            if ds_pill_button(ui, &c, label, looping).clicked() {
+                                self.haptic_state.clip_loop = !looping;
+                            }
+                        });
+                        ds_row(ui, |ui| {
+                            ds_label(ui, "Playback");
+                            if ds_pill_button(ui, &c, "Play", false).clicked() {
+                                clip_action = Some(ClipAction::Play);
+                            }
+                            ui.add_space(8.0);
+                            if ds_pill_button(ui, &c, "Stop", false).clicked() {
+                                clip_action = Some(ClipAction::Stop);
+                            }
+                        });
+                    }
+                }
+
+                if can_stream {
+                    ds_section(ui, &c, "Audio Reactive");
+                    let r = &mut self.haptic_state.reactive;
+                    ds_row(ui, |ui| {
+                        ds_label(ui, "Enabled");
+                        let label = if r.enabled { "On" } else { "Off" };
+                        if ds_pill_button(ui, &c, label, r.enabled).clicked() {
+                            r.enabled = !r.enabled;
+                            reactive_changed = true;
+                        }
+                    });
+                    ds_row(ui, |ui| {
+                        ds_label(ui, "Source");
+                        let devices = &mut self.haptic_state.capture_devices;
+                        egui::ComboBox::from_id_salt("reactive_source")
+                            .selected_text(RichText::new(&r.device).size(16.0))
+                            .width(280.0)
+                            .show_ui(ui, |ui| {
+                                if devices.is_empty() {
+                                    *devices = list_capture_devices();
+                                }
+                                for name in devices.iter() {
+                                    if ui.selectable_label(r.device == *name, name).clicked()
+                                        && r.device != *name
+                                    {
+                                        r.device = name.clone();
+                                        reactive_changed = true;
+                                    }
+                                }
+                            });
+                        ui.add_space(8.0);
+                        if ds_pill_button(ui, &c, "Refresh", false).clicked() {
+                            *devices = list_capture_devices();
+                        }
+                    });
+                    ds_row(ui, |ui| {
+                        ds_label(ui, "Crossover");
+                        if ds_slider(ui, &c, &mut r.crossover_hz, 40.0..=400.0).changed() {
+                            reactive_changed = true;
+                        }
+                        ui.label(RichText::new(format!("{:.0} Hz", r.crossover_hz)).size(16.0));
+                    });
+                    ds_row(ui, |ui| {
+                        ds_label(ui, "Gain");
+                        if ds_slider(ui, &c, &mut r.gain, 0.0..=8.0).changed() {
+                            reactive_changed = true;
+                        }
+                        ds_value_pct(ui, r.gain * 100.0);
+                    });
+                    ds_row(ui, |ui| {
+                        ds_label(ui, "Gate");
+                        if ds_slider(ui, &c, &mut r.gate, 0.0..=0.25).changed() {
+                            reactive_changed = true;
+                        }
+                        ds_value_pct(ui, r.gate * 100.0);
+                    });
+                    ds_row(ui, |ui| {
+                        ds_label(ui, "Channels");
+                        ui.horizontal_wrapped(|ui| {
+                            for m in ChannelMapping::ALL {
+                                let active = r.mapping == m;
+                                if ds_pill_button(ui, &c, m.label(), active).clicked() && !active {
+                                    r.mapping = m;
+                                    reactive_changed = true;
+                                }
+                            }
+                        });
+                    });
+                }
+
+                ds_section(ui, &c, "Game Rumble");
                 ds_row(ui, |ui| {
-                    ds_label(ui, "Rumble");
-                    if ds_slider_int(ui, &c, &mut rum, 0..=7).changed() {
-                        self.vibration.rumble = rum as u8;
-                        changed = true;
+                    ds_label(ui, "Strength");
+                    if ds_slider(ui, &c, &mut self.vibration.rumble_scale, 0.0..=2.0).changed() {
+                        rumble_scale_changed = true;
                     }
-                    ds_value_pct(ui, ((7 - rum) as f32 / 7.0) * 100.0);
+                    ds_value_pct(ui, self.vibration.rumble_scale * 100.0);
                 });
+                let rh = &mut self.vibration.rumble_haptics;
+                ds_row(ui, |ui| {
+                    ds_label(ui, "Output");
+                    for (label, haptics) in [("Motors", false), ("Voice Coils", true)] {
+                        let active = rh.enabled == haptics;
+                        if ds_pill_button(ui, &c, label, active).clicked() && !active {
+                            rh.enabled = haptics;
+                            rumble_haptics_changed = true;
+                        }
+                        ui.add_space(6.0);
+                    }
+                });
+                if rh.enabled {
+                    ds_row(ui, |ui| {
+                        ds_label(ui, "Heavy Motor");
+                        if ds_slider(ui, &c, &mut rh.low_hz, 20.0..=200.0).changed() {
+                            rumble_haptics_changed = true;
+                        }
+                        ds_value_text(ui, &format!("{:.0} Hz", rh.low_hz));
+                    });
+                    ds_row(ui, |ui| {
+                        ds_label(ui, "Light Motor");
+                        if ds_slider(ui, &c, &mut rh.high_hz, 80.0..=MAX_CARRIER_HZ).changed() {
+                            rumble_haptics_changed = true;
+                        }
+                        ds_value_text(ui, &format!("{:.0} Hz", rh.high_hz));
+                    });
+                }
+
+                ds_section(ui, &c, "Vibration Attenuation");
+                let mut trg = self.vibration.trigger as i32;
                 ds_row(ui, |ui| {
                     ds_label(ui, "Trigger");
                     if ds_slider_int(ui, &c, &mut trg, 0..=7).changed() {
@@ -231,6 +650,17 @@ impl DS4UApp {
                 });
             });
 
+        match pattern_action {
+            Some(PatternAction::New) => self.new_custom_pattern(),
+            Some(PatternAction::Save) => self.save_custom_pattern(),
+            Some(PatternAction::Delete) => self.delete_custom_pattern(),
+            Some(PatternAction::Select(name)) => self.select_custom_pattern(&name),
+            None => {}
+        }
+        if custom_changed {
+            self.apply_haptic_pattern();
+            self.update_raw_haptics();
+        }
         if pat_changed || params_changed {
             self.apply_haptic_pattern();
             self.sync_profile();
@@ -238,6 +668,24 @@ impl DS4UApp {
         if changed {
             self.apply_vibration();
         }
+        if rumble_scale_changed {
+            self.apply_rumble_scale();
+            self.sync_profile();
+        }
+        if rumble_haptics_changed {
+            self.apply_rumble_haptics();
+            self.sync_profile();
+        }
+        if reactive_changed {
+            self.apply_audio_reactive();
+            self.sync_profile();
+        }
+        match clip_action {
+            Some(ClipAction::Open) => self.open_haptic_clip(),
+            Some(ClipAction::Play) => self.play_haptic_clip(),
+            Some(ClipAction::Stop) => self.stop_haptic_clip(),
+            None => {}
+        }
         match raw_action {
             Some(true) => self.start_raw_haptics(),
             Some(false) => self.stop_raw_haptics(),
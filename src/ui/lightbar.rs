@@ -1,13 +1,330 @@
-use egui::{Color32, Ui};
+use std::time::Instant;
 
+use egui::{Color32, CornerRadius, RichText, Sense, Stroke, StrokeKind, Ui, pos2, vec2};
+
+use crate::audio_reactive::list_capture_devices;
+use crate::audio_visualizer::Palette;
+use crate::dualsense::PLAYER_LED_PATTERNS;
+use crate::inputs::Button;
+use crate::lightbar_anim::{ColorKey, Easing, LightbarAnimation};
+use crate::lightbar_react::{Analog, LightbarBinding, Stick, TintWhen};
+use crate::player_leds::{PLAYER_LED_COUNT, PlayerLedAnimation, PlayerLedBrightness};
+use crate::system_metrics::{MetricBinding, MetricSource};
+use crate::theme::ThemeColors;
 use crate::ui::widgets::{
-    ds_label, ds_pill_button, ds_row, ds_section, ds_slider, ds_swatch, ds_value_pct, ds_value_text,
+    ROW_PAD_X, ds_label, ds_pill_button, ds_row, ds_section, ds_slider, ds_slider_int, ds_swatch,
+    ds_toggle, ds_value_pct, ds_value_text,
 };
 use crate::{app::DS4UApp, common::LightbarEffect};
 
+const BINDING_BUTTONS: &[(Button, &str)] = &[
+    (Button::L1, "L1"),
+    (Button::R1, "R1"),
+    (Button::L2, "L2"),
+    (Button::R2, "R2"),
+    (Button::L3, "L3"),
+    (Button::R3, "R3"),
+    (Button::Cross, "Cross"),
+    (Button::Circle, "Circle"),
+    (Button::Square, "Square"),
+    (Button::Triangle, "Triangle"),
+];
+
+fn new_binding(kind: &str) -> LightbarBinding {
+    match kind {
+        "Brightness" => LightbarBinding::Brightness {
+            source: Analog::R2,
+            min: 40,
+        },
+        "Hue" => LightbarBinding::Hue { stick: Stick::Left },
+        "Flash" => LightbarBinding::Flash {
+            button: Button::R1,
+            color: (255, 255, 255),
+        },
+        _ => LightbarBinding::Tint {
+            when: TintWhen::Gyro,
+            color: (255, 0, 0),
+            amount: 0.6,
+        },
+    }
+}
+
+fn color_row(ui: &mut Ui, color: &mut (u8, u8, u8)) -> bool {
+    ds_row(ui, |ui| {
+        ds_label(ui, "Color");
+        let mut rgb = [color.0, color.1, color.2];
+        let changed = ui.color_edit_button_srgb(&mut rgb).changed();
+        *color = (rgb[0], rgb[1], rgb[2]);
+        changed
+    })
+}
+
+fn button_row(ui: &mut Ui, c: &ThemeColors, button: &mut Button) -> bool {
+    let mut changed = false;
+    ds_row(ui, |ui| {
+        ds_label(ui, "Button");
+        ui.horizontal_wrapped(|ui| {
+            for (b, name) in BINDING_BUTTONS {
+                let active = button == b;
+                if ds_pill_button(ui, c, name, active).clicked() && !active {
+                    *button = b.clone();
+                    changed = true;
+                }
+            }
+        });
+    });
+    changed
+}
+
+fn binding_editor(ui: &mut Ui, c: &ThemeColors, binding: &mut LightbarBinding) -> bool {
+    let mut changed = false;
+    match binding {
+        LightbarBinding::Brightness { source, min } => {
+            ds_row(ui, |ui| {
+                ds_label(ui, "Source");
+                for (a, name) in [(Analog::L2, "L2"), (Analog::R2, "R2")] {
+                    if ds_pill_button(ui, c, name, *source == a).clicked() && *source != a {
+                        *source = a;
+                        changed = true;
+                    }
+                    ui.add_space(8.0);
+                }
+            });
+            ds_row(ui, |ui| {
+                ds_label(ui, "Minimum");
+                let mut v = *min as i32;
+                if ds_slider_int(ui, c, &mut v, 0..=255).changed() {
+                    *min = v as u8;
+                    changed = true;
+                }
+                ds_value_pct(ui, v as f32 / 255.0 * 100.0);
+            });
+        }
+        LightbarBinding::Hue { stick } => {
+            ds_row(ui, |ui| {
+                ds_label(ui, "Stick");
+                for (st, name) in [(Stick::Left, "Left"), (Stick::Right, "Right")] {
+                    if ds_pill_button(ui, c, name, *stick == st).clicked() && *stick != st {
+                        *stick = st;
+                        changed = true;
+                    }
+                    ui.add_space(8.0);
+                }
+            });
+        }
+        LightbarBinding::Flash { button, color } => {
+            changed |= button_row(ui, c, button);
+            changed |= color_row(ui, color);
+        }
+        LightbarBinding::Tint {
+            when,
+            color,
+            amount,
+        } => {
+            ds_row(ui, |ui| {
+                ds_label(ui, "When");
+                let gyro = matches!(when, TintWhen::Gyro);
+                if ds_pill_button(ui, c, "Gyro moving", gyro).clicked() && !gyro {
+                    *when = TintWhen::Gyro;
+                    changed = true;
+                }
+                ui.add_space(8.0);
+                if ds_pill_button(ui, c, "Button held", !gyro).clicked() && gyro {
+                    *when = TintWhen::Held(Button::L2);
+                    changed = true;
+                }
+            });
+            if let TintWhen::Held(button) = when {
+                changed |= button_row(ui, c, button);
+            }
+            changed |= color_row(ui, color);
+            ds_row(ui, |ui| {
+                ds_label(ui, "Amount");
+                if ds_slider(ui, c, amount, 0.0..=1.0).changed() {
+                    changed = true;
+                }
+                ds_value_pct(ui, *amount * 100.0);
+            });
+        }
+    }
+    changed
+}
+
+fn metric_editor(ui: &mut Ui, c: &ThemeColors, metric: &mut Option<MetricBinding>) -> bool {
+    let mut changed = false;
+    let current = metric.as_ref().map(|m| m.source.label());
+    ds_row(ui, |ui| {
+        ds_label(ui, "Source");
+        ui.horizontal_wrapped(|ui| {
+            if ds_pill_button(ui, c, "Off", current.is_none()).clicked() && current.is_some() {
+                *metric = None;
+                changed = true;
+            }
+            for source in [
+                MetricSource::CpuLoad,
+                MetricSource::CpuTemp,
+                MetricSource::Memory,
+                MetricSource::Command {
+                    command: String::new(),
+                    interval_secs: 30,
+                },
+            ] {
+                let active = current == Some(source.label());
+                if ds_pill_button(ui, c, source.label(), active).clicked() && !active {
+                    *metric = Some(MetricBinding::new(source));
+                    changed = true;
+                }
+            }
+        });
+    });
+
+    let Some(m) = metric.as_mut() else {
+        return changed;
+    };
+    match &mut m.source {
+        MetricSource::Command {
+            command,
+            interval_secs,
+        } => {
+            ds_row(ui, |ui| {
+                ds_label(ui, "Command");
+                let resp = ui.add(
+                    egui::TextEdit::singleline(command)
+                        .hint_text("exit 0 = ok")
+                        .desired_width(320.0),
+                );
+                if resp.lost_focus() {
+                    changed = true;
+                }
+            });
+            ds_row(ui, |ui| {
+                ds_label(ui, "Interval");
+                let mut v = *interval_secs as i32;
+                if ds_slider_int(ui, c, &mut v, 1..=600).changed() {
+                    *interval_secs = v as u32;
+                    changed = true;
+                }
+                ds_value_text(ui, &format!("{} s", v));
+            });
+        }
+        source => {
+            let (unit, limit) = match source {
+                MetricSource::CpuTemp => ("°C", 120.0),
+                _ => ("%", 100.0),
+            };
+            for (label, value) in [("Low", &mut m.min), ("High", &mut m.max)] {
+                ds_row(ui, |ui| {
+                    ds_label(ui, label);
+                    if ds_slider(ui, c, value, 0.0..=limit).changed() {
+                        changed = true;
+                    }
+                    ds_value_text(ui, &format!("{:.0}{}", value, unit));
+                });
+            }
+        }
+    }
+    ds_row(ui, |ui| {
+        ds_label(ui, "Gradient");
+        for color in m.colors.iter_mut() {
+            let mut rgb = [color.0, color.1, color.2];
+            if ui.color_edit_button_srgb(&mut rgb).changed() {
+                *color = (rgb[0], rgb[1], rgb[2]);
+                changed = true;
+            }
+            ui.add_space(8.0);
+        }
+    });
+    changed
+}
+
+enum AnimationAction {
+    New,
+    Save,
+    Delete,
+    Select(String),
+    Replay,
+}
+
+fn scaled(r: u8, g: u8, b: u8, br: u8) -> Color32 {
+    let f = br as f32 / 255.0;
+    Color32::from_rgb(
+        (r as f32 * f) as u8,
+        (g as f32 * f) as u8,
+        (b as f32 * f) as u8,
+    )
+}
+
+fn animation_timeline(
+    ui: &mut Ui,
+    c: &ThemeColors,
+    anim: &LightbarAnimation,
+    selected: &mut usize,
+    playhead: f32,
+) {
+    let (rect, resp) = ui.allocate_exact_size(vec2(520.0, 64.0), Sense::click());
+    let painter = ui.painter_at(rect.expand(2.0));
+    let rounding = CornerRadius::same(6);
+    painter.rect_filled(rect, rounding, c.extreme_bg());
+
+    let strip = rect.shrink2(vec2(10.0, 14.0));
+    let total = anim.duration().max(0.001);
+    let steps = 128;
+    let w = strip.width() / steps as f32;
+    for i in 0..steps {
+        let t = (i as f32 + 0.5) / steps as f32 * total;
+        let (r, g, b, br) = anim.sample(t);
+        let x = strip.left() + i as f32 * w;
+        painter.rect_filled(
+            egui::Rect::from_min_size(pos2(x, strip.top()), vec2(w + 0.5, strip.height())),
+            0.0,
+            scaled(r, g, b, br),
+        );
+    }
+    painter.rect_stroke(
+        rect,
+        rounding,
+        Stroke::new(1.0, c.widget_inactive()),
+        StrokeKind::Inside,
+    );
+
+    let x_of = |t: f32| strip.left() + (t / total).clamp(0.0, 1.0) * strip.width();
+    for i in 0..anim.keys.len() {
+        let x = x_of(anim.key_start(i));
+        let col = if i == *selected {
+            c.accent()
+        } else {
+            c.text_dim()
+        };
+        painter.line_segment(
+            [pos2(x, rect.top() + 4.0), pos2(x, rect.bottom() - 4.0)],
+            Stroke::new(if i == *selected { 3.0 } else { 1.5 }, col),
+        );
+    }
+    let x = x_of(playhead);
+    painter.line_segment(
+        [pos2(x, strip.top()), pos2(x, strip.bottom())],
+        Stroke::new(1.0, Color32::WHITE),
+    );
+
+    if resp.clicked()
+        && let Some(p) = resp.interact_pointer_pos()
+    {
+        let t = (p.x - strip.left()) / strip.width() * total;
+        *selected = (0..anim.keys.len())
+            .rev()
+            .find(|&i| anim.key_start(i) <= t)
+            .unwrap_or(0);
+    }
+}
+
 impl DS4UApp {
     pub(crate) fn render_lightbar_section(&mut self, ui: &mut Ui) {
         let c = self.theme.colors.clone();
+        let mut anim_action: Option<AnimationAction> = None;
+        let mut anim_changed = false;
+        let mut bindings_changed = false;
+        let mut metric_changed = false;
+        let mut leds_changed = false;
 
         egui::ScrollArea::vertical()
             .auto_shrink([false, false])
@@ -76,21 +393,58 @@ impl DS4UApp {
                         let b = matches!(self.lightbar_effect, Breath { .. });
                         let r = matches!(self.lightbar_effect, Rainbow { .. });
                         let s = matches!(self.lightbar_effect, Strobe { .. });
+                        let bat = matches!(self.lightbar_effect, Battery { .. });
+                        let cus = matches!(self.lightbar_effect, Custom { .. });
+                        let vis = matches!(self.lightbar_effect, Visualizer { .. });
+                        let custom = Custom {
+                            animation: self
+                                .lightbar
+                                .animation
+                                .clone()
+                                .unwrap_or_else(|| LightbarAnimation::new("Animation1")),
+                        };
+                        let visualizer = Visualizer {
+                            config: self.lightbar.visualizer.clone(),
+                        };
                         ui.horizontal_wrapped(|ui| {
                             let mut select =
                                 |ui: &mut Ui, label: &str, active: bool, new: LightbarEffect| {
                                     if ds_pill_button(ui, &c, label, active).clicked() && !active {
+                                        let was_persisted = cus || vis;
+                                        if let Custom { animation } = &new {
+                                            self.lightbar.animation = Some(animation.clone());
+                                            self.lightbar.animation_started = Instant::now();
+                                        }
                                         self.lightbar_effect = new;
                                         self.apply_lightbar_effect();
                                         if matches!(self.lightbar_effect, LightbarEffect::None) {
                                             self.apply_lightbar();
                                         }
+                                        if was_persisted
+                                            || matches!(
+                                                self.lightbar_effect,
+                                                Custom { .. } | Visualizer { .. }
+                                            )
+                                        {
+                                            self.sync_profile();
+                                        }
                                     }
                                 };
                             select(ui, "Static", n, None);
                             select(ui, "Breathe", b, Breath { speed: 0.4 });
                             select(ui, "Rainbow", r, Rainbow { speed: 0.15 });
                             select(ui, "Strobe", s, Strobe { speed: 4.0 });
+                            select(
+                                ui,
+                                "Battery",
+                                bat,
+                                Battery {
+                                    low: 20,
+                                    player_leds: false,
+                                },
+                            );
+                            select(ui, "Custom", cus, custom);
+                            select(ui, "Visualizer", vis, visualizer);
                         });
                     });
 
@@ -143,29 +497,384 @@ impl DS4UApp {
                                 ds_value_text(ui, &format!("{:.0} Hz", speed));
                             });
                         }
+                        LightbarEffect::Custom { .. } => {}
+                        LightbarEffect::Battery { low, player_leds } => {
+                            ds_row(ui, |ui| {
+                                ds_label(ui, "Low Warning");
+                                let mut v = *low as i32;
+                                if ds_slider_int(ui, &c, &mut v, 0..=50).changed() {
+                                    *low = v as u8;
+                                    effect_changed = true;
+                                }
+                                ds_value_text(ui, &format!("{}%", v));
+                            });
+                            ds_row(ui, |ui| {
+                                ds_label(ui, "Player LED Gauge");
+                                if ds_toggle(ui, &c, player_leds).changed() {
+                                    effect_changed = true;
+                                }
+                            });
+                        }
+                        LightbarEffect::Visualizer { config } => {
+                            ds_row(ui, |ui| {
+                                ds_label(ui, "Source");
+                                let devices = &mut self.lightbar.capture_devices;
+                                egui::ComboBox::from_id_salt("visualizer_source")
+                                    .selected_text(RichText::new(&config.device).size(16.0))
+                                    .width(280.0)
+                                    .show_ui(ui, |ui| {
+                                        if devices.is_empty() {
+                                            *devices = list_capture_devices();
+                                        }
+                                        for name in devices.iter() {
+                                            if ui
+                                                .selectable_label(config.device == *name, name)
+                                                .clicked()
+                                                && config.device != *name
+                                            {
+                                                config.device = name.clone();
+                                                effect_changed = true;
+                                            }
+                                        }
+                                    });
+                                ui.add_space(8.0);
+                                if ds_pill_button(ui, &c, "Refresh", false).clicked() {
+                                    *devices = list_capture_devices();
+                                }
+                            });
+                            ds_row(ui, |ui| {
+                                ds_label(ui, "Gain");
+                                if ds_slider(ui, &c, &mut config.gain, 0.5..=16.0).changed() {
+                                    effect_changed = true;
+                                }
+                                ds_value_pct(ui, config.gain * 100.0);
+                            });
+                            ds_row(ui, |ui| {
+                                ds_label(ui, "Smoothing");
+                                if ds_slider(ui, &c, &mut config.smoothing, 0.0..=0.95).changed() {
+                                    effect_changed = true;
+                                }
+                                ds_value_pct(ui, config.smoothing * 100.0);
+                            });
+                            ds_row(ui, |ui| {
+                                ds_label(ui, "Palette");
+                                ui.horizontal_wrapped(|ui| {
+                                    for p in Palette::ALL {
+                                        let active = config.palette == p;
+                                        if ds_pill_button(ui, &c, p.label(), active).clicked()
+                                            && !active
+                                        {
+                                            config.palette = p;
+                                            effect_changed = true;
+                                        }
+                                    }
+                                });
+                            });
+                        }
                     }
                     if effect_changed {
+                        if let LightbarEffect::Visualizer { config } = &effect_clone {
+                            self.lightbar.visualizer = config.clone();
+                        }
                         self.lightbar_effect = effect_clone;
                         self.apply_lightbar_effect();
+                        if matches!(self.lightbar_effect, LightbarEffect::Visualizer { .. }) {
+                            self.sync_profile();
+                        }
+                    }
+
+                    if matches!(self.lightbar_effect, LightbarEffect::Custom { .. }) {
+                        self.render_animation_editor(ui, &c, &mut anim_action, &mut anim_changed);
+                    }
+
+                    ds_section(ui, &c, "System Metric");
+                    metric_changed = metric_editor(ui, &c, &mut self.lightbar.metric);
+
+                    ds_section(ui, &c, "Reactive");
+                    let mut remove = None;
+                    for (i, binding) in self.lightbar.bindings.iter_mut().enumerate() {
+                        ds_row(ui, |ui| {
+                            ds_label(ui, binding.label());
+                            if ds_pill_button(ui, &c, "Remove", false).clicked() {
+                                remove = Some(i);
+                            }
+                        });
+                        ui.push_id(i, |ui| {
+                            bindings_changed |= binding_editor(ui, &c, binding);
+                        });
+                        ui.add_space(8.0);
+                    }
+                    if let Some(i) = remove {
+                        self.lightbar.bindings.remove(i);
+                        bindings_changed = true;
                     }
+                    ds_row(ui, |ui| {
+                        ds_label(ui, "Add");
+                        for kind in ["Brightness", "Hue", "Flash", "Tint"] {
+                            if ds_pill_button(ui, &c, kind, false).clicked() {
+                                self.lightbar.bindings.push(new_binding(kind));
+                                bindings_changed = true;
+                            }
+                            ui.add_space(8.0);
+                        }
+                    });
                 }
 
                 ds_section(ui, &c, "Player Indicator");
+                if let Some(slot) = self.assigned_player() {
+                    ds_row(ui, |ui| {
+                        ds_label(ui, "Player");
+                        ds_value_text(ui, &format!("{} (assigned by daemon)", slot));
+                    });
+                } else {
+                    let led = &mut self.player_led;
+                    ds_row(ui, |ui| {
+                        ds_label(ui, "Player");
+                        ui.horizontal_wrapped(|ui| {
+                            for (i, &pattern) in PLAYER_LED_PATTERNS.iter().enumerate() {
+                                let active = led.pattern == pattern;
+                                if ds_pill_button(ui, &c, &format!("{}", i + 1), active).clicked()
+                                    && !active
+                                {
+                                    led.pattern = pattern;
+                                    leds_changed = true;
+                                }
+                            }
+                        });
+                    });
+                    ds_row(ui, |ui| {
+                        ds_label(ui, "Pattern");
+                        ui.horizontal_wrapped(|ui| {
+                            for bit in 0..PLAYER_LED_COUNT {
+                                let active = led.pattern & (1 << bit) != 0;
+                                let label = if active { "●" } else { "○" };
+                                if ds_pill_button(ui, &c, label, active).clicked() {
+                                    led.pattern ^= 1 << bit;
+                                    leds_changed = true;
+                                }
+                            }
+                        });
+                    });
+                }
+                let led = &mut self.player_led;
                 ds_row(ui, |ui| {
-                    ds_label(ui, "Player");
+                    ds_label(ui, "LED Brightness");
                     ui.horizontal_wrapped(|ui| {
-                        for i in 0..=7u8 {
-                            let active = self.player_leds == i;
-                            if ds_pill_button(ui, &c, &format!("{}", i + 1), active).clicked()
-                                && !active
-                            {
-                                self.player_leds = i;
-                                self.apply_player_leds();
-                                self.sync_profile();
+                        for b in PlayerLedBrightness::ALL {
+                            let active = led.brightness == b;
+                            if ds_pill_button(ui, &c, b.label(), active).clicked() && !active {
+                                led.brightness = b;
+                                leds_changed = true;
                             }
                         }
                     });
                 });
+                if self.ipc.is_some() {
+                    ds_row(ui, |ui| {
+                        ds_label(ui, "Animation");
+                        ui.horizontal_wrapped(|ui| {
+                            if ds_pill_button(ui, &c, "Off", led.animation.is_none()).clicked()
+                                && led.animation.is_some()
+                            {
+                                led.animation = None;
+                                leds_changed = true;
+                            }
+                            for a in PlayerLedAnimation::ALL {
+                                let active = led.animation == Some(a);
+                                if ds_pill_button(ui, &c, a.label(), active).clicked() && !active {
+                                    led.animation = Some(a);
+                                    leds_changed = true;
+                                }
+                            }
+                        });
+                    });
+                    if led.animation.is_some() {
+                        ds_row(ui, |ui| {
+                            ds_label(ui, "Speed");
+                            if ds_slider(ui, &c, &mut led.speed, 0.2..=4.0).changed() {
+                                leds_changed = true;
+                            }
+                            ds_value_text(ui, &format!("{:.1} Hz", led.speed));
+                        });
+                    }
+                }
             });
+
+        match anim_action {
+            Some(AnimationAction::New) => self.new_lightbar_animation(),
+            Some(AnimationAction::Save) => self.save_lightbar_animation(),
+            Some(AnimationAction::Delete) => self.delete_lightbar_animation(),
+            Some(AnimationAction::Select(name)) => self.select_lightbar_animation(&name),
+            Some(AnimationAction::Replay) => self.replay_lightbar_animation(),
+            None => {}
+        }
+        if anim_changed {
+            self.preview_lightbar_animation();
+        }
+        if leds_changed {
+            self.apply_player_leds();
+            self.sync_profile();
+        }
+        if metric_changed {
+            self.apply_lightbar_metric();
+            self.sync_profile();
+        }
+        if bindings_changed {
+            self.apply_lightbar_bindings();
+            self.sync_profile();
+        }
+    }
+
+    fn render_animation_editor(
+        &mut self,
+        ui: &mut Ui,
+        c: &ThemeColors,
+        action: &mut Option<AnimationAction>,
+        changed: &mut bool,
+    ) {
+        ds_section(ui, c, "Animation");
+        let current = self
+            .lightbar
+            .animation
+            .as_ref()
+            .map(|a| a.name.clone())
+            .unwrap_or_else(|| "None".to_string());
+        ds_row(ui, |ui| {
+            ds_label(ui, "Animation");
+            egui::ComboBox::from_id_salt("lightbar_animation")
+                .selected_text(RichText::new(&current).size(16.0))
+                .width(200.0)
+                .show_ui(ui, |ui| {
+                    for name in &self.lightbar.animations {
+                        if ui.selectable_label(*name == current, name).clicked() {
+                            *action = Some(AnimationAction::Select(name.clone()));
+                        }
+                    }
+                });
+            ui.add_space(8.0);
+            if ds_pill_button(ui, c, "New", false).clicked() {
+                *action = Some(AnimationAction::New);
+            }
+            ui.add_space(8.0);
+            if ds_pill_button(ui, c, "Save", false).clicked() {
+                *action = Some(AnimationAction::Save);
+            }
+            ui.add_space(8.0);
+            if ds_pill_button(ui, c, "Delete", false).clicked() {
+                *action = Some(AnimationAction::Delete);
+            }
+        });
+
+        let elapsed = self.lightbar.animation_started.elapsed().as_secs_f32();
+        let selected = &mut self.lightbar.animation_key;
+        let Some(anim) = self.lightbar.animation.as_mut() else {
+            return;
+        };
+
+        ds_row(ui, |ui| {
+            ds_label(ui, "Name");
+            ui.add(egui::TextEdit::singleline(&mut anim.name).desired_width(240.0));
+        });
+        ds_row(ui, |ui| {
+            ds_label(ui, "Loop");
+            let label = if anim.looping { "On" } else { "Off" };
+            if ds_pill_button(ui, c, label, anim.looping).clicked() {
+                anim.looping = !anim.looping;
+                *changed = true;
+            }
+            ui.add_space(8.0);
+            if ds_pill_button(ui, c, "Replay", false).clicked() {
+                *action = Some(AnimationAction::Replay);
+            }
+        });
+
+        let total = anim.duration();
+        let playhead = if anim.looping && total > 0.0 {
+            elapsed % total
+        } else {
+            elapsed.min(total)
+        };
+        let (r, g, b, br) = anim.sample(elapsed);
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            ui.add_space(ROW_PAD_X);
+            animation_timeline(ui, c, anim, selected, playhead);
+            ui.add_space(12.0);
+            let (rect, _) = ui.allocate_exact_size(vec2(64.0, 64.0), Sense::hover());
+            ui.painter()
+                .rect_filled(rect, CornerRadius::same(6), scaled(r, g, b, br));
+        });
+        ui.ctx().request_repaint();
+
+        *selected = (*selected).min(anim.keys.len().saturating_sub(1));
+        let count = anim.keys.len();
+        ds_row(ui, |ui| {
+            ds_label(ui, "Keyframe");
+            ds_value_text(ui, &format!("{} / {}", *selected + 1, count));
+            ui.add_space(12.0);
+            if ds_pill_button(ui, c, "Add", false).clicked() {
+                let key = anim.keys[*selected];
+                anim.keys.insert(*selected + 1, key);
+                *selected += 1;
+                *changed = true;
+            }
+            ui.add_space(8.0);
+            if ds_pill_button(ui, c, "Remove", false).clicked() && count > 1 {
+                anim.keys.remove(*selected);
+                *selected = (*selected).min(count - 2);
+                *changed = true;
+            }
+            ui.add_space(8.0);
+            if ds_pill_button(ui, c, "Earlier", false).clicked() && *selected > 0 {
+                anim.keys.swap(*selected, *selected - 1);
+                *selected -= 1;
+                *changed = true;
+            }
+            ui.add_space(8.0);
+            if ds_pill_button(ui, c, "Later", false).clicked() && *selected + 1 < count {
+                anim.keys.swap(*selected, *selected + 1);
+                *selected += 1;
+                *changed = true;
+            }
+        });
+
+        let key: &mut ColorKey = &mut anim.keys[*selected];
+        ds_row(ui, |ui| {
+            ds_label(ui, "Color");
+            let mut rgb = [key.color.0, key.color.1, key.color.2];
+            if ui.color_edit_button_srgb(&mut rgb).changed() {
+                key.color = (rgb[0], rgb[1], rgb[2]);
+                *changed = true;
+            }
+        });
+        ds_row(ui, |ui| {
+            ds_label(ui, "Brightness");
+            let mut v = key.brightness as i32;
+            if ds_slider_int(ui, c, &mut v, 0..=255).changed() {
+                key.brightness = v as u8;
+                *changed = true;
+            }
+            ds_value_pct(ui, v as f32 / 255.0 * 100.0);
+        });
+        ds_row(ui, |ui| {
+            ds_label(ui, "Duration");
+            let mut v = key.duration_ms as i32;
+            if ds_slider_int(ui, c, &mut v, 50..=5000).changed() {
+                key.duration_ms = v as u32;
+                *changed = true;
+            }
+            ds_value_text(ui, &format!("{} ms", v));
+        });
+        ds_row(ui, |ui| {
+            ds_label(ui, "Easing");
+            for e in Easing::ALL {
+                let active = key.easing == e;
+                if ds_pill_button(ui, c, e.label(), active).clicked() && !active {
+                    key.easing = e;
+                    *changed = true;
+                }
+                ui.add_space(8.0);
+            }
+        });
     }
 }
@@ -122,6 +122,8 @@ impl App for DS4UApp {
                     }
                     self.input.controller_state = Some(state);
                 }
+
+                self.handle_daemon_events();
             } else if self.input.polling {
                 self.stop_input_polling();
             }
@@ -140,6 +142,17 @@ impl App for DS4UApp {
                 self.update_battery();
             }
 
+            if self.active_section == Section::Audio
+                && self.audio.levels_polled.elapsed() > Duration::from_secs(1)
+            {
+                self.poll_audio_levels();
+            }
+
+            if self.triggers.preview_until.is_some() {
+                self.check_trigger_preview();
+                ctx.request_repaint_after_secs(0.25);
+            }
+
             if !needs_input && self.active_section != Section::Haptics {
                 ctx.request_repaint_after_secs(2.0);
             }
@@ -1,6 +1,8 @@
 use egui::{Color32, RichText, TextEdit, Ui, vec2};
 
 use crate::app::DS4UApp;
+use crate::settings::PlayerSlotMode;
+use crate::theme::ThemeColors;
 
 use super::widgets::{ds_label, ds_pill_button, ds_row, ds_section, ds_value_text};
 
@@ -37,6 +39,8 @@ impl DS4UApp {
                     }
                 });
 
+                self.render_controller_binding(ui, &c);
+
                 ds_section(ui, &c, "Create");
                 ds_row(ui, |ui| {
                     ds_label(ui, "Name");
@@ -115,4 +119,87 @@ impl DS4UApp {
             }
         }
     }
+
+    fn render_controller_binding(&mut self, ui: &mut Ui, c: &ThemeColors) {
+        let Some(serial) = self.controller_serial.clone() else {
+            return;
+        };
+        let binding = self.settings.controllers.get(&serial).cloned();
+        let mut nickname = binding
+            .as_ref()
+            .map(|b| b.nickname.clone())
+            .unwrap_or_default();
+        let mut player = binding.as_ref().map_or(0, |b| b.player);
+        let mut bound = binding.map(|b| b.profile).unwrap_or_default();
+        let mut changed = false;
+        let mut player_changed = false;
+
+        ds_section(ui, c, "This Controller");
+        ds_row(ui, |ui| {
+            ds_label(ui, "Serial");
+            ds_value_text(ui, &serial);
+        });
+        ds_row(ui, |ui| {
+            ds_label(ui, "Nickname");
+            changed |= ui
+                .add(
+                    TextEdit::singleline(&mut nickname)
+                        .hint_text("Optional")
+                        .desired_width(220.0),
+                )
+                .changed();
+        });
+        ds_row(ui, |ui| {
+            ds_label(ui, "Default profile");
+            let text = if bound.is_empty() {
+                "Global default".to_string()
+            } else {
+                bound.clone()
+            };
+            egui::ComboBox::from_id_salt("controller_binding")
+                .selected_text(text)
+                .width(220.0)
+                .show_ui(ui, |ui| {
+                    changed |= ui
+                        .selectable_value(&mut bound, String::new(), "Global default")
+                        .changed();
+                    for p in self.profile_manager.list_profiles() {
+                        let name = p.name.clone();
+                        changed |= ui.selectable_value(&mut bound, p.name, name).changed();
+                    }
+                });
+        });
+
+        if self.settings.player_slots == PlayerSlotMode::Sticky {
+            ds_row(ui, |ui| {
+                ds_label(ui, "Player slot");
+                ui.horizontal_wrapped(|ui| {
+                    for slot in 0..=7u8 {
+                        let label = if slot == 0 {
+                            "Auto".to_string()
+                        } else {
+                            slot.to_string()
+                        };
+                        let active = player == slot;
+                        if ds_pill_button(ui, c, &label, active).clicked() && !active {
+                            player = slot;
+                            player_changed = true;
+                        }
+                    }
+                });
+            });
+        }
+
+        if changed || player_changed {
+            let b = self.settings.binding_mut(&serial);
+            b.nickname = nickname;
+            b.profile = bound;
+            b.player = player;
+            self.settings.prune_bindings();
+            self.settings_manager.save(&self.settings);
+        }
+        if player_changed {
+            self.reassign_players();
+        }
+    }
 }
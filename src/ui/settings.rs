@@ -1,7 +1,8 @@
 use egui::{Color32, RichText, Sense, Stroke, StrokeKind, Ui, vec2};
 
 use crate::app::DS4UApp;
-use crate::ui::widgets::{ROW_PAD_X, ds_label, ds_pill_button, ds_row, ds_section};
+use crate::settings::PlayerSlotMode;
+use crate::ui::widgets::{ROW_PAD_X, ds_label, ds_pill_button, ds_row, ds_section, ds_toggle};
 
 impl DS4UApp {
     pub(crate) fn render_settings_section(&mut self, ui: &mut Ui) {
@@ -82,6 +83,59 @@ impl DS4UApp {
                     });
                 });
 
+                ds_section(ui, &c, "Players");
+                ds_row(ui, |ui| {
+                    ds_label(ui, "Player LEDs");
+                    for (mode, label) in [
+                        (PlayerSlotMode::Profile, "From profile"),
+                        (PlayerSlotMode::Order, "Connection order"),
+                        (PlayerSlotMode::Sticky, "Per controller"),
+                    ] {
+                        let active = self.settings.player_slots == mode;
+                        if ds_pill_button(ui, &c, label, active).clicked() && !active {
+                            self.settings.player_slots = mode;
+                            self.reassign_players();
+                        }
+                        ui.add_space(6.0);
+                    }
+                });
+
+                ds_section(ui, &c, "Game Integration");
+                ds_row(ui, |ui| {
+                    ds_label(ui, "DSX UDP server");
+                    let mut dsx = self.settings.dsx;
+                    ds_toggle(ui, &c, &mut dsx.enabled);
+                    ui.add_space(16.0);
+                    ui.label(RichText::new("Port").size(16.0).color(c.text_dim()));
+                    ui.add(egui::DragValue::new(&mut dsx.port).range(1024..=65535));
+                    if dsx != self.settings.dsx {
+                        self.settings.dsx = dsx;
+                        self.settings_manager.save(&self.settings);
+                    }
+                    if dsx.enabled && !self.daemon_alive() {
+                        ui.add_space(16.0);
+                        ui.label(
+                            RichText::new("Requires the daemon")
+                                .size(16.0)
+                                .color(c.warning()),
+                        );
+                    }
+                });
+                ds_row(ui, |ui| {
+                    ds_label(ui, "Virtual gamepad");
+                    if ds_toggle(ui, &c, &mut self.settings.virtual_pad).changed() {
+                        self.settings_manager.save(&self.settings);
+                    }
+                    if self.settings.virtual_pad && !self.daemon_alive() {
+                        ui.add_space(16.0);
+                        ui.label(
+                            RichText::new("Requires the daemon")
+                                .size(16.0)
+                                .color(c.warning()),
+                        );
+                    }
+                });
+
                 ds_section(ui, &c, "Daemon");
 
                 let alive = self.daemon_alive();
@@ -171,10 +171,37 @@ impl DS4UApp {
                         c.warning(),
                     );
                 }
+                if via_daemon && self.controllers.len() > 1 {
+                    ui.add_space(12.0);
+                    self.render_controller_picker(ui);
+                }
             });
         });
     }
 
+    fn render_controller_picker(&mut self, ui: &mut Ui) {
+        let current = self.controller_serial.clone().unwrap_or_default();
+        let mut picked = None;
+        egui::ComboBox::from_id_salt("controller_picker")
+            .selected_text(RichText::new(&current).size(13.0))
+            .show_ui(ui, |ui| {
+                for ctrl in &self.controllers {
+                    let name = if ctrl.nickname.is_empty() {
+                        &ctrl.serial
+                    } else {
+                        &ctrl.nickname
+                    };
+                    let label = format!("{}  {}", name, if ctrl.is_bt { "BT" } else { "USB" });
+                    if ui.selectable_label(ctrl.serial == current, label).clicked() {
+                        picked = Some(ctrl.serial.clone());
+                    }
+                }
+            });
+        if let Some(serial) = picked {
+            self.select_controller(&serial);
+        }
+    }
+
     pub(crate) fn render_header(&mut self, ui: &mut Ui) {
         let title = match self.active_section {
             Section::Inputs => "Controller Inputs",
@@ -1,8 +1,14 @@
-use egui::{RichText, Ui, vec2};
+use std::ops::RangeInclusive;
+use std::time::Instant;
+
+use egui::{CornerRadius, Rect, RichText, Sense, Stroke, StrokeKind, Ui, pos2, vec2};
 
 use crate::app::DS4UApp;
 use crate::common::TriggerMode;
-use crate::profiles::TriggerConfig;
+use crate::inputs::Button;
+use crate::profiles::{TriggerConfig, TriggerProgram};
+use crate::theme::ThemeColors;
+use crate::trigger_presets::is_builtin;
 use crate::ui::widgets::{ds_label, ds_row, ds_slider_int, ds_value_text};
 
 use super::widgets::{ds_pill_button, ds_section};
@@ -17,6 +23,48 @@ const ALL_MODES: &[TriggerMode] = &[
     TriggerMode::Machine,
 ];
 
+const PROGRAMS: &[&str] = &["Ramp", "Threshold", "Hold"];
+
+fn default_program(name: &str) -> TriggerProgram {
+    match name {
+        "Threshold" => TriggerProgram::Threshold {
+            threshold: 200,
+            effect: TriggerConfig {
+                mode: TriggerMode::Vibration,
+                ..TriggerConfig::default()
+            },
+        },
+        "Hold" => TriggerProgram::Hold {
+            button: Button::L1,
+            effect: TriggerConfig {
+                mode: TriggerMode::Feedback,
+                ..TriggerConfig::default()
+            },
+        },
+        _ => TriggerProgram::Ramp { min: 1, max: 8 },
+    }
+}
+
+const HOLD_BUTTONS: &[(Button, &str)] = &[
+    (Button::L1, "L1"),
+    (Button::R1, "R1"),
+    (Button::L3, "L3"),
+    (Button::R3, "R3"),
+    (Button::Cross, "Cross"),
+    (Button::Circle, "Circle"),
+    (Button::Square, "Square"),
+    (Button::Triangle, "Triangle"),
+];
+
+enum PresetAction {
+    Apply(bool, bool),
+    Preview(bool),
+    Save(bool),
+    Delete,
+    Import,
+    Export,
+}
+
 fn mode_label(m: &TriggerMode) -> &'static str {
     match m {
         TriggerMode::Off => "Off",
@@ -26,174 +74,262 @@ fn mode_label(m: &TriggerMode) -> &'static str {
         TriggerMode::Galloping => "Galloping",
         TriggerMode::Vibration => "Vibration",
         TriggerMode::Machine => "Machine",
+        TriggerMode::Raw => "Raw",
     }
 }
 
-impl DS4UApp {
-    fn render_trigger_panel(
-        ui: &mut Ui,
-        c: &crate::theme::ThemeColors,
-        label: &str,
-        cfg: &mut TriggerConfig,
-    ) -> bool {
-        let mut changed = false;
+fn int_row(
+    ui: &mut Ui,
+    c: &ThemeColors,
+    label: &str,
+    value: &mut u8,
+    range: RangeInclusive<i32>,
+    suffix: &str,
+) -> bool {
+    let mut v = *value as i32;
+    let changed = ds_row(ui, |ui| {
+        ds_label(ui, label);
+        let changed = ds_slider_int(ui, c, &mut v, range).changed();
+        ds_value_text(ui, &format!("{v}{suffix}"));
+        changed
+    });
+    if changed {
+        *value = v as u8;
+    }
+    changed
+}
 
-        ds_section(ui, c, label);
+fn effect_editor(ui: &mut Ui, c: &ThemeColors, cfg: &mut TriggerConfig) -> bool {
+    let mut changed = false;
 
-        ds_row(ui, |ui| {
-            ds_label(ui, "Mode");
-            ui.horizontal_wrapped(|ui| {
-                for m in ALL_MODES {
-                    let active = std::mem::discriminant(&cfg.mode) == std::mem::discriminant(m);
-                    if ds_pill_button(ui, c, mode_label(m), active).clicked() && !active {
-                        cfg.mode = m.clone();
-                        changed = true;
-                    }
+    ds_row(ui, |ui| {
+        ds_label(ui, "Mode");
+        ui.horizontal_wrapped(|ui| {
+            for m in ALL_MODES {
+                let active = std::mem::discriminant(&cfg.mode) == std::mem::discriminant(m);
+                if ds_pill_button(ui, c, mode_label(m), active).clicked() && !active {
+                    cfg.mode = m.clone();
+                    changed = true;
                 }
-            });
+            }
         });
+    });
 
-        match cfg.mode {
-            TriggerMode::Off => {
-                ds_row(ui, |ui| {
-                    ds_label(ui, "Status");
-                    ui.label(
-                        RichText::new("Effect disabled")
-                            .size(18.0)
-                            .italics()
-                            .color(c.text_dim()),
-                    );
-                });
+    match cfg.mode {
+        TriggerMode::Off => {
+            ds_row(ui, |ui| {
+                ds_label(ui, "Status");
+                ui.label(
+                    RichText::new("Effect disabled")
+                        .size(18.0)
+                        .italics()
+                        .color(c.text_dim()),
+                );
+            });
+        }
+        TriggerMode::Feedback => {
+            changed |= zone_row(ui, c, "Zones", cfg);
+        }
+        TriggerMode::Weapon => {
+            let lo = cfg.start as i32 + 1;
+            changed |= int_row(ui, c, "Pre-pull", &mut cfg.start, 2..=7, "");
+            changed |= int_row(ui, c, "Break", &mut cfg.end, lo..=8, "");
+            changed |= int_row(ui, c, "Strength", &mut cfg.strength, 1..=8, "");
+        }
+        TriggerMode::Bow => {
+            let lo = cfg.start as i32 + 1;
+            changed |= int_row(ui, c, "Start", &mut cfg.start, 0..=7, "");
+            changed |= int_row(ui, c, "Snap", &mut cfg.end, lo..=8, "");
+            changed |= int_row(ui, c, "Force", &mut cfg.strength, 1..=8, "");
+            let mut snap = cfg.snap();
+            if int_row(ui, c, "Snap Force", &mut snap, 1..=8, "") {
+                cfg.snap = Some(snap);
+                changed = true;
             }
-            TriggerMode::Feedback => {
-                let mut s = cfg.start as i32;
-                let mut e = cfg.end as i32;
-                let mut g = cfg.strength as i32;
-                ds_row(ui, |ui| {
-                    ds_label(ui, "Start");
-                    if ds_slider_int(ui, c, &mut s, 0..=9).changed() {
-                        cfg.start = s as u8;
-                        changed = true;
-                    }
-                    ds_value_text(ui, &s.to_string());
-                });
-                ds_row(ui, |ui| {
-                    ds_label(ui, "End");
-                    if ds_slider_int(ui, c, &mut e, 0..=9).changed() {
-                        cfg.end = e as u8;
-                        changed = true;
-                    }
-                    ds_value_text(ui, &e.to_string());
-                });
-                ds_row(ui, |ui| {
-                    ds_label(ui, "Strength");
-                    if ds_slider_int(ui, c, &mut g, 1..=8).changed() {
-                        cfg.strength = g as u8;
-                        changed = true;
-                    }
-                    ds_value_text(ui, &g.to_string());
-                });
+        }
+        TriggerMode::Galloping => {
+            let lo = cfg.start as i32 + 1;
+            let mut feet = cfg.feet();
+            let second_lo = feet.0 as i32 + 1;
+            changed |= int_row(ui, c, "Start", &mut cfg.start, 0..=8, "");
+            changed |= int_row(ui, c, "End", &mut cfg.end, lo..=9, "");
+            let mut feet_changed = int_row(ui, c, "First Foot", &mut feet.0, 0..=6, "");
+            feet_changed |= int_row(ui, c, "Second Foot", &mut feet.1, second_lo..=7, "");
+            if feet_changed {
+                cfg.feet = Some(feet);
+                changed = true;
             }
-            TriggerMode::Weapon => {
-                let lo = cfg.start as i32 + 1;
-                let mut s = cfg.start as i32;
-                let mut e = cfg.end as i32;
-                let mut g = cfg.strength as i32;
-                ds_row(ui, |ui| {
-                    ds_label(ui, "Pre-pull");
-                    if ds_slider_int(ui, c, &mut s, 2..=7).changed() {
-                        cfg.start = s as u8;
-                        changed = true;
-                    }
-                    ds_value_text(ui, &s.to_string());
-                });
-                ds_row(ui, |ui| {
-                    ds_label(ui, "Break");
-                    if ds_slider_int(ui, c, &mut e, lo..=8).changed() {
-                        cfg.end = e as u8;
-                        changed = true;
-                    }
-                    ds_value_text(ui, &e.to_string());
-                });
-                ds_row(ui, |ui| {
-                    ds_label(ui, "Strength");
-                    if ds_slider_int(ui, c, &mut g, 1..=8).changed() {
-                        cfg.strength = g as u8;
-                        changed = true;
-                    }
-                    ds_value_text(ui, &g.to_string());
-                });
+            changed |= int_row(ui, c, "Frequency", &mut cfg.frequency, 1..=255, " Hz");
+        }
+        TriggerMode::Vibration => {
+            changed |= zone_row(ui, c, "Amplitude", cfg);
+            changed |= int_row(ui, c, "Frequency", &mut cfg.frequency, 1..=255, " Hz");
+        }
+        TriggerMode::Machine => {
+            let lo = cfg.start as i32 + 1;
+            changed |= int_row(ui, c, "Start", &mut cfg.start, 0..=8, "");
+            changed |= int_row(ui, c, "End", &mut cfg.end, lo..=9, "");
+            changed |= int_row(ui, c, "Amplitude A", &mut cfg.strength, 1..=8, "");
+            let mut amplitude_b = cfg.amplitude_b();
+            if int_row(ui, c, "Amplitude B", &mut amplitude_b, 1..=8, "") {
+                cfg.amplitude_b = Some(amplitude_b);
+                changed = true;
             }
-            TriggerMode::Bow => {
-                let lo = cfg.start as i32 + 1;
-                let mut s = cfg.start as i32;
-                let mut e = cfg.end as i32;
-                let mut g = cfg.strength as i32;
-                ds_row(ui, |ui| {
-                    ds_label(ui, "Start");
-                    if ds_slider_int(ui, c, &mut s, 0..=8).changed() {
-                        cfg.start = s as u8;
-                        changed = true;
-                    }
-                    ds_value_text(ui, &s.to_string());
-                });
-                ds_row(ui, |ui| {
-                    ds_label(ui, "Snap");
-                    if ds_slider_int(ui, c, &mut e, lo..=8).changed() {
-                        cfg.end = e as u8;
-                        changed = true;
-                    }
-                    ds_value_text(ui, &e.to_string());
-                });
-                ds_row(ui, |ui| {
-                    ds_label(ui, "Force");
-                    if ds_slider_int(ui, c, &mut g, 1..=8).changed() {
-                        cfg.strength = g as u8;
-                        changed = true;
-                    }
-                    ds_value_text(ui, &g.to_string());
-                });
+            changed |= int_row(ui, c, "Frequency", &mut cfg.frequency, 1..=255, " Hz");
+            changed |= int_row(ui, c, "Period", &mut cfg.period, 0..=255, "");
+        }
+        TriggerMode::Raw => {
+            let (effect_type, params) = cfg.to_effect();
+            let bytes: Vec<String> = params.iter().map(|b| format!("{b:02x}")).collect();
+            ds_row(ui, |ui| {
+                ds_label(ui, "Effect");
+                ui.label(
+                    RichText::new(format!("{effect_type:#04x}  {}", bytes.join(" ")))
+                        .size(16.0)
+                        .monospace()
+                        .color(c.text_dim()),
+                );
+            });
+        }
+    }
+
+    changed
+}
+
+fn program_editor(ui: &mut Ui, c: &ThemeColors, program: &mut Option<TriggerProgram>) -> bool {
+    let mut changed = false;
+
+    ds_section(ui, c, "Dynamic");
+    ds_row(ui, |ui| {
+        ds_label(ui, "Program");
+        ui.horizontal_wrapped(|ui| {
+            let current = program.as_ref().map(|p| p.label());
+            if ds_pill_button(ui, c, "Off", current.is_none()).clicked() && current.is_some() {
+                *program = None;
+                changed = true;
             }
-            TriggerMode::Galloping | TriggerMode::Vibration | TriggerMode::Machine => {
-                let lo = cfg.start as i32 + 1;
-                let mut s = cfg.start as i32;
-                let mut e = cfg.end as i32;
-                let mut g = cfg.strength as i32;
-                let mut f = cfg.frequency as i32;
-                ds_row(ui, |ui| {
-                    ds_label(ui, "Start");
-                    if ds_slider_int(ui, c, &mut s, 0..=8).changed() {
-                        cfg.start = s as u8;
-                        changed = true;
-                    }
-                    ds_value_text(ui, &s.to_string());
-                });
-                ds_row(ui, |ui| {
-                    ds_label(ui, "End");
-                    if ds_slider_int(ui, c, &mut e, lo..=9).changed() {
-                        cfg.end = e as u8;
-                        changed = true;
-                    }
-                    ds_value_text(ui, &e.to_string());
-                });
-                ds_row(ui, |ui| {
-                    ds_label(ui, "Amplitude");
-                    if ds_slider_int(ui, c, &mut g, 1..=8).changed() {
-                        cfg.strength = g as u8;
-                        changed = true;
-                    }
-                    ds_value_text(ui, &g.to_string());
-                });
-                ds_row(ui, |ui| {
-                    ds_label(ui, "Frequency");
-                    if ds_slider_int(ui, c, &mut f, 1..=255).changed() {
-                        cfg.frequency = f as u8;
-                        changed = true;
+            for name in PROGRAMS {
+                let active = current == Some(*name);
+                if ds_pill_button(ui, c, name, active).clicked() && !active {
+                    *program = Some(default_program(name));
+                    changed = true;
+                }
+            }
+        });
+    });
+
+    match program {
+        None => {}
+        Some(TriggerProgram::Ramp { min, max }) => {
+            changed |= int_row(ui, c, "Min", min, 0..=8, "");
+            changed |= int_row(ui, c, "Max", max, 0..=8, "");
+        }
+        Some(TriggerProgram::Threshold { threshold, effect }) => {
+            changed |= int_row(ui, c, "Threshold", threshold, 0..=255, "");
+            changed |= effect_editor(ui, c, effect);
+        }
+        Some(TriggerProgram::Hold { button, effect }) => {
+            ds_row(ui, |ui| {
+                ds_label(ui, "Button");
+                ui.horizontal_wrapped(|ui| {
+                    for (b, name) in HOLD_BUTTONS {
+                        let active = button == b;
+                        if ds_pill_button(ui, c, name, active).clicked() && !active {
+                            *button = b.clone();
+                            changed = true;
+                        }
                     }
-                    ds_value_text(ui, &format!("{} Hz", f));
                 });
-            }
+            });
+            changed |= effect_editor(ui, c, effect);
+        }
+    }
+
+    changed
+}
+
+fn zone_editor(ui: &mut Ui, c: &ThemeColors, zones: &mut [u8; 10]) -> bool {
+    let w = ui.available_width().min(420.0);
+    let (rect, resp) = ui.allocate_exact_size(vec2(w, 110.0), Sense::click_and_drag());
+    let painter = ui.painter_at(rect.expand(2.0));
+    let rounding = CornerRadius::same(6);
+    painter.rect_filled(rect, rounding, c.extreme_bg());
+    painter.rect_stroke(
+        rect,
+        rounding,
+        Stroke::new(1.0, c.widget_inactive()),
+        StrokeKind::Inside,
+    );
+
+    let plot = rect.shrink2(vec2(10.0, 18.0)).translate(vec2(0.0, 8.0));
+    let slot = plot.width() / zones.len() as f32;
+
+    let mut changed = false;
+    if (resp.clicked() || resp.dragged())
+        && let Some(p) = resp.interact_pointer_pos()
+    {
+        let i = (((p.x - plot.left()) / slot).floor() as usize).min(zones.len() - 1);
+        let v = (((plot.bottom() - p.y) / plot.height()).clamp(0.0, 1.0) * 8.0).round() as u8;
+        if zones[i] != v {
+            zones[i] = v;
+            changed = true;
         }
+    }
+
+    for (i, &z) in zones.iter().enumerate() {
+        let x = plot.left() + i as f32 * slot;
+        let bar = Rect::from_min_max(
+            pos2(x + 3.0, plot.bottom() - z as f32 / 8.0 * plot.height()),
+            pos2(x + slot - 3.0, plot.bottom()),
+        );
+        let color = if z > 0 {
+            c.accent()
+        } else {
+            c.widget_inactive()
+        };
+        painter.rect_filled(bar.expand2(vec2(0.0, 1.0)), CornerRadius::same(2), color);
+        painter.text(
+            pos2(x + slot * 0.5, rect.top() + 4.0),
+            egui::Align2::CENTER_TOP,
+            if z > 0 {
+                z.to_string()
+            } else {
+                "·".to_string()
+            },
+            egui::FontId::proportional(11.0),
+            c.text_dim(),
+        );
+    }
+
+    changed
+}
+
+fn zone_row(ui: &mut Ui, c: &ThemeColors, label: &str, cfg: &mut TriggerConfig) -> bool {
+    let mut zones = cfg.zone_strengths();
+    let changed = ds_row(ui, |ui| {
+        ds_label(ui, label);
+        zone_editor(ui, c, &mut zones)
+    });
+    if changed {
+        cfg.zones = Some(zones);
+    }
+    changed
+}
+
+impl DS4UApp {
+    fn render_trigger_panel(
+        ui: &mut Ui,
+        c: &ThemeColors,
+        label: &str,
+        cfg: &mut TriggerConfig,
+        program: &mut Option<TriggerProgram>,
+    ) -> bool {
+        let mut changed = false;
+
+        ds_section(ui, c, label);
+
+        changed |= effect_editor(ui, c, cfg);
+        changed |= program_editor(ui, c, program);
 
         ds_section(ui, c, "Deadband");
         let lo = cfg.deadband.release as i32 + 1;
@@ -219,12 +355,102 @@ impl DS4UApp {
         changed
     }
 
+    fn render_trigger_presets(
+        &mut self,
+        ui: &mut Ui,
+        c: &ThemeColors,
+        action: &mut Option<PresetAction>,
+    ) {
+        ds_section(ui, c, "Presets");
+        let selected = self.triggers.selected_preset.clone();
+        ds_row(ui, |ui| {
+            ds_label(ui, "Preset");
+            let text = selected.as_deref().unwrap_or("None");
+            egui::ComboBox::from_id_salt("trigger_preset")
+                .selected_text(RichText::new(text).size(16.0))
+                .width(200.0)
+                .show_ui(ui, |ui| {
+                    for p in &self.triggers.presets {
+                        let active = selected.as_ref() == Some(&p.name);
+                        if ui.selectable_label(active, &p.name).clicked() {
+                            self.triggers.selected_preset = Some(p.name.clone());
+                        }
+                    }
+                });
+            ui.add_space(8.0);
+            if ds_pill_button(ui, c, "Import", false).clicked() {
+                *action = Some(PresetAction::Import);
+            }
+        });
+
+        if let Some(name) = &selected {
+            ds_row(ui, |ui| {
+                ds_label(ui, "Apply");
+                if ds_pill_button(ui, c, "L2", false).clicked() {
+                    *action = Some(PresetAction::Apply(true, false));
+                }
+                ui.add_space(8.0);
+                if ds_pill_button(ui, c, "R2", false).clicked() {
+                    *action = Some(PresetAction::Apply(false, true));
+                }
+                ui.add_space(8.0);
+                if ds_pill_button(ui, c, "Both", false).clicked() {
+                    *action = Some(PresetAction::Apply(true, true));
+                }
+            });
+            ds_row(ui, |ui| {
+                ds_label(ui, "Try 5 s");
+                if ds_pill_button(ui, c, "L2", false).clicked() {
+                    *action = Some(PresetAction::Preview(true));
+                }
+                ui.add_space(8.0);
+                if ds_pill_button(ui, c, "R2", false).clicked() {
+                    *action = Some(PresetAction::Preview(false));
+                }
+                if let Some(until) = self.triggers.preview_until {
+                    let left = until.saturating_duration_since(Instant::now());
+                    ds_value_text(ui, &format!("{:.0} s", left.as_secs_f32().ceil()));
+                }
+            });
+            ds_row(ui, |ui| {
+                ds_label(ui, "Library");
+                if ds_pill_button(ui, c, "Export", false).clicked() {
+                    *action = Some(PresetAction::Export);
+                }
+                if !is_builtin(name) {
+                    ui.add_space(8.0);
+                    if ds_pill_button(ui, c, "Delete", false).clicked() {
+                        *action = Some(PresetAction::Delete);
+                    }
+                }
+            });
+        }
+
+        ds_row(ui, |ui| {
+            ds_label(ui, "Save As");
+            ui.add(egui::TextEdit::singleline(&mut self.triggers.preset_name).desired_width(200.0));
+            ui.add_space(8.0);
+            if ds_pill_button(ui, c, "From L2", false).clicked() {
+                *action = Some(PresetAction::Save(true));
+            }
+            ui.add_space(8.0);
+            if ds_pill_button(ui, c, "From R2", false).clicked() {
+                *action = Some(PresetAction::Save(false));
+            }
+        });
+    }
+
     pub(crate) fn render_triggers_section(&mut self, ui: &mut Ui) {
         let c = self.theme.colors.clone();
         let mut left_changed = false;
         let mut right_changed = false;
+        let mut preset_action: Option<PresetAction> = None;
 
         let (mut l, mut r) = (self.triggers.left.clone(), self.triggers.right.clone());
+        let (mut lp, mut rp) = (
+            self.triggers.left_program.clone(),
+            self.triggers.right_program.clone(),
+        );
 
         egui::ScrollArea::vertical()
             .auto_shrink([false, false])
@@ -241,8 +467,13 @@ impl DS4UApp {
                             vec2(col_w, 0.0),
                             egui::Layout::top_down(egui::Align::Min),
                             |ui| {
-                                left_changed =
-                                    Self::render_trigger_panel(ui, &c, "L2 — Left Trigger", &mut l);
+                                left_changed = Self::render_trigger_panel(
+                                    ui,
+                                    &c,
+                                    "L2 — Left Trigger",
+                                    &mut l,
+                                    &mut lp,
+                                );
                             },
                         )
                         .response
@@ -260,6 +491,7 @@ impl DS4UApp {
                                     &c,
                                     "R2 — Right Trigger",
                                     &mut r,
+                                    &mut rp,
                                 );
                             },
                         )
@@ -274,12 +506,16 @@ impl DS4UApp {
                     );
                 });
 
+                self.render_trigger_presets(ui, &c, &mut preset_action);
+
                 ds_section(ui, &c, "Actions");
                 ds_row(ui, |ui| {
                     ds_label(ui, "Reset");
                     if ds_pill_button(ui, &c, "Both triggers to Off", false).clicked() {
                         self.triggers.left = TriggerConfig::default();
                         self.triggers.right = TriggerConfig::default();
+                        self.triggers.left_program = None;
+                        self.triggers.right_program = None;
                         self.apply_triggers();
                         self.apply_input_transform();
                         self.sync_profile();
@@ -289,6 +525,18 @@ impl DS4UApp {
 
         self.triggers.left = l;
         self.triggers.right = r;
+        self.triggers.left_program = lp;
+        self.triggers.right_program = rp;
+
+        match preset_action {
+            Some(PresetAction::Apply(left, right)) => self.apply_trigger_preset(left, right),
+            Some(PresetAction::Preview(left)) => self.preview_trigger_preset(left),
+            Some(PresetAction::Save(left)) => self.save_trigger_preset(left),
+            Some(PresetAction::Delete) => self.delete_trigger_preset(),
+            Some(PresetAction::Import) => self.import_trigger_preset(),
+            Some(PresetAction::Export) => self.export_trigger_preset(),
+            None => {}
+        }
 
         if left_changed || right_changed {
             self.apply_triggers();
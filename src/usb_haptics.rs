@@ -1,3 +1,5 @@
+use std::fs;
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
@@ -6,17 +8,21 @@ use alsa::pcm::{Access, Format, HwParams, PCM};
 use alsa::{Direction, ValueOr};
 use anyhow::{Context, Result};
 
+use crate::audio_reactive::ReactiveConfig;
 use crate::common::{DS_PID, DS_VID, DSE_PID, HapticPattern};
+use crate::haptic_clip::ClipPlayer;
+use crate::haptic_synth::{CustomPattern, RumbleHaptics, RumbleVoice};
+use crate::haptics_stream::HapticSource;
 use crate::util::mlock;
 
 const USB_AUDIO_CHANNELS: usize = 4;
 const USB_AUDIO_RATE: u32 = 48_000;
+const SPEAKER_L_CHANNEL: usize = 0;
+const SPEAKER_R_CHANNEL: usize = 1;
 const HAPTIC_L_CHANNEL: usize = 2;
 const HAPTIC_R_CHANNEL: usize = 3;
 const WRITE_FRAMES: usize = 480;
-pub const HAPTIC_CARRIER_HZ: f32 = 160.0;
-
-type Params = (HapticPattern, u8, f32);
+pub const USB_HAPTICS_RATE: u32 = USB_AUDIO_RATE;
 
 pub fn find_dualsense_card() -> Option<u32> {
     let want_ds = format!("{:04x}:{:04x}", DS_VID, DS_PID);
@@ -33,24 +39,15 @@ pub fn find_dualsense_card() -> Option<u32> {
     None
 }
 
-fn envelope(pattern: HapticPattern, strength: u8, speed: f32, t: f32) -> f32 {
-    let s = (strength.min(7) as f32 / 7.0).clamp(0.0, 1.0);
-    match pattern {
-        HapticPattern::None => 0.0,
-        HapticPattern::Constant => s,
-        HapticPattern::Pulse => {
-            let phase = (t * speed).rem_euclid(1.0);
-            if phase < 0.5 { s } else { 0.0 }
-        }
-        HapticPattern::Ramp => {
-            let phase = (t * speed).rem_euclid(1.0);
-            s * phase
-        }
-        HapticPattern::Wave => {
-            let v = (t * speed * std::f32::consts::TAU).sin() * 0.5 + 0.5;
-            s * v
-        }
-    }
+pub fn find_card_for_hid(hid_path: &str) -> Option<u32> {
+    let node = Path::new(hid_path).file_name()?;
+    let hid = fs::canonicalize(Path::new("/sys/class/hidraw").join(node).join("device")).ok()?;
+    // hidraw sits under the USB interface, the sound card under a sibling interface.
+    let usb = hid.parent()?.parent()?;
+    (0..32u32).find(|n| {
+        fs::canonicalize(format!("/sys/class/sound/card{n}/device"))
+            .is_ok_and(|dev| dev.parent() == Some(usb))
+    })
 }
 
 fn open_pcm(card: u32) -> Result<PCM> {
@@ -68,31 +65,89 @@ fn open_pcm(card: u32) -> Result<PCM> {
     Ok(pcm)
 }
 
-fn run_stream(pcm: &PCM, stop: &AtomicBool, params: &Arc<Mutex<Params>>) -> Result<()> {
+struct Mix {
+    haptics: Option<HapticSource>,
+    rumble: Option<RumbleVoice>,
+    overlay: Option<ClipPlayer>,
+    speaker: Option<ClipPlayer>,
+    open: bool,
+}
+
+impl Mix {
+    fn render(&mut self, t: f32, haptics: &mut [[f32; 2]], speaker: &mut [[f32; 2]]) -> bool {
+        match &mut self.haptics {
+            Some(source) => {
+                if !source.render(USB_AUDIO_RATE, t, haptics) {
+                    self.haptics = None;
+                }
+            }
+            None => haptics.fill([0.0; 2]),
+        }
+        if let Some(voice) = &mut self.rumble {
+            voice.mix(USB_AUDIO_RATE, haptics);
+        }
+        if let Some(player) = &mut self.overlay {
+            mix_overlay(player, haptics);
+            if player.finished() {
+                self.overlay = None;
+            }
+        }
+        match &mut self.speaker {
+            Some(player) => {
+                if !player.fill(speaker) {
+                    self.speaker = None;
+                }
+            }
+            None => speaker.fill([0.0; 2]),
+        }
+        self.open = self.haptics.is_some()
+            || self.rumble.is_some()
+            || self.overlay.is_some()
+            || self.speaker.is_some();
+        self.open
+    }
+}
+
+pub fn mix_overlay(player: &mut ClipPlayer, out: &mut [[f32; 2]]) {
+    for f in out.iter_mut() {
+        let Some(o) = player.next_frame() else {
+            break;
+        };
+        f[0] = (f[0] + o[0]).clamp(-1.0, 1.0);
+        f[1] = (f[1] + o[1]).clamp(-1.0, 1.0);
+    }
+}
+
+fn to_sample(v: f32) -> i16 {
+    (v * 32767.0).round().clamp(-32767.0, 32767.0) as i16
+}
+
+fn run_stream(pcm: &PCM, stop: &AtomicBool, mix: &Mutex<Mix>) -> Result<()> {
     let io = pcm.io_i16()?;
     pcm.prepare()?;
 
-    let dt = 1.0 / USB_AUDIO_RATE as f32;
     let mut t: f32 = 0.0;
+    let mut haptics = vec![[0.0f32; 2]; WRITE_FRAMES];
+    let mut speaker = vec![[0.0f32; 2]; WRITE_FRAMES];
     let mut buf = vec![0i16; WRITE_FRAMES * USB_AUDIO_CHANNELS];
 
     while !stop.load(Ordering::Relaxed) {
-        // Read the latest parameters each buffer so UI changes take effect live.
-        let (pattern, strength, speed) = *mlock(params);
-        for frame in 0..WRITE_FRAMES {
-            let env = envelope(pattern, strength, speed, t).clamp(0.0, 1.0);
-            let carrier = (t * HAPTIC_CARRIER_HZ * std::f32::consts::TAU).sin();
-            let v = (carrier * env * 32767.0).round().clamp(-32767.0, 32767.0) as i16;
+        // Render under the lock each buffer so UI changes take effect live.
+        let open = mlock(mix).render(t, &mut haptics, &mut speaker);
+        for (frame, (h, s)) in haptics.iter().zip(&speaker).enumerate() {
             let base = frame * USB_AUDIO_CHANNELS;
-            buf[base] = 0; // speaker L
-            buf[base + 1] = 0; // speaker R
-            buf[base + HAPTIC_L_CHANNEL] = v;
-            buf[base + HAPTIC_R_CHANNEL] = v;
-            t += dt;
+            buf[base + SPEAKER_L_CHANNEL] = to_sample(s[0]);
+            buf[base + SPEAKER_R_CHANNEL] = to_sample(s[1]);
+            buf[base + HAPTIC_L_CHANNEL] = to_sample(h[0]);
+            buf[base + HAPTIC_R_CHANNEL] = to_sample(h[1]);
         }
+        t += WRITE_FRAMES as f32 / USB_AUDIO_RATE as f32;
         if let Err(e) = io.writei(&buf) {
             pcm.try_recover(e, true)?;
         }
+        if !open {
+            break;
+        }
     }
 
     for x in buf.iter_mut() {
@@ -105,7 +160,7 @@ fn run_stream(pcm: &PCM, stop: &AtomicBool, params: &Arc<Mutex<Params>>) -> Resu
 pub struct UsbHapticStream {
     stop: Option<Arc<AtomicBool>>,
     thread: Option<JoinHandle<()>>,
-    params: Arc<Mutex<Params>>,
+    mix: Arc<Mutex<Mix>>,
 }
 
 impl UsbHapticStream {
@@ -113,37 +168,123 @@ impl UsbHapticStream {
         Self {
             stop: None,
             thread: None,
-            params: Arc::new(Mutex::new((HapticPattern::None, 0, 1.0))),
+            mix: Arc::new(Mutex::new(Mix {
+                haptics: None,
+                rumble: None,
+                overlay: None,
+                speaker: None,
+                open: false,
+            })),
         }
     }
 
-    pub fn is_active(&self) -> bool {
+    fn running(&self) -> bool {
         self.thread.as_ref().is_some_and(|h| !h.is_finished())
     }
 
-    pub fn set_params(&self, pattern: HapticPattern, strength: u8, speed: f32) {
-        *mlock(&self.params) = (pattern, strength, speed);
+    pub fn is_active(&self) -> bool {
+        self.running() && mlock(&self.mix).haptics.is_some()
     }
 
-    pub fn start(&mut self, card: u32, pattern: HapticPattern, strength: u8, speed: f32) {
-        self.stop();
+    pub fn set_params(
+        &self,
+        pattern: HapticPattern,
+        strength: u8,
+        speed: f32,
+        balance: f32,
+        custom: Option<Arc<CustomPattern>>,
+    ) {
+        if let Some(source) = &mut mlock(&self.mix).haptics {
+            source.retune(pattern, strength, speed, balance, custom);
+        }
+    }
+
+    pub fn is_reactive(&self) -> bool {
+        self.running()
+            && mlock(&self.mix)
+                .haptics
+                .as_ref()
+                .is_some_and(|s| s.is_reactive())
+    }
+
+    pub fn update_reactive(&self, config: &ReactiveConfig) -> bool {
+        self.running()
+            && mlock(&self.mix)
+                .haptics
+                .as_ref()
+                .is_some_and(|s| s.update_reactive(config))
+    }
+
+    pub fn start(&mut self, card: u32, source: HapticSource) {
+        // A replaced reactive source joins its capture thread; drop it after the mix is unlocked.
+        let (spawn, old) = {
+            let mut mix = mlock(&self.mix);
+            let old = mix.haptics.replace(source);
+            (!std::mem::replace(&mut mix.open, true), old)
+        };
+        drop(old);
+        if spawn {
+            self.spawn(card);
+        }
+    }
 
-        *mlock(&self.params) = (pattern, strength, speed);
-        let params = Arc::clone(&self.params);
+    pub fn set_rumble(&mut self, card: u32, config: RumbleHaptics, strong: f32, weak: f32) {
+        let spawn = {
+            let mut mix = mlock(&self.mix);
+            if strong <= 0.0 && weak <= 0.0 {
+                mix.rumble = None;
+                return;
+            }
+            mix.rumble
+                .get_or_insert_with(RumbleVoice::default)
+                .set(config, strong, weak);
+            !std::mem::replace(&mut mix.open, true)
+        };
+        if spawn {
+            self.spawn(card);
+        }
+    }
+
+    pub fn play_overlay(&mut self, card: u32, player: ClipPlayer) {
+        let spawn = {
+            let mut mix = mlock(&self.mix);
+            mix.overlay = Some(player);
+            !std::mem::replace(&mut mix.open, true)
+        };
+        if spawn {
+            self.spawn(card);
+        }
+    }
 
+    pub fn play_sound(&mut self, card: u32, player: ClipPlayer) {
+        let spawn = {
+            let mut mix = mlock(&self.mix);
+            mix.speaker = Some(player);
+            !std::mem::replace(&mut mix.open, true)
+        };
+        if spawn {
+            self.spawn(card);
+        }
+    }
+
+    fn spawn(&mut self, card: u32) {
+        // The previous thread has already seen the mix go idle; wait for it to release the PCM.
+        self.join();
+
+        let mix = Arc::clone(&self.mix);
         let stop = Arc::new(AtomicBool::new(false));
         let stop_c = Arc::clone(&stop);
 
         let handle = thread::spawn(move || {
-            let pcm = match open_pcm(card) {
-                Ok(pcm) => pcm,
-                Err(e) => {
-                    eprintln!("[usb-haptics] open failed: {e}");
-                    return;
-                }
-            };
-            if let Err(e) = run_stream(&pcm, &stop_c, &params) {
+            let result = open_pcm(card).and_then(|pcm| run_stream(&pcm, &stop_c, &mix));
+            if let Err(e) = result {
                 eprintln!("[usb-haptics] stream ended: {e}");
+                let mut mix = mlock(&mix);
+                mix.haptics = None;
+                mix.rumble = None;
+                mix.overlay = None;
+                mix.speaker = None;
+                mix.open = false;
             }
         });
 
@@ -151,7 +292,7 @@ impl UsbHapticStream {
         self.thread = Some(handle);
     }
 
-    pub fn stop(&mut self) {
+    fn join(&mut self) {
         if let Some(stop) = self.stop.take() {
             stop.store(true, Ordering::Relaxed);
         }
@@ -159,6 +300,28 @@ impl UsbHapticStream {
             let _ = handle.join();
         }
     }
+
+    pub fn stop(&mut self) {
+        let old = mlock(&self.mix).haptics.take();
+        drop(old);
+    }
+
+    pub fn stop_sound(&mut self) {
+        mlock(&self.mix).speaker = None;
+    }
+
+    pub fn shutdown(&mut self) {
+        self.join();
+        let old = {
+            let mut mix = mlock(&self.mix);
+            mix.rumble = None;
+            mix.overlay = None;
+            mix.speaker = None;
+            mix.open = false;
+            mix.haptics.take()
+        };
+        drop(old);
+    }
 }
 
 impl Default for UsbHapticStream {
@@ -169,6 +332,6 @@ impl Default for UsbHapticStream {
 
 impl Drop for UsbHapticStream {
     fn drop(&mut self) {
-        self.stop();
+        self.shutdown();
     }
 }
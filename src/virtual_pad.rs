@@ -0,0 +1,371 @@
+use std::{
+    collections::HashMap,
+    ffi::{CString, c_char, c_int},
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    mem,
+    os::{fd::AsRawFd, unix::fs::OpenOptionsExt},
+    ptr, slice,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{common::DS_VID, inputs::*, util::mlock};
+
+const UINPUT_PATH: &str = "/dev/uinput";
+const FF_EFFECTS_MAX: u32 = 16;
+
+const IOC_NONE: u32 = 0;
+const IOC_WRITE: u32 = 1;
+const IOC_READ: u32 = 2;
+
+const fn uinput_ioc(dir: u32, nr: u32, size: usize) -> libc::Ioctl {
+    ((dir << 30) | ((size as u32) << 16) | ((b'U' as u32) << 8) | nr) as libc::Ioctl
+}
+
+const UI_DEV_CREATE: libc::Ioctl = uinput_ioc(IOC_NONE, 1, 0);
+const UI_DEV_DESTROY: libc::Ioctl = uinput_ioc(IOC_NONE, 2, 0);
+const UI_DEV_SETUP: libc::Ioctl = uinput_ioc(IOC_WRITE, 3, size_of::<libc::uinput_setup>());
+const UI_ABS_SETUP: libc::Ioctl = uinput_ioc(IOC_WRITE, 4, size_of::<libc::uinput_abs_setup>());
+const UI_SET_EVBIT: libc::Ioctl = uinput_ioc(IOC_WRITE, 100, size_of::<c_int>());
+const UI_SET_KEYBIT: libc::Ioctl = uinput_ioc(IOC_WRITE, 101, size_of::<c_int>());
+const UI_SET_ABSBIT: libc::Ioctl = uinput_ioc(IOC_WRITE, 103, size_of::<c_int>());
+const UI_SET_FFBIT: libc::Ioctl = uinput_ioc(IOC_WRITE, 107, size_of::<c_int>());
+const UI_SET_PHYS: libc::Ioctl = uinput_ioc(IOC_WRITE, 108, size_of::<*const c_char>());
+const UI_BEGIN_FF_UPLOAD: libc::Ioctl = uinput_ioc(
+    IOC_READ | IOC_WRITE,
+    200,
+    size_of::<libc::uinput_ff_upload>(),
+);
+const UI_END_FF_UPLOAD: libc::Ioctl =
+    uinput_ioc(IOC_WRITE, 201, size_of::<libc::uinput_ff_upload>());
+const UI_BEGIN_FF_ERASE: libc::Ioctl = uinput_ioc(
+    IOC_READ | IOC_WRITE,
+    202,
+    size_of::<libc::uinput_ff_erase>(),
+);
+const UI_END_FF_ERASE: libc::Ioctl = uinput_ioc(IOC_WRITE, 203, size_of::<libc::uinput_ff_erase>());
+
+const BUS_USB: u16 = 0x03;
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_ABS: u16 = 0x03;
+const EV_FF: u16 = 0x15;
+const EV_UINPUT: u16 = 0x0101;
+const SYN_REPORT: u16 = 0;
+const UI_FF_UPLOAD: u16 = 1;
+const UI_FF_ERASE: u16 = 2;
+const FF_RUMBLE: u16 = 0x50;
+const FF_GAIN: u16 = 0x60;
+
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const ABS_Z: u16 = 0x02;
+const ABS_RX: u16 = 0x03;
+const ABS_RY: u16 = 0x04;
+const ABS_RZ: u16 = 0x05;
+const ABS_HAT0X: u16 = 0x10;
+const ABS_HAT0Y: u16 = 0x11;
+
+// Same layout hid-playstation gives the real pad, so existing SDL mappings apply.
+const BUTTONS: [(u32, u16); 13] = [
+    (BTN_CROSS, 0x130),
+    (BTN_CIRCLE, 0x131),
+    (BTN_TRIANGLE, 0x133),
+    (BTN_SQUARE, 0x134),
+    (BTN_L1, 0x136),
+    (BTN_R1, 0x137),
+    (BTN_L2, 0x138),
+    (BTN_R2, 0x139),
+    (BTN_CREATE, 0x13a),
+    (BTN_OPTIONS, 0x13b),
+    (BTN_PS, 0x13c),
+    (BTN_L3, 0x13d),
+    (BTN_R3, 0x13e),
+];
+const STICK_AXES: [u16; 6] = [ABS_X, ABS_Y, ABS_Z, ABS_RX, ABS_RY, ABS_RZ];
+
+struct RumbleEffect {
+    strong: u16,
+    weak: u16,
+    length: Duration,
+    delay: Duration,
+}
+
+struct ForceFeedback {
+    effects: HashMap<i16, RumbleEffect>,
+    playing: HashMap<i16, (Instant, Option<Instant>)>,
+    gain: f32,
+}
+
+impl ForceFeedback {
+    fn play(&mut self, id: i16, count: i32) {
+        let Some(effect) = self.effects.get(&id).filter(|_| count > 0) else {
+            self.playing.remove(&id);
+            return;
+        };
+        let start = Instant::now() + effect.delay;
+        let end = (!effect.length.is_zero()).then(|| start + effect.length * count as u32);
+        self.playing.insert(id, (start, end));
+    }
+
+    fn level(&mut self) -> (f32, f32) {
+        let now = Instant::now();
+        self.playing
+            .retain(|_, (_, end)| end.is_none_or(|end| now < end));
+        let (mut strong, mut weak) = (0.0f32, 0.0f32);
+        for (id, (start, _)) in &self.playing {
+            if let Some(effect) = self.effects.get(id).filter(|_| now >= *start) {
+                strong += effect.strong as f32 / u16::MAX as f32;
+                weak += effect.weak as f32 / u16::MAX as f32;
+            }
+        }
+        (strong.min(1.0) * self.gain, weak.min(1.0) * self.gain)
+    }
+}
+
+pub struct VirtualPad {
+    file: File,
+    last: Mutex<Vec<(u16, u16, i32)>>,
+    ff: Mutex<ForceFeedback>,
+}
+
+impl VirtualPad {
+    pub fn create(name: &str, phys: &str, product_id: u16) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(UINPUT_PATH)
+            .with_context(|| format!("opening {UINPUT_PATH}"))?;
+        let pad = Self {
+            file,
+            last: Mutex::new(Vec::new()),
+            ff: Mutex::new(ForceFeedback {
+                effects: HashMap::new(),
+                playing: HashMap::new(),
+                gain: 1.0,
+            }),
+        };
+
+        for ev in [EV_KEY, EV_ABS, EV_FF] {
+            pad.ioctl_int(UI_SET_EVBIT, ev)?;
+        }
+        for (_, code) in BUTTONS {
+            pad.ioctl_int(UI_SET_KEYBIT, code)?;
+        }
+        for code in STICK_AXES {
+            pad.setup_abs(code, 0, 255)?;
+        }
+        for code in [ABS_HAT0X, ABS_HAT0Y] {
+            pad.setup_abs(code, -1, 1)?;
+        }
+        for code in [FF_RUMBLE, FF_GAIN] {
+            pad.ioctl_int(UI_SET_FFBIT, code)?;
+        }
+        let phys = CString::new(phys).context("virtual pad phys")?;
+        let r = unsafe { libc::ioctl(pad.file.as_raw_fd(), UI_SET_PHYS, phys.as_ptr()) };
+        if r < 0 {
+            return Err(io::Error::last_os_error()).context("UI_SET_PHYS");
+        }
+
+        let mut setup: libc::uinput_setup = unsafe { mem::zeroed() };
+        setup.id = libc::input_id {
+            bustype: BUS_USB,
+            vendor: DS_VID,
+            product: product_id,
+            version: 1,
+        };
+        for (dst, src) in setup
+            .name
+            .iter_mut()
+            .zip(name.bytes().take(libc::UINPUT_MAX_NAME_SIZE - 1))
+        {
+            *dst = src as libc::c_char;
+        }
+        setup.ff_effects_max = FF_EFFECTS_MAX;
+        pad.ioctl_ptr(UI_DEV_SETUP, &mut setup)
+            .context("UI_DEV_SETUP")?;
+        pad.ioctl_int(UI_DEV_CREATE, 0).context("UI_DEV_CREATE")?;
+        Ok(pad)
+    }
+
+    fn ioctl_int(&self, request: libc::Ioctl, value: u16) -> io::Result<()> {
+        let r = unsafe { libc::ioctl(self.file.as_raw_fd(), request, value as c_int) };
+        if r < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn ioctl_ptr<T>(&self, request: libc::Ioctl, arg: &mut T) -> io::Result<()> {
+        let r = unsafe { libc::ioctl(self.file.as_raw_fd(), request, arg as *mut T) };
+        if r < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn setup_abs(&self, code: u16, minimum: i32, maximum: i32) -> io::Result<()> {
+        self.ioctl_int(UI_SET_ABSBIT, code)?;
+        let mut abs: libc::uinput_abs_setup = unsafe { mem::zeroed() };
+        abs.code = code;
+        abs.absinfo.minimum = minimum;
+        abs.absinfo.maximum = maximum;
+        abs.absinfo.value = (minimum + maximum) / 2;
+        self.ioctl_ptr(UI_ABS_SETUP, &mut abs)
+    }
+
+    pub fn emit(&self, state: &ControllerState) -> Result<()> {
+        let (hat_x, hat_y) = match state.dpad {
+            DPAD_N => (0, -1),
+            DPAD_NE => (1, -1),
+            DPAD_E => (1, 0),
+            DPAD_SE => (1, 1),
+            DPAD_S => (0, 1),
+            DPAD_SW => (-1, 1),
+            DPAD_W => (-1, 0),
+            DPAD_NW => (-1, -1),
+            _ => (0, 0),
+        };
+        let sticks = [
+            state.left_x,
+            state.left_y,
+            state.l2,
+            state.right_x,
+            state.right_y,
+            state.r2,
+        ];
+        let values: Vec<(u16, u16, i32)> = BUTTONS
+            .iter()
+            .map(|&(bit, code)| (EV_KEY, code, (state.buttons & bit != 0) as i32))
+            .chain(
+                STICK_AXES
+                    .iter()
+                    .zip(sticks)
+                    .map(|(&code, v)| (EV_ABS, code, v as i32)),
+            )
+            .chain([(EV_ABS, ABS_HAT0X, hat_x), (EV_ABS, ABS_HAT0Y, hat_y)])
+            .collect();
+
+        let mut last = mlock(&self.last);
+        let mut events: Vec<libc::input_event> = values
+            .iter()
+            .enumerate()
+            .filter(|&(i, v)| last.get(i) != Some(v))
+            .map(|(_, &(type_, code, value))| input_event(type_, code, value))
+            .collect();
+        if events.is_empty() {
+            return Ok(());
+        }
+        events.push(input_event(EV_SYN, SYN_REPORT, 0));
+        let bytes = unsafe {
+            slice::from_raw_parts(
+                events.as_ptr().cast::<u8>(),
+                events.len() * size_of::<libc::input_event>(),
+            )
+        };
+        (&self.file).write_all(bytes)?;
+        *last = values;
+        Ok(())
+    }
+
+    // Force-feedback requests block the game until answered, so call this often.
+    pub fn wait_ff(&self, timeout: Duration) -> Result<()> {
+        let mut pfd = libc::pollfd {
+            fd: self.file.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        if unsafe { libc::poll(&mut pfd, 1, timeout.as_millis() as c_int) } <= 0 {
+            return Ok(());
+        }
+
+        const EVENT_SIZE: usize = size_of::<libc::input_event>();
+        let mut buf = [0u8; EVENT_SIZE * 16];
+        loop {
+            let n = match (&self.file).read(&mut buf) {
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e.into()),
+            };
+            for chunk in buf[..n].chunks_exact(EVENT_SIZE) {
+                let ev: libc::input_event = unsafe { ptr::read_unaligned(chunk.as_ptr().cast()) };
+                match (ev.type_, ev.code) {
+                    (EV_UINPUT, UI_FF_UPLOAD) => self.upload(ev.value as u32)?,
+                    (EV_UINPUT, UI_FF_ERASE) => self.erase(ev.value as u32)?,
+                    (EV_FF, FF_GAIN) => {
+                        mlock(&self.ff).gain = ev.value.clamp(0, 0xffff) as f32 / 0xffff as f32
+                    }
+                    (EV_FF, id) => mlock(&self.ff).play(id as i16, ev.value),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn upload(&self, request_id: u32) -> Result<()> {
+        let mut upload: libc::uinput_ff_upload = unsafe { mem::zeroed() };
+        upload.request_id = request_id;
+        self.ioctl_ptr(UI_BEGIN_FF_UPLOAD, &mut upload)
+            .context("UI_BEGIN_FF_UPLOAD")?;
+        let effect = &upload.effect;
+        if effect.type_ == FF_RUMBLE {
+            let rumble: libc::ff_rumble_effect =
+                unsafe { ptr::read_unaligned(effect.u.as_ptr().cast()) };
+            mlock(&self.ff).effects.insert(
+                effect.id,
+                RumbleEffect {
+                    strong: rumble.strong_magnitude,
+                    weak: rumble.weak_magnitude,
+                    length: Duration::from_millis(effect.replay.length as u64),
+                    delay: Duration::from_millis(effect.replay.delay as u64),
+                },
+            );
+            upload.retval = 0;
+        } else {
+            upload.retval = -libc::EINVAL;
+        }
+        self.ioctl_ptr(UI_END_FF_UPLOAD, &mut upload)
+            .context("UI_END_FF_UPLOAD")?;
+        Ok(())
+    }
+
+    fn erase(&self, request_id: u32) -> Result<()> {
+        let mut erase: libc::uinput_ff_erase = unsafe { mem::zeroed() };
+        erase.request_id = request_id;
+        self.ioctl_ptr(UI_BEGIN_FF_ERASE, &mut erase)
+            .context("UI_BEGIN_FF_ERASE")?;
+        {
+            let mut ff = mlock(&self.ff);
+            let id = erase.effect_id as i16;
+            ff.effects.remove(&id);
+            ff.playing.remove(&id);
+        }
+        erase.retval = 0;
+        self.ioctl_ptr(UI_END_FF_ERASE, &mut erase)
+            .context("UI_END_FF_ERASE")?;
+        Ok(())
+    }
+
+    /// Current (strong, weak) motor levels the game asked for, 0.0..=1.0.
+    pub fn rumble(&self) -> (f32, f32) {
+        mlock(&self.ff).level()
+    }
+}
+
+impl Drop for VirtualPad {
+    fn drop(&mut self) {
+        let _ = self.ioctl_int(UI_DEV_DESTROY, 0);
+    }
+}
+
+fn input_event(type_: u16, code: u16, value: i32) -> libc::input_event {
+    let mut ev: libc::input_event = unsafe { mem::zeroed() };
+    ev.type_ = type_;
+    ev.code = code;
+    ev.value = value;
+    ev
+}